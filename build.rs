@@ -0,0 +1,14 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "xtb")]
+    link_xtb();
+}
+
+/// Link the xtb library, located by pkg-config if it provides `xtb.pc`, or else
+/// searched by the linker in its default paths.
+#[cfg(feature = "xtb")]
+fn link_xtb() {
+    if pkg_config::probe_library("xtb").is_err() {
+        println!("cargo:rustc-link-lib=xtb");
+    }
+}
@@ -5,8 +5,8 @@ use pyo3::prelude::*;
 pub struct BlankDriver {}
 
 impl GeomDriverAPI for BlankDriver {
-    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> GradOutput {
-        GradOutput { energy: 0.0, gradient: vec![0.0; coords.len()] }
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        Ok(GradOutput::new(0.0, vec![0.0; coords.len()]))
     }
 }
 
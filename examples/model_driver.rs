@@ -53,8 +53,8 @@ pub struct ModelDriver<'a> {
 ///
 /// You need to implement this trait for your driver.
 impl GeomDriverAPI for ModelDriver<'_> {
-    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> GradOutput {
-        self.model.calc_eng_grad(coords)
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        Ok(self.model.calc_eng_grad(coords))
     }
 }
 
@@ -113,56 +113,50 @@ fn main_test() -> PyResult<()> {
     // As previously mentioned, it is better to use reference `&model` or mutable
     // reference `&mut model` here. After optimization, you may still retrieve the
     // `model` object.
-    let driver = ModelDriver { model: &mut model };
-    // The following step is also required. It will convert `ModelDriver` to python
-    // side.
-    let driver: PyGeomDriver = driver.into();
-
-    Python::with_gil(|py| -> PyResult<()> {
-        // The following three lines will perform the optimization.
-        // 1. Create a new instance of `PyO3Engine` class.
-        // 2. Set the driver to the engine.
-        // 3. Run the optimization.
-        let custom_engine = pyo3_engine_cls.call1(py, (molecule,))?;
-        custom_engine.call_method1(py, "set_driver", (driver,))?;
-        let res = run_optimization(custom_engine, &params, input)?;
-
-        // You can retrieve the optimization result from `res` object.
-        // This is the same to python code
-        // `list(res.xyzs[-1].flatten())`.
-        // The returned coordinates are in Angstrom.
-        //
-        // `res.xyzs` is actually a list of coordinates, showing the trajectory of
-        // optimization.
-        //
-        // We currently do not implement such kind of post-processing codes in rust
-        // side. User may handle those post-processing by themselves.
-        let coords = res
-            .getattr(py, "xyzs")?
-            .call_method1(py, "__getitem__", (-1,))?
-            .call_method0(py, "flatten")?
-            .call_method0(py, "tolist")?
-            .extract::<Vec<f64>>(py)?;
-        println!("Optimized Coordinates (Angstrom): {:?}", coords);
-
-        // You can also retrieve the energy from `res` object.
-        // This is the same to python code
-        // `res.qm_energies[-1]`.
-        // The returned energy is in Hartree.
-        //
-        // `res.qm_energies` also shows the trajectory of optimization.
-        // The last energy is the optimized energy.
-        let energy = res
-            .getattr(py, "qm_energies")?
-            .call_method1(py, "__getitem__", (-1,))?
-            .extract::<f64>(py)?;
-        println!("Optimized Energy (Eh): {:?}", energy);
-
-        // For this specific case, energy should be close to 0.32 Eh for transition
-        // state.
-        assert!((energy - 0.32).abs() < 1.0e-8);
-
-        Ok(())
+    let mut driver = ModelDriver { model: &mut model };
+    // The following step is also required. It will lend `ModelDriver` to python
+    // side, only during the closure; so `model` is safely usable afterwards.
+    with_driver(&mut driver, |driver| {
+        Python::with_gil(|py| -> PyResult<()> {
+            // The following three lines will perform the optimization.
+            // 1. Create a new instance of `PyO3Engine` class.
+            // 2. Set the driver to the engine.
+            // 3. Run the optimization.
+            let custom_engine = pyo3_engine_cls.call1(py, (molecule,))?;
+            custom_engine.call_method1(py, "set_driver", (driver.clone(),))?;
+            let res = run_optimization(custom_engine, &params, input)?;
+
+            // `res` is an `OptimizationOutcome`. For normal runs it is `Converged`; the
+            // trajectory of optimization is stored in the inner `OptResult`.
+            assert!(res.is_converged());
+            let res = res.into_result();
+
+            // You can retrieve the optimized coordinates from `res` object.
+            // This is the same to python code
+            // `list(progress.xyzs[-1].flatten())`.
+            // The returned coordinates are in Angstrom.
+            //
+            // `res.xyzs` is actually a list of coordinates, showing the trajectory of
+            // optimization.
+            let coords = res.final_coords().unwrap();
+            println!("Optimized Coordinates (Angstrom): {:?}", coords);
+
+            // You can also retrieve the energy from `res` object.
+            // This is the same to python code
+            // `progress.qm_energies[-1]`.
+            // The returned energy is in Hartree.
+            //
+            // `res.energies` also shows the trajectory of optimization.
+            // The last energy is the optimized energy.
+            let energy = res.final_energy().unwrap();
+            println!("Optimized Energy (Eh): {:?}", energy);
+
+            // For this specific case, energy should be close to 0.32 Eh for transition
+            // state.
+            assert!((energy - 0.32).abs() < 1.0e-8);
+
+            Ok(())
+        })
     })?;
 
     // You can also retrieve the optimized coordinates and energy from the original
@@ -245,7 +239,7 @@ impl Model {
 
         self.current_energy = Some(energy);
 
-        GradOutput { energy, gradient }
+        GradOutput::new(energy, gradient)
     }
 }
 
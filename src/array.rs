@@ -0,0 +1,109 @@
+//! Interop with `ndarray` (feature `ndarray`).
+//!
+//! Coordinates and gradients are viewed as arrays of shape (natom, 3), and
+//! trajectories as arrays of shape (nframe, natom, 3), instead of the flattened
+//! vectors used elsewhere in this crate.
+
+use ndarray::{Array2, Array3, ArrayView2};
+
+use crate::interface::{AtomData, DriverError, GeomDriverAPI, GradOutput};
+use crate::result::OptResult;
+
+impl GradOutput {
+    /// Create output from energy (Hartree) and gradient array (Hartree/Bohr)
+    /// of shape (natom, 3).
+    pub fn from_array(energy: f64, gradient: Array2<f64>) -> Self {
+        let gradient = match gradient.is_standard_layout() {
+            true => gradient.into_raw_vec_and_offset().0,
+            false => gradient.iter().copied().collect(),
+        };
+        GradOutput::new(energy, gradient)
+    }
+}
+
+/// Variant of [`GeomDriverAPI`] taking coordinates as array.
+///
+/// Wrap the driver in [`ArrayDriver`] to use it as a [`GeomDriverAPI`].
+pub trait ArrayGeomDriverAPI: Send {
+    /// Calculate the energy and gradient of the system.
+    ///
+    /// `coords` are in Bohr, of shape (natom, 3); other arguments are the same
+    /// to [`GeomDriverAPI::calc_new`].
+    fn calc_new(
+        &mut self,
+        coords: ArrayView2<f64>,
+        dirname: &str,
+    ) -> Result<GradOutput, DriverError>;
+
+    /// See [`GeomDriverAPI::set_atom_data`].
+    fn set_atom_data(&mut self, _atom_data: &AtomData) {}
+}
+
+/// Adapter to use an [`ArrayGeomDriverAPI`] as [`GeomDriverAPI`].
+pub struct ArrayDriver<T: ArrayGeomDriverAPI>(pub T);
+
+impl<T: ArrayGeomDriverAPI> GeomDriverAPI for ArrayDriver<T> {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        let coords = ArrayView2::from_shape((coords.len() / 3, 3), coords).map_err(|e| {
+            DriverError::CalculationFailed(format!("Invalid coordinates shape: {}", e))
+        })?;
+        self.0.calc_new(coords, dirname)
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.0.set_atom_data(atom_data);
+    }
+}
+
+impl OptResult {
+    /// Coordinates of all frames (Angstrom) as array of shape (nframe, natom,
+    /// 3).
+    pub fn trajectory_array(&self) -> Array3<f64> {
+        let natom = self.elem.len();
+        let flat = self.xyzs.iter().flatten().copied().collect::<Vec<_>>();
+        Array3::from_shape_vec((self.xyzs.len(), natom, 3), flat)
+            .expect("frames of inconsistent number of atoms")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::array;
+
+    struct Harmonic;
+
+    impl ArrayGeomDriverAPI for Harmonic {
+        fn calc_new(
+            &mut self,
+            coords: ArrayView2<f64>,
+            _dirname: &str,
+        ) -> Result<GradOutput, DriverError> {
+            let energy = coords.iter().map(|x| x * x).sum();
+            Ok(GradOutput::from_array(energy, coords.mapv(|x| 2.0 * x)))
+        }
+    }
+
+    #[test]
+    fn test_array_driver() {
+        let mut driver = ArrayDriver(Harmonic);
+        let output = driver.calc_new(&[1.0, 0.0, 0.0, 0.0, 2.0, 0.0], "").unwrap();
+        assert_eq!(output.energy, 5.0);
+        assert_eq!(output.gradient, vec![2.0, 0.0, 0.0, 0.0, 4.0, 0.0]);
+
+        let transposed = array![[1.0, 3.0], [2.0, 4.0]].reversed_axes();
+        assert_eq!(GradOutput::from_array(0.0, transposed).gradient, vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_trajectory_array() {
+        let result = OptResult::new(
+            vec!["H".into(), "H".into()],
+            vec![vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.75], vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.74]],
+            vec![-1.16, -1.17],
+        );
+        let traj = result.trajectory_array();
+        assert_eq!(traj.shape(), &[2, 2, 3]);
+        assert_eq!(traj[[1, 1, 2]], 0.74);
+    }
+}
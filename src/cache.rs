@@ -0,0 +1,179 @@
+//! Memoization of driver evaluations.
+//!
+//! geomeTRIC occasionally evaluates the same geometry again (e.g. returning to
+//! the previous geometry after a rejected step). [`CachedDriver`] returns the
+//! stored [`GradOutput`] in that case, instead of repeating an expensive
+//! calculation.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::interface::{
+    AtomData, DriverError, GeomDriverAPI, GradOutput, HessOutput, WorkQueueJob,
+};
+use crate::params::CoordSys;
+
+/// Driver wrapper caching gradients by coordinates.
+///
+/// - `driver`: The wrapped driver.
+/// - `tol`: Coordinates (Bohr) are hashed after rounding to multiples of `tol`
+///   (default 1e-8); geometries rounding to the same multiples (thus differing
+///   by less than `tol`) are considered equal. Evaluations fail unless `tol` is
+///   finite and positive.
+/// - `capacity`: Maximum number of stored results; the oldest are dropped
+///   first. Unlimited if `None` (default).
+///
+/// Cached results are returned without calling the driver, so nothing is
+/// written to `dirname`. Failed evaluations are not cached; Hessians are not
+/// cached. The cache is cleared when atom data is set.
+pub struct CachedDriver<D: GeomDriverAPI> {
+    pub driver: D,
+    pub tol: f64,
+    pub capacity: Option<usize>,
+    entries: HashMap<Vec<i64>, GradOutput>,
+    order: VecDeque<Vec<i64>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<D: GeomDriverAPI> CachedDriver<D> {
+    pub fn new(driver: D) -> Self {
+        CachedDriver {
+            driver,
+            tol: 1e-8,
+            capacity: None,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Tolerance of coordinates (Bohr).
+    pub fn tol(mut self, tol: f64) -> Self {
+        self.tol = tol;
+        self
+    }
+
+    /// Maximum number of stored results.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = Some(capacity);
+        self
+    }
+
+    /// Number of evaluations answered from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of evaluations passed to the driver.
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Drop all stored results.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Take the wrapped driver.
+    pub fn into_inner(self) -> D {
+        self.driver
+    }
+
+    fn key(&self, coords: &[f64]) -> Vec<i64> {
+        coords.iter().map(|x| (x / self.tol).round() as i64).collect()
+    }
+}
+
+impl<D: GeomDriverAPI> GeomDriverAPI for CachedDriver<D> {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        if !(self.tol.is_finite() && self.tol > 0.0) {
+            return Err(DriverError::CalculationFailed(format!(
+                "Cache tolerance must be positive, got {}",
+                self.tol
+            )));
+        }
+        let key = self.key(coords);
+        if let Some(output) = self.entries.get(&key) {
+            self.hits += 1;
+            return Ok(output.clone());
+        }
+        self.misses += 1;
+        let output = self.driver.calc_new(coords, dirname)?;
+        if self.capacity == Some(0) {
+            return Ok(output);
+        }
+        if self.capacity.is_some_and(|capacity| self.entries.len() >= capacity) {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.entries.insert(key, output.clone());
+        Ok(output)
+    }
+
+    fn calc_hessian(&mut self, coords: &[f64], dirname: &str) -> Option<HessOutput> {
+        self.driver.calc_hessian(coords, dirname)
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.clear();
+        self.driver.set_atom_data(atom_data);
+    }
+
+    fn prepare_wq_job(
+        &mut self,
+        coords: &[f64],
+        dirname: &str,
+    ) -> Result<Option<WorkQueueJob>, DriverError> {
+        self.driver.prepare_wq_job(coords, dirname)
+    }
+
+    fn read_wq_result(&mut self, dirname: &str) -> Result<GradOutput, DriverError> {
+        self.driver.read_wq_result(dirname)
+    }
+
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        self.driver.preferred_coordsys()
+    }
+
+    fn finalize(&mut self) {
+        self.driver.finalize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_driver() {
+        let mut calls = 0;
+        let driver = |coords: &[f64], _: &str| {
+            calls += 1;
+            GradOutput::new(
+                coords.iter().map(|x| x * x).sum(),
+                coords.iter().map(|x| 2.0 * x).collect(),
+            )
+        };
+        let mut driver = CachedDriver::new(driver).capacity(2);
+        let first = driver.calc_new(&[0.1, 0.2, 0.3], "").unwrap();
+        assert_eq!(driver.calc_new(&[0.1, 0.2, 0.3 + 1e-12], "").unwrap(), first);
+        driver.calc_new(&[0.2, 0.2, 0.3], "").unwrap();
+        driver.calc_new(&[0.3, 0.2, 0.3], "").unwrap();
+        // The first geometry has been dropped
+        driver.calc_new(&[0.1, 0.2, 0.3], "").unwrap();
+        assert_eq!((driver.hits(), driver.misses()), (1, 4));
+        drop(driver);
+        assert_eq!(calls, 4);
+    }
+    #[test]
+    fn test_cached_driver_tol() {
+        let driver = |coords: &[f64], _: &str| GradOutput::new(0.0, coords.to_vec());
+        for tol in [0.0, -1e-8, f64::NAN] {
+            assert!(CachedDriver::new(driver).tol(tol).calc_new(&[0.1, 0.2, 0.3], "").is_err());
+        }
+    }
+}
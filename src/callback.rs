@@ -0,0 +1,70 @@
+//! Per-iteration callback of the optimization.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Information of one driver evaluation, passed to [`OptCallback`].
+///
+/// - `step`: Index of the evaluation (0 for the initial geometry).
+/// - `energy`: Energy in Hartree.
+/// - `grad_rms`: RMS atomic gradient in Hartree/Bohr (as geomeTRIC's `grms`).
+/// - `grad_max`: Maximum atomic gradient in Hartree/Bohr (`gmax`).
+/// - `disp_rms`, `disp_max`: RMS and maximum atomic displacement (Angstrom)
+///   from the previous evaluation (`drms`, `dmax`, without alignment); zero for
+///   the first evaluation.
+/// - `trust`: Trust radius of geomeTRIC's optimizer (as parameter `trust`), if
+///   the optimizer is known to the engine (steps of
+///   [`OptimizerSession`](crate::optimize::OptimizerSession)); `None` for
+///   [`run_optimization`](crate::optimize::run_optimization), as geomeTRIC does
+///   not pass its optimizer to engines.
+/// - `coords`: Coordinates in Angstrom, flattened (natom * 3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepInfo {
+    pub step: usize,
+    pub energy: f64,
+    pub grad_rms: f64,
+    pub grad_max: f64,
+    pub disp_rms: f64,
+    pub disp_max: f64,
+    pub trust: Option<f64>,
+    pub coords: Vec<f64>,
+}
+
+/// Callback invoked after every geomeTRIC iteration (driver evaluation).
+///
+/// Closures `FnMut(&StepInfo) + Send` implement this trait. Evaluations
+/// requested by geomeTRIC also include rejected steps, so the energy is not
+/// necessarily decreasing.
+pub trait OptCallback: Send {
+    fn on_step(&mut self, info: &StepInfo);
+}
+
+impl<F> OptCallback for F
+where
+    F: FnMut(&StepInfo) + Send,
+{
+    fn on_step(&mut self, info: &StepInfo) {
+        self(info)
+    }
+}
+
+/// Shared handle of a callback, usable in
+/// [`RunOptions::callback`](crate::optimize::RunOptions).
+#[derive(Clone)]
+pub struct CallbackHandle(pub Arc<Mutex<dyn OptCallback>>);
+
+impl CallbackHandle {
+    pub fn new(callback: impl OptCallback + 'static) -> Self {
+        CallbackHandle(Arc::new(Mutex::new(callback)))
+    }
+
+    pub(crate) fn on_step(&self, info: &StepInfo) {
+        self.0.lock().unwrap().on_step(info);
+    }
+}
+
+impl fmt::Debug for CallbackHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CallbackHandle")
+    }
+}
@@ -0,0 +1,148 @@
+//! Checkpoint and restart of optimizations.
+//!
+//! A [`Checkpoint`] is saved by an [`OptimizerSession`] (periodically with
+//! [`OptimizerSession::checkpoint_every`]) as a JSON file. After the job died,
+//! [`resume_optimization`] continues from it with a new driver.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::PyGeomDriver;
+use crate::molecule::MoleculeSpec;
+use crate::optimize::OptimizerSession;
+use crate::result::OptimizationOutcome;
+use crate::runtime::initialize_python;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+
+/// geomeTRIC's default of `maxiter`.
+const DEFAULT_MAXITER: usize = 300;
+
+/// State of an optimization.
+///
+/// - `elem`, `charge`, `mult`: Element symbols, total charge and spin
+///   multiplicity of the molecule.
+/// - `coords`: Current geometry (Angstrom, flattened natom * 3).
+/// - `energy`: Energy of the current geometry (Hartree), if evaluated.
+/// - `hessian`: Current approximate Hessian of the optimizer, transformed to
+///   Cartesian coordinates (Hartree/Bohr², flattened natom * 3 by natom * 3).
+/// - `internal_coordinates`: Descriptions of the primitive internal coordinates
+///   (e.g. `Distance 1-2`), for inspection; internal coordinates are rebuilt
+///   from `coords` on resume.
+/// - `step`: Number of optimization steps taken.
+/// - `params`: geomeTRIC parameters of the run, as JSON object.
+/// - `constraints`: Constraints in geomeTRIC's format, if any.
+/// - `checkpoint_every`: Checkpoint interval of the run, continued on resume.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub elem: Vec<String>,
+    pub charge: Option<i64>,
+    pub mult: Option<i64>,
+    pub coords: Vec<f64>,
+    pub energy: Option<f64>,
+    pub hessian: Option<Vec<f64>>,
+    pub internal_coordinates: Vec<String>,
+    pub step: usize,
+    pub params: String,
+    pub constraints: Option<String>,
+    pub checkpoint_every: Option<usize>,
+}
+
+impl Checkpoint {
+    /// Write the checkpoint as JSON, replacing `path` atomically.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(&tmp_path, path)
+    }
+
+    /// Read a checkpoint written by [`save`](Self::save).
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Continue the optimization saved in a checkpoint file.
+///
+/// The optimization restarts from the saved geometry and Hessian, with the
+/// saved parameters and constraints; `maxiter` counts the steps taken before
+/// the checkpoint. If the run saved checkpoints periodically, the resumed run
+/// continues to update the same file. The returned result only contains the
+/// trajectory of the resumed run.
+pub fn resume_optimization(
+    checkpoint_path: impl AsRef<Path>,
+    driver: &PyGeomDriver,
+) -> Result<OptimizationOutcome, GeometricError> {
+    let checkpoint_path = checkpoint_path.as_ref();
+    let checkpoint = Checkpoint::load(checkpoint_path)?;
+    initialize_python();
+    let molecule = MoleculeSpec {
+        elem: checkpoint.elem.clone(),
+        xyzs: vec![checkpoint.coords.clone()],
+        charge: checkpoint.charge,
+        mult: checkpoint.mult,
+        ..Default::default()
+    }
+    .build()?;
+    let custom_engine = init_pyo3_engine(&molecule.pyobj, driver)?;
+    // The remaining steps are run with reduced `maxiter`; the original
+    // parameters are kept for further checkpoints.
+    let (params, remaining_params) = Python::with_gil(|py| -> PyResult<_> {
+        let params = py.import("json")?.call_method1("loads", (&checkpoint.params,))?;
+        let params = params.downcast_into::<PyDict>()?;
+        let maxiter = match params.get_item("maxiter")? {
+            Some(maxiter) => maxiter.extract::<usize>()?,
+            None => DEFAULT_MAXITER,
+        };
+        let remaining_params = params.copy()?;
+        remaining_params.set_item("maxiter", maxiter.saturating_sub(checkpoint.step).max(1))?;
+        Ok((params.unbind(), remaining_params.unbind()))
+    })?;
+
+    let mut session = OptimizerSession::with_constraint_string(
+        custom_engine,
+        &remaining_params,
+        checkpoint.constraints.clone(),
+    )?;
+    session.set_resumed(checkpoint.step, params);
+    if let Some(hessian) = checkpoint.hessian {
+        session = session.initial_hessian(hessian);
+    }
+    if let Some(every) = checkpoint.checkpoint_every {
+        session = session.checkpoint_every(checkpoint_path, every);
+    }
+    while !session.step()? {}
+    Ok(OptimizationOutcome::Converged(session.result()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let checkpoint = Checkpoint {
+            elem: vec!["H".into(), "H".into()],
+            charge: Some(0),
+            mult: Some(1),
+            coords: vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.74],
+            energy: Some(-1.17),
+            hessian: None,
+            internal_coordinates: vec!["Distance 1-2".into()],
+            step: 4,
+            params: r#"{"coordsys": "tric"}"#.into(),
+            constraints: None,
+            checkpoint_every: Some(2),
+        };
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("opt.chk");
+        checkpoint.save(&path).unwrap();
+        assert_eq!(Checkpoint::load(&path).unwrap(), checkpoint);
+    }
+}
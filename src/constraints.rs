@@ -0,0 +1,347 @@
+//! Constraints of geometry optimization.
+//!
+//! This builds the text of geomeTRIC's constraints file. Atom indices in this
+//! module are 0-based (as in rust), and are converted to 1-based indices of
+//! geomeTRIC when serialized.
+//!
+//! Units of target values follow geomeTRIC: Angstrom for distances, degree for
+//! angles and dihedrals.
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Internal coordinate referenced by a constraint (0-based atom indices).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintCoord {
+    Distance(usize, usize),
+    Angle(usize, usize, usize),
+    Dihedral(usize, usize, usize, usize),
+}
+
+impl ConstraintCoord {
+    /// Keyword of this coordinate in geomeTRIC constraints file.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ConstraintCoord::Distance(..) => "distance",
+            ConstraintCoord::Angle(..) => "angle",
+            ConstraintCoord::Dihedral(..) => "dihedral",
+        }
+    }
+
+    /// Atom indices (0-based) of this coordinate.
+    pub fn atoms(&self) -> Vec<usize> {
+        match *self {
+            ConstraintCoord::Distance(a, b) => vec![a, b],
+            ConstraintCoord::Angle(a, b, c) => vec![a, b, c],
+            ConstraintCoord::Dihedral(a, b, c, d) => vec![a, b, c, d],
+        }
+    }
+
+    /// Check atom indices against number of atoms.
+    pub fn validate(&self, natom: usize) -> PyResult<()> {
+        let atoms = self.atoms();
+        if let Some(&idx) = atoms.iter().find(|&&idx| idx >= natom) {
+            return Err(PyValueError::new_err(format!(
+                "Atom index {} of {} constraint out of range for {} atoms",
+                idx,
+                self.keyword(),
+                natom
+            )));
+        }
+        if (1..atoms.len()).any(|i| atoms[..i].contains(&atoms[i])) {
+            return Err(PyValueError::new_err(format!(
+                "Duplicated atom indices {:?} in {} constraint",
+                atoms,
+                self.keyword()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Value of this coordinate at coordinates `xyz` (Angstrom, flattened
+    /// natom * 3), in Angstrom or degree. Dihedrals are in (-180, 180].
+    pub fn value(&self, xyz: &[f64]) -> f64 {
+        let pos = |idx: usize| [xyz[3 * idx], xyz[3 * idx + 1], xyz[3 * idx + 2]];
+        match *self {
+            ConstraintCoord::Distance(a, b) => norm(sub(pos(b), pos(a))),
+            ConstraintCoord::Angle(a, b, c) => {
+                let (u, v) = (sub(pos(a), pos(b)), sub(pos(c), pos(b)));
+                (dot(u, v) / (norm(u) * norm(v))).clamp(-1.0, 1.0).acos().to_degrees()
+            },
+            ConstraintCoord::Dihedral(a, b, c, d) => {
+                let (b1, b2, b3) = (sub(pos(b), pos(a)), sub(pos(c), pos(b)), sub(pos(d), pos(c)));
+                let (n1, n2) = (cross(b1, b2), cross(b2, b3));
+                (norm(b2) * dot(b1, n2)).atan2(dot(n1, n2)).to_degrees()
+            },
+        }
+    }
+
+    /// Whether the coordinate is a dihedral, whose values are periodic.
+    fn is_dihedral(&self) -> bool {
+        matches!(self, ConstraintCoord::Dihedral(..))
+    }
+
+    /// geomeTRIC text of this coordinate (keyword and 1-based indices).
+    fn to_geometric(self) -> String {
+        let atoms = self.atoms().iter().map(|idx| (idx + 1).to_string()).collect::<Vec<_>>();
+        format!("{} {}", self.keyword(), atoms.join(" "))
+    }
+}
+
+/// Scan of a coordinate from `start` to `end` (inclusive) in `steps` points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanSpec {
+    pub coord: ConstraintCoord,
+    pub start: f64,
+    pub end: f64,
+    pub steps: usize,
+}
+
+impl ScanSpec {
+    /// Values of the scan points, evenly spaced from `start` to `end`.
+    pub fn values(&self) -> Vec<f64> {
+        match self.steps {
+            0 => vec![],
+            1 => vec![self.start],
+            n => (0..n)
+                .map(|i| self.start + (self.end - self.start) * i as f64 / (n - 1) as f64)
+                .collect(),
+        }
+    }
+}
+
+/// How well a `$set` constraint is satisfied at a geometry.
+///
+/// - `description`: Constraint in geomeTRIC's format without the target value
+///   (1-based indices), e.g. `distance 1 2`.
+/// - `target`: Target value (Angstrom or degree).
+/// - `achieved`: Value at the geometry.
+/// - `deviation`: `achieved - target`; for dihedrals, wrapped into (-180, 180]
+///   degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintReport {
+    pub description: String,
+    pub target: f64,
+    pub achieved: f64,
+    pub deviation: f64,
+}
+
+/// Builder of geomeTRIC constraints.
+///
+/// - `$freeze` constraints keep the coordinate at its initial value; Cartesian
+///   blocks freeze all coordinates of the given atoms;
+/// - `$set` constraints drive the coordinate to the given value;
+/// - `$scan` constraints make geomeTRIC run a constrained optimization at each
+///   scan point by itself. The result of
+///   [`run_optimization`](crate::optimize::run_optimization) then only covers
+///   geomeTRIC's returned trajectory; use [`run_scan`](crate::scan::run_scan)
+///   for typed results of every scan point.
+///
+/// Bonds are given as [`ConstraintCoord::Distance`]. Constraints are passed to
+/// geomeTRIC by [`RunOptions::constraints`](crate::optimize::RunOptions).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Constraints {
+    freeze: Vec<ConstraintCoord>,
+    freeze_cartesian: Vec<usize>,
+    set: Vec<(ConstraintCoord, f64)>,
+    scan: Vec<ScanSpec>,
+}
+
+impl Constraints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Freeze an internal coordinate at its initial value.
+    pub fn freeze(mut self, coord: ConstraintCoord) -> Self {
+        self.freeze.push(coord);
+        self
+    }
+
+    /// Freeze Cartesian coordinates of atoms (0-based indices).
+    pub fn freeze_cartesian(mut self, atoms: &[usize]) -> Self {
+        self.freeze_cartesian.extend_from_slice(atoms);
+        self
+    }
+
+    /// Scan an internal coordinate from `start` to `end` (Angstrom or degree,
+    /// inclusive) in `steps` points.
+    pub fn scan(mut self, coord: ConstraintCoord, start: f64, end: f64, steps: usize) -> Self {
+        self.scan.push(ScanSpec { coord, start, end, steps });
+        self
+    }
+
+    /// Constrain an internal coordinate to a value (Angstrom or degree).
+    pub fn set(mut self, coord: ConstraintCoord, value: f64) -> Self {
+        self.set.push((coord, value));
+        self
+    }
+
+    /// Whether no constraint has been added.
+    pub fn is_empty(&self) -> bool {
+        self.freeze.is_empty()
+            && self.freeze_cartesian.is_empty()
+            && self.set.is_empty()
+            && self.scan.is_empty()
+    }
+
+    /// Check all constraints against number of atoms.
+    pub fn validate(&self, natom: usize) -> PyResult<()> {
+        self.freeze.iter().try_for_each(|coord| coord.validate(natom))?;
+        for (coord, value) in &self.set {
+            coord.validate(natom)?;
+            if !value.is_finite() {
+                return Err(PyValueError::new_err(format!(
+                    "Target value of {} constraint must be finite",
+                    coord.keyword()
+                )));
+            }
+        }
+        if let Some(&idx) = self.freeze_cartesian.iter().find(|&&idx| idx >= natom) {
+            return Err(PyValueError::new_err(format!(
+                "Atom index {} of Cartesian constraint out of range for {} atoms",
+                idx, natom
+            )));
+        }
+        for scan in &self.scan {
+            scan.coord.validate(natom)?;
+            if !(scan.start.is_finite() && scan.end.is_finite()) || scan.steps < 2 {
+                return Err(PyValueError::new_err(format!(
+                    "Scan of {} constraint must have finite range and at least 2 steps",
+                    scan.coord.keyword()
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Report of `$set` constraints at coordinates `xyz` (Angstrom, flattened
+    /// natom * 3), in the order they were added. `$freeze` and `$scan`
+    /// constraints are not reported.
+    pub fn report(&self, xyz: &[f64]) -> PyResult<Vec<ConstraintReport>> {
+        self.validate(xyz.len() / 3)?;
+        let report = self.set.iter().map(|&(coord, target)| {
+            let achieved = coord.value(xyz);
+            let deviation = match coord.is_dihedral() {
+                true => 180.0 - (180.0 - (achieved - target)).rem_euclid(360.0),
+                false => achieved - target,
+            };
+            ConstraintReport { description: coord.to_geometric(), target, achieved, deviation }
+        });
+        Ok(report.collect())
+    }
+
+    /// Validate and serialize to the text of geomeTRIC constraints file.
+    pub fn to_geometric_string(&self, natom: usize) -> PyResult<String> {
+        self.validate(natom)?;
+        let mut lines = vec![];
+        if !self.freeze.is_empty() || !self.freeze_cartesian.is_empty() {
+            lines.push("$freeze".to_string());
+            lines.extend(self.freeze.iter().map(|coord| coord.to_geometric()));
+            if !self.freeze_cartesian.is_empty() {
+                lines.push(format!("xyz {}", atom_ranges(&self.freeze_cartesian)));
+            }
+        }
+        if !self.set.is_empty() {
+            lines.push("$set".to_string());
+            lines.extend(
+                self.set.iter().map(|(coord, value)| format!("{} {}", coord.to_geometric(), value)),
+            );
+        }
+        if !self.scan.is_empty() {
+            lines.push("$scan".to_string());
+            lines.extend(self.scan.iter().map(|scan| {
+                format!("{} {} {} {}", scan.coord.to_geometric(), scan.start, scan.end, scan.steps)
+            }));
+        }
+        Ok(lines.join("\n") + "\n")
+    }
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// geomeTRIC text of atom ranges (1-based), e.g. `1-3,5`.
+fn atom_ranges(atoms: &[usize]) -> String {
+    let mut atoms = atoms.to_vec();
+    atoms.sort_unstable();
+    atoms.dedup();
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for idx in atoms {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == idx => *end = idx,
+            _ => ranges.push((idx, idx)),
+        }
+    }
+    let ranges = ranges.iter().map(|&(start, end)| match start == end {
+        true => format!("{}", start + 1),
+        false => format!("{}-{}", start + 1, end + 1),
+    });
+    ranges.collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConstraintCoord::*;
+    use super::*;
+
+    #[test]
+    fn test_constraints_string() {
+        let constraints = Constraints::new().freeze(Dihedral(0, 1, 2, 3)).set(Distance(0, 1), 1.5);
+        let string = constraints.to_geometric_string(4).unwrap();
+        assert_eq!(string, "$freeze\ndihedral 1 2 3 4\n$set\ndistance 1 2 1.5\n");
+    }
+
+    #[test]
+    fn test_cartesian_and_scan() {
+        let constraints =
+            Constraints::new().freeze_cartesian(&[4, 0, 1, 2]).scan(Distance(0, 1), 1.0, 2.0, 11);
+        let string = constraints.to_geometric_string(5).unwrap();
+        assert_eq!(string, "$freeze\nxyz 1-3,5\n$scan\ndistance 1 2 1 2 11\n");
+        assert!(Constraints::new().scan(Distance(0, 1), 1.0, 2.0, 1).validate(5).is_err());
+    }
+
+    #[test]
+    fn test_constraints_validate() {
+        assert!(Constraints::new().set(Angle(0, 1, 4), 109.5).validate(4).is_err());
+        assert!(Constraints::new().freeze(Distance(1, 1)).validate(4).is_err());
+        assert!(Constraints::new().set(Distance(0, 1), f64::NAN).validate(4).is_err());
+    }
+    #[test]
+    fn test_constraints_report() {
+        // H2O2-like geometry with a dihedral of -90 degrees
+        #[rustfmt::skip]
+        let xyz = [
+            1.0, 0.0, 1.0,
+            0.0, 0.0, 1.0,
+            0.0, 0.0, 0.0,
+            0.0, 1.0, 0.0,
+        ];
+        let constraints = Constraints::new()
+            .freeze(Distance(0, 1))
+            .set(Distance(1, 2), 1.1)
+            .set(Angle(0, 1, 2), 90.0)
+            .set(Dihedral(0, 1, 2, 3), 175.0);
+        let report = constraints.report(&xyz).unwrap();
+        assert_eq!(report.len(), 3);
+        assert_eq!(report[0].description, "distance 2 3");
+        assert!((report[0].deviation + 0.1).abs() < 1e-12);
+        assert!(report[1].deviation.abs() < 1e-12);
+        assert!((report[2].achieved + 90.0).abs() < 1e-12);
+        assert!((report[2].deviation - 95.0).abs() < 1e-9);
+        assert!(Constraints::new().set(Distance(0, 4), 1.0).report(&xyz).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+//! Convergence criteria of geomeTRIC as structured data.
+//!
+//! Values are computed from the trajectory of [`OptResult`] (energies,
+//! gradients and coordinates of consecutive frames), the same quantities that
+//! geomeTRIC prints in its convergence table.
+
+use crate::result::OptResult;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Convergence thresholds of geomeTRIC.
+///
+/// - `energy`: Energy change (Hartree).
+/// - `grms`, `gmax`: RMS and maximum atomic gradient (Hartree/Bohr).
+/// - `drms`, `dmax`: RMS and maximum atomic displacement (Angstrom).
+///
+/// Default values are geomeTRIC's defaults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvergenceCriteria {
+    pub energy: f64,
+    pub grms: f64,
+    pub gmax: f64,
+    pub drms: f64,
+    pub dmax: f64,
+}
+
+impl Default for ConvergenceCriteria {
+    fn default() -> Self {
+        ConvergenceCriteria {
+            energy: 1.0e-6,
+            grms: 3.0e-4,
+            gmax: 4.5e-4,
+            drms: 1.2e-3,
+            dmax: 1.8e-3,
+        }
+    }
+}
+
+impl ConvergenceCriteria {
+    /// Read thresholds (`convergence_energy`, `convergence_grms`, etc.) from
+    /// parameters, defaulting to geomeTRIC's defaults.
+    ///
+    /// Convergence sets (`convergence_set`) are not resolved.
+    pub fn from_params(params: &Py<PyDict>) -> PyResult<Self> {
+        Python::with_gil(|py| {
+            let params = params.bind(py);
+            let get = |key: &str, default: f64| -> PyResult<f64> {
+                match params.get_item(format!("convergence_{}", key))? {
+                    Some(value) => value.extract(),
+                    None => Ok(default),
+                }
+            };
+            let default = Self::default();
+            Ok(ConvergenceCriteria {
+                energy: get("energy", default.energy)?,
+                grms: get("grms", default.grms)?,
+                gmax: get("gmax", default.gmax)?,
+                drms: get("drms", default.drms)?,
+                dmax: get("dmax", default.dmax)?,
+            })
+        })
+    }
+}
+
+/// Convergence quantities of one optimization step (from frame `step - 1` to
+/// frame `step`).
+///
+/// Gradient quantities are `None` if the gradient of the frame is not
+/// available. Displacements are computed without aligning the frames, so they
+/// may be slightly larger than those of geomeTRIC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvergenceStatus {
+    pub step: usize,
+    pub energy_change: f64,
+    pub grms: Option<f64>,
+    pub gmax: Option<f64>,
+    pub drms: f64,
+    pub dmax: f64,
+}
+
+impl ConvergenceStatus {
+    /// Convergence quantities of all steps of the trajectory.
+    pub fn from_result(result: &OptResult) -> Vec<ConvergenceStatus> {
+        (1..result.xyzs.len().min(result.energies.len()))
+            .map(|step| {
+                let gradient = result.gradients.get(step).filter(|g| !g.is_empty());
+                let (grms, gmax) = match gradient {
+                    Some(g) => {
+                        let (rms, max) = rms_max(g.chunks(3).map(norm));
+                        (Some(rms), Some(max))
+                    },
+                    None => (None, None),
+                };
+                let displacements = result.xyzs[step]
+                    .chunks(3)
+                    .zip(result.xyzs[step - 1].chunks(3))
+                    .map(|(a, b)| norm(&[a[0] - b[0], a[1] - b[1], a[2] - b[2]]));
+                let (drms, dmax) = rms_max(displacements);
+                ConvergenceStatus {
+                    step,
+                    energy_change: result.energies[step] - result.energies[step - 1],
+                    grms,
+                    gmax,
+                    drms,
+                    dmax,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Value of a convergence quantity compared to its threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Criterion {
+    pub value: f64,
+    pub threshold: f64,
+    pub met: bool,
+}
+
+impl Criterion {
+    fn new(value: f64, threshold: f64) -> Self {
+        Criterion { value, threshold, met: value.abs() < threshold }
+    }
+}
+
+/// Final convergence table of an optimization.
+///
+/// Gradient criteria are `None` if the final gradient is not available.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConvergenceReport {
+    pub energy: Criterion,
+    pub grms: Option<Criterion>,
+    pub gmax: Option<Criterion>,
+    pub drms: Criterion,
+    pub dmax: Criterion,
+}
+
+impl ConvergenceReport {
+    /// Compare quantities of a step to thresholds.
+    pub fn new(status: &ConvergenceStatus, criteria: &ConvergenceCriteria) -> Self {
+        ConvergenceReport {
+            energy: Criterion::new(status.energy_change, criteria.energy),
+            grms: status.grms.map(|v| Criterion::new(v, criteria.grms)),
+            gmax: status.gmax.map(|v| Criterion::new(v, criteria.gmax)),
+            drms: Criterion::new(status.drms, criteria.drms),
+            dmax: Criterion::new(status.dmax, criteria.dmax),
+        }
+    }
+
+    /// Whether all (available) criteria are met.
+    pub fn all_met(&self) -> bool {
+        [Some(self.energy), self.grms, self.gmax, Some(self.drms), Some(self.dmax)]
+            .iter()
+            .flatten()
+            .all(|c| c.met)
+    }
+}
+
+impl OptResult {
+    /// Final convergence table, from the last step of the trajectory.
+    ///
+    /// Returns `None` for trajectories of less than two frames.
+    pub fn convergence_report(&self, criteria: &ConvergenceCriteria) -> Option<ConvergenceReport> {
+        let status = ConvergenceStatus::from_result(self).pop()?;
+        Some(ConvergenceReport::new(&status, criteria))
+    }
+}
+
+fn norm(v: &[f64]) -> f64 {
+    v.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+/// RMS and maximum of values.
+fn rms_max(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (mut sum2, mut max, mut count) = (0.0, 0.0_f64, 0);
+    for v in values {
+        sum2 += v * v;
+        max = max.max(v);
+        count += 1;
+    }
+    match count {
+        0 => (0.0, 0.0),
+        _ => ((sum2 / count as f64).sqrt(), max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convergence_report() {
+        let mut result = OptResult::new(
+            vec!["H".into(), "H".into()],
+            vec![vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.75], vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.7405]],
+            vec![-1.1700, -1.1702],
+        );
+        result.gradients[1] = vec![0.0, 0.0, 1.0e-4, 0.0, 0.0, -1.0e-4];
+        let report = result.convergence_report(&ConvergenceCriteria::default()).unwrap();
+        assert!(!report.energy.met);
+        assert!(report.grms.unwrap().met && report.gmax.unwrap().met);
+        assert!((report.dmax.value - 0.0095).abs() < 1.0e-12);
+        assert!(!report.dmax.met);
+        assert!(!report.all_met());
+    }
+}
@@ -1,77 +1,786 @@
 //! Engine corresponds to `geometric.engine.Engine` class in geomeTRIC.
 
-use crate::interface::PyGeomDriver;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::callback::{CallbackHandle, StepInfo};
+use crate::interface::{
+    AtomData, DriverError, GeomDriverAPI, GradOutput, PyGeomDriver, WorkQueueJob,
+};
+use crate::params::CoordSys;
+use crate::pyarray::{PyArray1, PyArrayMethods, PyReadonlyArray1};
+use crate::result::{CallStats, OptResult, BOHR2ANG};
+use crate::scratch::ScratchManager;
+use crate::util::json2py_val_with_bound;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyNotImplementedError, PyRuntimeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyList};
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyDict, PyTuple, PyType};
 use pyo3::PyTypeInfo;
 
+create_exception!(
+    geometric_pyo3,
+    DriverTimeBudgetExceeded,
+    PyException,
+    "Raised by `EngineMixin.calc_new` when the driver time budget or timeout is exhausted."
+);
+
+create_exception!(
+    geometric_pyo3,
+    EnergyTargetReached,
+    PyException,
+    "Raised by `EngineMixin.calc_new` when the energy crosses the target energy."
+);
+
+create_exception!(
+    geometric_pyo3,
+    OptimizationCancelled,
+    PyException,
+    "Raised by `EngineMixin.calc_new` when the optimization has been cancelled."
+);
+
+/// Token for cancelling optimizations from another thread (e.g. a GUI or
+/// server front-end).
+///
+/// Clones share the same state. The engine checks the token before each driver
+/// evaluation, so a running evaluation is completed first.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Direction in which the energy crosses an [`EnergyTarget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossingDirection {
+    /// Stop when the energy decreases to or below the target.
+    FromAbove,
+    /// Stop when the energy increases to or above the target.
+    FromBelow,
+}
+
+/// Target energy (Hartree) at which the optimization stops.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnergyTarget {
+    pub energy: f64,
+    pub direction: CrossingDirection,
+}
+
+impl EnergyTarget {
+    /// Whether `energy` has crossed the target.
+    pub fn is_reached(&self, energy: f64) -> bool {
+        match self.direction {
+            CrossingDirection::FromAbove => energy <= self.energy,
+            CrossingDirection::FromBelow => energy >= self.energy,
+        }
+    }
+}
+
+/// Handling of non-finite (NaN or infinity) energy or gradient returned by the
+/// driver.
+///
+/// - `Error`: Stop the optimization with [`DriverError::NonFiniteOutput`]
+///   (default).
+/// - `RejectStep`: Raise geomeTRIC's `geometric.errors.EngineError`, which
+///   geomeTRIC recognizes as a failed calculation at the current step, so the
+///   optimizer may reject the step and retry with a smaller trust radius
+///   instead of crashing the whole run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    #[default]
+    Error,
+    RejectStep,
+}
+
 /// Mixin class to be mult-inherited together with `geometric.engine.Engine`.
 #[pyclass(subclass)]
 pub struct EngineMixin {
     driver: Option<PyGeomDriver>,
+    /// Per-atom data read from the molecule, passed to the driver.
+    atom_data: AtomData,
+    /// Soft limit of accumulated driver wall-clock time.
+    time_budget: Option<Duration>,
+    /// Accumulated wall-clock time spent in the driver in the current run.
+    driver_time: Duration,
+    /// Wall-clock deadline of the optimization.
+    deadline: Option<Instant>,
+    /// End of the previous driver evaluation.
+    last_return: Option<Instant>,
+    /// Call statistics of the current run.
+    stats: CallStats,
+    /// Records of driver evaluations of the current run, in order of
+    /// evaluation.
+    history: Vec<CalcRecord>,
+    /// Probe the gradient sign convention at the first evaluation.
+    check_gradient_sign: bool,
+    /// Handling of non-finite driver output.
+    non_finite_policy: NonFinitePolicy,
+    /// Energy at which the optimization stops.
+    energy_target: Option<EnergyTarget>,
+    /// Token checked before every evaluation.
+    cancel_token: Option<CancelToken>,
+    /// Callback invoked after every evaluation.
+    callback: Option<CallbackHandle>,
+    /// Scratch directories passed to the driver instead of geomeTRIC's.
+    scratch: Option<ScratchManager>,
+    /// Weak reference to geomeTRIC's `Optimizer` driving the engine, if known.
+    optimizer: Option<PyObject>,
+}
+
+/// Record of one driver evaluation.
+///
+/// - `coords`: Coordinates in Bohr.
+/// - `energy`: Energy in Hartree.
+/// - `gradient`: Gradient in Hartree/Bohr.
+/// - `energy_components`: Labeled energy components given by the driver.
+/// - `extras`: Extra properties given by the driver.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CalcRecord {
+    pub coords: Vec<f64>,
+    pub energy: f64,
+    pub gradient: Vec<f64>,
+    pub energy_components: HashMap<String, f64>,
+    pub extras: HashMap<String, serde_json::Value>,
 }
 
+/// Step lengths (Bohr) along the negative gradient used to probe the gradient
+/// sign convention.
+const GRADIENT_SIGN_PROBE_STEPS: [f64; 2] = [1.0e-3, 5.0e-4];
+
 #[pymethods]
 impl EngineMixin {
     /// Initialize the EngineMixin class.
     ///
-    /// Though this function does not do anything, it is intended to be
-    /// inherited by `geometric.engine.Engine`'s initializer. So input
-    /// `_molecule` is actually gracefully initialized.
+    /// This function only reads per-atom data (see [`AtomData`]) from
+    /// `molecule`; it is intended to be inherited by
+    /// `geometric.engine.Engine`'s initializer. So input `molecule` is
+    /// actually gracefully initialized.
     ///
     /// Please note that `driver` is not initialized here. It should be set
     /// using the `set_driver` method manually.
     #[new]
-    pub fn new(_molecule: PyObject) -> PyResult<Self> {
-        Ok(EngineMixin { driver: None })
+    pub fn new(molecule: Bound<'_, PyAny>) -> PyResult<Self> {
+        Ok(EngineMixin {
+            driver: None,
+            atom_data: AtomData::from_molecule(&molecule)?,
+            time_budget: None,
+            driver_time: Duration::ZERO,
+            deadline: None,
+            last_return: None,
+            stats: CallStats::default(),
+            history: Vec::new(),
+            check_gradient_sign: false,
+            non_finite_policy: NonFinitePolicy::default(),
+            energy_target: None,
+            cancel_token: None,
+            callback: None,
+            scratch: None,
+            optimizer: None,
+        })
     }
 
     /// Set the driver for the engine.
     ///
     /// This driver is used to calculate the energy and gradient of the
     /// system. This function must be called before using the engine.
+    ///
+    /// Per-atom data of the molecule is passed to the driver by
+    /// [`GeomDriverAPI::set_atom_data`] here.
     pub fn set_driver(&mut self, driver: &PyGeomDriver) {
+        driver.pointer.lock().unwrap().set_atom_data(&self.atom_data);
         self.driver = Some(driver.clone());
     }
 
+    /// Set the electronic state to be optimized (0 for the ground state).
+    ///
+    /// The state is passed to the driver with the atom data (see
+    /// [`AtomData::state`]), again if the driver is already set.
+    pub fn set_state(&mut self, state: usize) {
+        self.atom_data.state = state;
+        if let Some(driver) = &self.driver {
+            driver.pointer.lock().unwrap().set_atom_data(&self.atom_data);
+        }
+    }
+
+    /// Set a soft budget (in seconds) of wall-clock time spent in the driver.
+    ///
+    /// Once the accumulated driver time of the run exceeds this budget,
+    /// `calc_new` raises `DriverTimeBudgetExceeded`, which
+    /// [`run_optimization`](crate::optimize::run_optimization) turns into
+    /// [`OptimizationOutcome::MaxTime`](crate::result::OptimizationOutcome::MaxTime).
+    /// `None` removes the budget.
+    #[pyo3(signature = (seconds=None))]
+    pub fn set_time_budget(&mut self, seconds: Option<f64>) -> PyResult<()> {
+        self.time_budget = seconds
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(|e| PyValueError::new_err(format!("Invalid time budget: {}", e)))?;
+        Ok(())
+    }
+
+    /// Enable or disable the gradient sign diagnostic.
+    ///
+    /// When enabled, the first `calc_new` additionally evaluates the energy at
+    /// small displacements along the negative gradient. If the energy
+    /// increases for all of them, the gradient sign is likely wrong (e.g.
+    /// forces are returned) and [`DriverError::LikelyGradientSignError`] is
+    /// raised. This costs two extra driver evaluations.
+    pub fn set_check_gradient_sign(&mut self, enabled: bool) {
+        self.check_gradient_sign = enabled;
+    }
+
     /// Inherits `geometric.engine.Engine`'s `calc_new` method.
-    pub fn calc_new(&mut self, coords: Vec<f64>, dirname: &str) -> PyResult<PyObject> {
-        // Compute the energy and gradient using the driver.
-        let mut driver = self.driver.as_mut().unwrap().pointer.lock().unwrap();
-        let result = driver.calc_new(&coords, dirname);
+    ///
+    /// `coords` is the flattened numpy array (natom * 3) given by geomeTRIC,
+    /// read without copy if contiguous.
+    pub fn calc_new(
+        &mut self,
+        coords: PyReadonlyArray1<'_, f64>,
+        dirname: &str,
+    ) -> PyResult<PyObject> {
+        let py = coords.py();
+        let coords = match coords.as_slice() {
+            Ok(coords) => Cow::Borrowed(coords),
+            Err(_) => Cow::Owned(coords.as_array().to_vec()),
+        };
+
+        let result = self.evaluate(py, &coords, dirname)?;
+
         // Convert the result to a Python object.
         // Note: that gradient must be converted to numpy flattened array (natom * 3),
         // list or 2-d array are both incorrect here.
-        Python::with_gil(|py| {
-            let numpy = py.import("numpy")?;
-            let energy = result.energy;
-            let gradient = numpy.call_method1("array", (PyList::new(py, result.gradient)?,))?;
-            let dict = PyDict::new(py);
-            dict.set_item("energy", energy)?;
-            dict.set_item("gradient", gradient)?;
-            Ok(dict.into())
+        let dict = PyDict::new(py);
+        for (key, value) in &result.extras {
+            dict.set_item(key, json2py_val_with_bound(py, value)?)?;
+        }
+        dict.set_item("energy", result.energy)?;
+        dict.set_item("gradient", PyArray1::from_vec(py, result.gradient))?;
+        Ok(dict.into())
+    }
+
+    /// Inherits `geometric.engine.Engine`'s `calc_hessian` method.
+    ///
+    /// Returns the Cartesian Hessian (Hartree/Bohr²) as numpy array of shape
+    /// (natom * 3, natom * 3). Raises `NotImplementedError` if the driver does
+    /// not provide an analytic Hessian, so that geomeTRIC falls back to
+    /// finite differences. Other arguments of geomeTRIC (e.g. `read_data`)
+    /// are ignored.
+    #[pyo3(signature = (coords, dirname, *_args, **_kwargs))]
+    pub fn calc_hessian(
+        &mut self,
+        coords: PyReadonlyArray1<'_, f64>,
+        dirname: &str,
+        _args: &Bound<'_, PyTuple>,
+        _kwargs: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<PyObject> {
+        let py = coords.py();
+        let coords = match coords.as_slice() {
+            Ok(coords) => Cow::Borrowed(coords),
+            Err(_) => Cow::Owned(coords.as_array().to_vec()),
+        };
+        let scratch_dir = self.create_scratch_dir("hessian")?;
+        let dirname = scratch_dir.as_deref().map_or(Ok(dirname), scratch_dirname)?;
+        let driver = &self.driver.as_ref().unwrap().pointer;
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("calc_hessian").entered();
+        let result = py.allow_threads(|| driver.lock().unwrap().calc_hessian(&coords, dirname));
+        self.record_call(start);
+        self.stats.n_hessian += result.is_some() as usize;
+        self.release_scratch_dir(scratch_dir, result.is_some())?;
+
+        let Some(result) = result else {
+            return Err(PyNotImplementedError::new_err("Driver does not provide analytic Hessian"));
+        };
+        let ncoord = coords.len();
+        if result.hessian.len() != ncoord * ncoord {
+            return Err(PyValueError::new_err(format!(
+                "Length of Hessian ({}) does not match number of coordinates ({}) squared",
+                result.hessian.len(),
+                ncoord
+            )));
+        }
+        let hessian = PyArray1::from_vec(py, result.hessian).reshape([ncoord, ncoord])?;
+        Ok(hessian.into_any().unbind())
+    }
+
+    /// Inherits `geometric.engine.Engine`'s `calc_wq_new` method.
+    ///
+    /// Queues the job prepared by [`GeomDriverAPI::prepare_wq_job`] to
+    /// geomeTRIC's Work Queue. Raises `NotImplementedError` if the driver does
+    /// not support Work Queue.
+    pub fn calc_wq_new(
+        &mut self,
+        coords: PyReadonlyArray1<'_, f64>,
+        dirname: &str,
+    ) -> PyResult<()> {
+        let py = coords.py();
+        let coords = coords.as_array().to_vec();
+        std::fs::create_dir_all(dirname)?;
+        let driver = &self.driver.as_ref().unwrap().pointer;
+        let job = py.allow_threads(|| driver.lock().unwrap().prepare_wq_job(&coords, dirname));
+        let Some(job) = job.map_err(|err| err.into_engine_error(py))? else {
+            return Err(PyNotImplementedError::new_err("Driver does not support Work Queue"));
+        };
+        queue_wq_job(py, &job, dirname)
+    }
+
+    /// Inherits `geometric.engine.Engine`'s `read_result` method, reading the
+    /// output of a Work Queue job by [`GeomDriverAPI::read_wq_result`].
+    ///
+    /// Unlike `calc_new`, results of Work Queue jobs are not recorded in the
+    /// evaluation history.
+    #[pyo3(signature = (dirname, check_coord=None))]
+    pub fn read_result(
+        &mut self,
+        py: Python<'_>,
+        dirname: &str,
+        check_coord: Option<PyObject>,
+    ) -> PyResult<PyObject> {
+        // Coordinates of the job are known to the driver; not checked here.
+        let _ = check_coord;
+        let driver = &self.driver.as_ref().unwrap().pointer;
+        let result = py.allow_threads(|| driver.lock().unwrap().read_wq_result(dirname));
+        let result = result.map_err(|err| err.into_engine_error(py))?;
+        let dict = PyDict::new(py);
+        dict.set_item("energy", result.energy)?;
+        dict.set_item("gradient", PyArray1::from_vec(py, result.gradient))?;
+        Ok(dict.into())
+    }
+}
+
+/// Submit a job to the Work Queue of geomeTRIC, with files relative to
+/// `dirname`.
+fn queue_wq_job(py: Python<'_>, job: &WorkQueueJob, dirname: &str) -> PyResult<()> {
+    let nifty = py.import("geometric.nifty")?;
+    let wq = nifty.call_method0("getWorkQueue")?;
+    if wq.is_none() {
+        return Err(PyRuntimeError::new_err("Work Queue is not initialized; set parameter `port`"));
+    }
+    let files = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| {
+                (Path::new(dirname).join(name).to_string_lossy().into_owned(), name.clone())
+            })
+            .collect::<Vec<_>>()
+    };
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("input_files", files(&job.input_files))?;
+    kwargs.set_item("output_files", files(&job.output_files))?;
+    kwargs.set_item("verbose", false)?;
+    nifty.call_method("queue_up_src_dest", (wq, &job.command), Some(&kwargs))?;
+    Ok(())
+}
+
+impl EngineMixin {
+    /// Set the handling of non-finite driver output.
+    pub fn set_non_finite_policy(&mut self, policy: NonFinitePolicy) {
+        self.non_finite_policy = policy;
+    }
+
+    /// Set the energy at which the optimization stops.
+    ///
+    /// When the energy of an evaluation crosses the target, `calc_new` raises
+    /// `EnergyTargetReached`, which
+    /// [`run_optimization`](crate::optimize::run_optimization) turns into
+    /// [`OptimizationOutcome::EnergyTarget`](crate::result::OptimizationOutcome::EnergyTarget).
+    pub fn set_energy_target(&mut self, target: Option<EnergyTarget>) {
+        self.energy_target = target;
+    }
+
+    /// Set the wall-clock deadline of the optimization.
+    ///
+    /// Once passed, `calc_new` raises `DriverTimeBudgetExceeded` instead of
+    /// calling the driver, as for the driver time budget.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Set the cancellation token.
+    ///
+    /// Once cancelled, `calc_new` raises `OptimizationCancelled` instead of
+    /// calling the driver, which
+    /// [`run_optimization`](crate::optimize::run_optimization) turns into
+    /// [`OptimizationOutcome::Cancelled`](crate::result::OptimizationOutcome::Cancelled).
+    pub fn set_cancel_token(&mut self, token: Option<CancelToken>) {
+        self.cancel_token = token;
+    }
+
+    /// Set the callback invoked after every driver evaluation.
+    pub fn set_callback(&mut self, callback: Option<CallbackHandle>) {
+        self.callback = callback;
+    }
+
+    /// Set the manager of per-evaluation scratch directories.
+    ///
+    /// If set, the driver receives a fresh directory created by the manager as
+    /// `dirname`, instead of the one given by geomeTRIC.
+    pub fn set_scratch(&mut self, scratch: Option<ScratchManager>) {
+        self.scratch = scratch;
+    }
+
+    fn create_scratch_dir(&mut self, kind: &str) -> PyResult<Option<PathBuf>> {
+        match &mut self.scratch {
+            Some(scratch) => Ok(Some(scratch.create(kind)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn release_scratch_dir(&mut self, dir: Option<PathBuf>, success: bool) -> PyResult<()> {
+        if let (Some(scratch), Some(dir)) = (&mut self.scratch, dir) {
+            scratch.release(dir, success)?;
+        }
+        Ok(())
+    }
+
+    /// Coordinate system preferred by the driver, if any.
+    pub fn preferred_coordsys(&self) -> Option<CoordSys> {
+        self.driver.as_ref()?.pointer.lock().unwrap().preferred_coordsys()
+    }
+
+    /// Accumulated wall-clock time spent in the driver since the last
+    /// [`begin_run`](Self::begin_run).
+    pub fn driver_time(&self) -> Duration {
+        self.driver_time
+    }
+
+    /// Call statistics since the last [`begin_run`](Self::begin_run).
+    pub fn call_stats(&self) -> CallStats {
+        self.stats
+    }
+
+    /// Reset per-run state (call statistics, evaluation history and driver
+    /// time) at the start of an optimization, so that results of an engine
+    /// reused for several runs only hold their own evaluations; time until the
+    /// first evaluation counts as python overhead.
+    pub fn begin_run(&mut self) {
+        self.stats = CallStats::default();
+        self.history.clear();
+        self.driver_time = Duration::ZERO;
+        self.last_return = Some(Instant::now());
+    }
+
+    /// Evaluate the driver at `coords` (Bohr, flattened natom * 3), with all
+    /// bookkeeping of [`calc_new`](Self::calc_new) but the conversion to
+    /// python.
+    pub(crate) fn evaluate(
+        &mut self,
+        py: Python<'_>,
+        coords: &[f64],
+        dirname: &str,
+    ) -> PyResult<GradOutput> {
+        if self.cancel_token.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(OptimizationCancelled::new_err("Optimization cancelled"));
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            return Err(DriverTimeBudgetExceeded::new_err("Wall-clock timeout exceeded"));
+        }
+
+        // Compute the energy and gradient using the driver. The GIL is released
+        // meanwhile, so that other Python threads are not blocked.
+        let scratch_dir = self.create_scratch_dir("step")?;
+        let dirname = scratch_dir.as_deref().map_or(Ok(dirname), scratch_dirname)?;
+        let driver = &self.driver.as_ref().unwrap().pointer;
+        let check_sign = self.check_gradient_sign && self.history.is_empty();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("calc_new", step = self.history.len()).entered();
+        let start = Instant::now();
+        let result = py.allow_threads(|| {
+            let mut driver = driver.lock().unwrap();
+            let result = driver.calc_new(coords, dirname)?;
+            if check_sign {
+                check_gradient_sign(&mut *driver, coords, dirname, &result)?;
+            }
+            Ok(result)
+        });
+        let python_time = self.record_call(start);
+        self.stats.n_gradient += 1;
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            driver_time = start.elapsed().as_secs_f64(),
+            python_time = python_time.map(|t| t.as_secs_f64()),
+            ok = result.is_ok(),
+            "driver evaluation"
+        );
+        #[cfg(not(feature = "tracing"))]
+        let _ = python_time;
+        self.release_scratch_dir(scratch_dir, result.is_ok())?;
+        let result = match result {
+            Ok(result) => result,
+            Err(err @ DriverError::LikelyGradientSignError { .. }) => return Err(err.into()),
+            Err(err) => return Err(err.into_engine_error(py)),
+        };
+
+        // Non-finite output is not recorded, so it never becomes the best
+        // geometry of a stopped optimization.
+        if !result.is_finite() {
+            let err = DriverError::NonFiniteOutput {
+                energy: result.energy,
+                n_nonfinite_gradient: result.gradient.iter().filter(|g| !g.is_finite()).count(),
+            };
+            return match self.non_finite_policy {
+                NonFinitePolicy::Error => Err(err.into()),
+                NonFinitePolicy::RejectStep => Err(err.into_engine_error(py)),
+            };
+        }
+
+        if let Some(callback) = &self.callback {
+            let (grad_rms, grad_max) = atomic_rms_max(&result.gradient);
+            // Displacement from the previous evaluation, in Angstrom
+            let (disp_rms, disp_max) = match self.history.last() {
+                Some(last) if last.coords.len() == coords.len() => atomic_rms_max(
+                    &coords
+                        .iter()
+                        .zip(&last.coords)
+                        .map(|(x, y)| (x - y) * BOHR2ANG)
+                        .collect::<Vec<_>>(),
+                ),
+                _ => (0.0, 0.0),
+            };
+            callback.on_step(&StepInfo {
+                step: self.history.len(),
+                energy: result.energy,
+                grad_rms,
+                grad_max,
+                disp_rms,
+                disp_max,
+                trust: self.optimizer_trust(py),
+                coords: coords.iter().map(|x| x * BOHR2ANG).collect(),
+            });
+        }
+        self.history.push(CalcRecord {
+            coords: coords.to_vec(),
+            energy: result.energy,
+            gradient: result.gradient.clone(),
+            energy_components: result.energy_components.clone(),
+            extras: result.extras.clone(),
+        });
+
+        if let Some(target) = self.energy_target {
+            if target.is_reached(result.energy) {
+                return Err(EnergyTargetReached::new_err(format!(
+                    "Energy {} crossed target {}",
+                    result.energy, target.energy
+                )));
+            }
+        }
+
+        if let Some(budget) = self.time_budget {
+            if self.driver_time > budget {
+                return Err(DriverTimeBudgetExceeded::new_err(format!(
+                    "Driver time {:.3} s exceeded budget {:.3} s",
+                    self.driver_time.as_secs_f64(),
+                    budget.as_secs_f64()
+                )));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Register geomeTRIC's `Optimizer` driving the engine, so that its trust
+    /// radius is reported in [`StepInfo::trust`].
+    ///
+    /// geomeTRIC does not pass the optimizer to engines; this is set by
+    /// [`OptimizerSession`](crate::optimize::OptimizerSession), which owns it.
+    /// Only a weak reference is kept, as the optimizer holds the engine.
+    pub fn set_optimizer(&mut self, optimizer: &Bound<'_, PyAny>) -> PyResult<()> {
+        let weakref = optimizer.py().import("weakref")?.getattr("ref")?;
+        self.optimizer = Some(weakref.call1((optimizer,))?.unbind());
+        Ok(())
+    }
+
+    /// Current trust radius of the registered optimizer, if any.
+    fn optimizer_trust(&self, py: Python<'_>) -> Option<f64> {
+        let optimizer = self.optimizer.as_ref()?.call0(py).ok()?;
+        optimizer.getattr(py, "trust").ok()?.extract(py).ok()
+    }
+
+    /// Account a driver call started at `start` and ended now; returns the
+    /// python overhead since the previous call.
+    fn record_call(&mut self, start: Instant) -> Option<Duration> {
+        let end = Instant::now();
+        self.driver_time += end - start;
+        self.stats.driver_time += end - start;
+        let python_time = self.last_return.map(|last| start.saturating_duration_since(last));
+        self.stats.python_time += python_time.unwrap_or_default();
+        self.last_return = Some(end);
+        python_time
+    }
+
+    /// Build a result from evaluated geometries, truncated after the
+    /// lowest-energy one.
+    ///
+    /// This is used when geomeTRIC is stopped before returning its own
+    /// `Progress` object, so the final frame is the best geometry seen.
+    /// Elements, charge and multiplicity are read from `molecule`.
+    pub fn best_result(&self, molecule: &Bound<'_, PyAny>) -> PyResult<OptResult> {
+        let best = self
+            .history
+            .iter()
+            .enumerate()
+            .min_by(|(_, r1), (_, r2)| r1.energy.total_cmp(&r2.energy))
+            .map_or(0, |(idx, _)| idx + 1);
+        self.history_result(molecule, best)
+    }
+
+    /// Build a result from all geometries evaluated in the current run (since
+    /// [`begin_run`](Self::begin_run)), so the final frame is the last
+    /// evaluated geometry.
+    ///
+    /// Elements, charge and multiplicity are read from `molecule`.
+    pub fn last_result(&self, molecule: &Bound<'_, PyAny>) -> PyResult<OptResult> {
+        self.history_result(molecule, self.history.len())
+    }
+
+    /// Build a result from the first `len` evaluated geometries.
+    fn history_result(&self, molecule: &Bound<'_, PyAny>, len: usize) -> PyResult<OptResult> {
+        let records = &self.history[..len];
+        let xyzs =
+            records.iter().map(|r| r.coords.iter().map(|x| x * BOHR2ANG).collect()).collect();
+        let energies = records.iter().map(|r| r.energy).collect();
+        let mut result = OptResult::with_molecule_info(molecule, xyzs, energies)?;
+        result.gradients = records.iter().map(|r| r.gradient.clone()).collect();
+        result.energy_components = records.iter().map(|r| r.energy_components.clone()).collect();
+        result.extras = records.iter().map(|r| r.extras.clone()).collect();
+        Ok(result)
+    }
+
+    /// Find the latest driver evaluation at given coordinates (Angstrom).
+    pub fn find_record(&self, xyz: &[f64]) -> Option<&CalcRecord> {
+        self.history.iter().rev().find(|r| {
+            r.coords.len() == xyz.len()
+                && r.coords.iter().zip(xyz).all(|(x, y)| (x * BOHR2ANG - y).abs() < 1.0e-8)
         })
     }
+
+    /// Fill per-frame data recorded by this engine (energy components and
+    /// extras) into a result extracted from geomeTRIC's `Progress` object.
+    ///
+    /// Frames are matched to driver evaluations by coordinates.
+    pub fn fill_result(&self, result: &mut OptResult) {
+        let records = result.xyzs.iter().map(|xyz| self.find_record(xyz)).collect::<Vec<_>>();
+        result.energy_components = records
+            .iter()
+            .map(|r| r.map(|r| r.energy_components.clone()).unwrap_or_default())
+            .collect();
+        result.extras =
+            records.iter().map(|r| r.map(|r| r.extras.clone()).unwrap_or_default()).collect();
+    }
+}
+
+/// RMS and maximum of per-atom norms of a flattened (natom * 3) vector.
+fn atomic_rms_max(vector: &[f64]) -> (f64, f64) {
+    let norms = vector.chunks(3).map(|v| v.iter().map(|x| x * x).sum::<f64>().sqrt());
+    let (sum_sq, max) = norms.fold((0.0, 0.0_f64), |(s, m), n| (s + n * n, m.max(n)));
+    ((sum_sq / (vector.len() / 3).max(1) as f64).sqrt(), max)
+}
+
+/// Scratch directory as `dirname` argument of the driver.
+fn scratch_dirname(dir: &Path) -> PyResult<&str> {
+    dir.to_str().ok_or_else(|| {
+        PyValueError::new_err(format!("Scratch directory {:?} is not valid UTF-8", dir))
+    })
+}
+
+/// Probe whether energy decreases along the negative gradient.
+fn check_gradient_sign(
+    driver: &mut dyn GeomDriverAPI,
+    coords: &[f64],
+    dirname: &str,
+    result: &GradOutput,
+) -> Result<(), DriverError> {
+    let norm = result.gradient.iter().map(|g| g * g).sum::<f64>().sqrt();
+    // gradient vanishes (e.g. already at stationary point); nothing to probe
+    if norm < 1.0e-8 {
+        return Ok(());
+    }
+    let energy_changes = GRADIENT_SIGN_PROBE_STEPS
+        .iter()
+        .map(|step| {
+            let displaced = coords
+                .iter()
+                .zip(&result.gradient)
+                .map(|(x, g)| x - step * g / norm)
+                .collect::<Vec<_>>();
+            Ok(driver.calc_new(&displaced, dirname)?.energy - result.energy)
+        })
+        .collect::<Result<Vec<_>, DriverError>>()?;
+    match energy_changes.iter().all(|&de| de > 0.0) {
+        true => Err(DriverError::LikelyGradientSignError { energy_changes }),
+        false => Ok(()),
+    }
+}
+
+/// Class `PyO3Engine`, created once per process.
+static PYO3_ENGINE_CLS: GILOnceCell<PyObject> = GILOnceCell::new();
+
+/// Class `geometric.molecule.Molecule`, imported once per process.
+static MOLECULE_CLS: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+/// Get the `geometric.molecule.Molecule` class.
+pub(crate) fn molecule_cls(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    MOLECULE_CLS.import(py, "geometric.molecule", "Molecule")
 }
 
 /// Get the PyO3 usable geomeTRIC engine class.
+///
+/// The class is created at the first call, and the same class is returned
+/// afterwards.
 pub fn get_pyo3_engine_cls() -> PyResult<PyObject> {
     Python::with_gil(|py| {
-        // get the type of base class `geometric.engine.Engine`
-        let base_type = py.import("geometric.engine")?.getattr("Engine")?;
-        // get the type of `EngineMixin` class
-        let engine_mixin_type = EngineMixin::type_object(py);
-
-        // execute and return the following code in Python:
-        // ```python
-        // PyO3Engine = type('PyO3Engine', (EngineMixin, Engine), {})
-        // ```
-        let locals = PyDict::new(py);
-        locals.set_item("Engine", base_type)?;
-        locals.set_item("EngineMixin", engine_mixin_type)?;
-        let pyo3_engine_type =
-            py.eval(c"type('PyO3Engine', (EngineMixin, Engine), {})", None, Some(&locals))?;
-        Ok(pyo3_engine_type.into())
+        let cls = PYO3_ENGINE_CLS.get_or_try_init(py, || create_pyo3_engine_cls(py))?;
+        Ok(cls.clone_ref(py))
+    })
+}
+
+fn create_pyo3_engine_cls(py: Python<'_>) -> PyResult<PyObject> {
+    // get the type of base class `geometric.engine.Engine`
+    let base_type = py.import("geometric.engine")?.getattr("Engine")?;
+    // get the type of `EngineMixin` class
+    let engine_mixin_type = EngineMixin::type_object(py);
+
+    // execute and return the following code in Python:
+    // ```python
+    // PyO3Engine = type('PyO3Engine', (EngineMixin, Engine), {})
+    // ```
+    let locals = PyDict::new(py);
+    locals.set_item("Engine", base_type)?;
+    locals.set_item("EngineMixin", engine_mixin_type)?;
+    let pyo3_engine_type =
+        py.eval(c"type('PyO3Engine', (EngineMixin, Engine), {})", None, Some(&locals))?;
+    Ok(pyo3_engine_type.into())
+}
+
+/// Create a `PyO3Engine` instance of the molecule, with driver set.
+///
+/// This is the same to calling the class from [`get_pyo3_engine_cls`] with
+/// `molecule`, then calling `set_driver` with `driver`.
+pub fn init_pyo3_engine(molecule: &PyObject, driver: &PyGeomDriver) -> PyResult<PyObject> {
+    let pyo3_engine_cls = get_pyo3_engine_cls()?;
+    Python::with_gil(|py| {
+        let custom_engine = pyo3_engine_cls.call1(py, (molecule,))?;
+        custom_engine.call_method1(py, "set_driver", (driver.clone(),))?;
+        Ok(custom_engine)
     })
 }
 
@@ -80,36 +789,132 @@ pub fn get_pyo3_engine_cls() -> PyResult<PyObject> {
 /// # Arguments
 ///
 /// - `elem`: A slice of strings representing the element types.
-/// - `xyzs`: A list of vectors representing the coordinates of the atoms. Each
-///   vector represents one molecule, where its length is (natom * 3), with
-///   dimension of coordinate (3) to be contiguous.
+/// - `xyzs`: A list of vectors representing the coordinates of the atoms in
+///   **Angstrom**. Each vector represents one molecule, where its length is
+///   (natom * 3), with dimension of coordinate (3) to be contiguous. For
+///   coordinates in Bohr, use [`MoleculeSpec`](crate::molecule::MoleculeSpec)
+///   with [`LengthUnit::Bohr`](crate::molecule::LengthUnit).
 pub fn init_pyo3_molecule(elem: &[&str], xyzs: &[Vec<f64>]) -> PyResult<PyObject> {
+    init_pyo3_molecule_with_comments(elem, xyzs, &[])
+}
+
+/// Initialize a geomeTRIC molecule into Python object, with comment lines.
+///
+/// Comments are set to `Molecule.comms`, which geomeTRIC writes as the comment
+/// line of each frame in XYZ output.
+///
+/// # Arguments
+///
+/// - `elem`, `xyzs`: See [`init_pyo3_molecule`].
+/// - `comments`: Empty for not setting comments; one comment to be used for all
+///   frames; or one comment per frame (length equals to `xyzs`).
+pub fn init_pyo3_molecule_with_comments(
+    elem: &[&str],
+    xyzs: &[Vec<f64>],
+    comments: &[&str],
+) -> PyResult<PyObject> {
+    let comms = match comments.len() {
+        0 => None,
+        1 => Some(vec![comments[0]; xyzs.len()]),
+        n if n == xyzs.len() => Some(comments.to_vec()),
+        n => {
+            return Err(PyValueError::new_err(format!(
+                "Number of comments ({}) does not match number of frames ({})",
+                n,
+                xyzs.len()
+            )))
+        },
+    };
+
     Python::with_gil(|py| {
         // Import the geometric Python module.
-        let molecule_cls = py.import("geometric.molecule")?.getattr("Molecule")?;
+        let molecule_cls = molecule_cls(py)?;
 
         // Create a new instance of the Molecule class
         let molecule_instance = molecule_cls.call0()?;
 
         // xyzs must be converted into numpy array of shape (natom, 3), where 1-D array
         // or python list are both incorrect.
-        let numpy = py.import("numpy")?;
         let xyzs = xyzs
             .iter()
-            .map(|xyz| {
-                let arr = numpy.call_method1("array", (PyList::new(py, xyz)?,))?;
-                let arr = arr.call_method1("reshape", (-1, 3))?;
-                Ok(arr)
-            })
+            .map(|xyz| PyArray1::from_slice(py, xyz).reshape([xyz.len() / 3, 3]))
             .collect::<PyResult<Vec<_>>>()?;
 
         // Set the attributes
         molecule_instance.setattr("elem", elem)?;
         molecule_instance.setattr("xyzs", xyzs)?;
+        if let Some(comms) = comms {
+            molecule_instance.setattr("comms", comms)?;
+        }
         Ok(molecule_instance.into())
     })
 }
 
+/// File formats readable by geomeTRIC's `Molecule` (`ftype` argument).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Xyz,
+    Pdb,
+    Mol2,
+    /// GROMACS coordinate file.
+    Gro,
+    /// Tinker `.arc`/`.xyz` file.
+    Arc,
+    /// AMBER restart/coordinate file.
+    Inpcrd,
+    /// Gaussian input file (`.com`/`.gjf`).
+    Gaussian,
+    /// Q-Chem input file.
+    QcIn,
+    /// Q-Chem output file.
+    QcOut,
+}
+
+impl Format {
+    /// String of `ftype` recognized by geomeTRIC.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Xyz => "xyz",
+            Format::Pdb => "pdb",
+            Format::Mol2 => "mol2",
+            Format::Gro => "gro",
+            Format::Arc => "arc",
+            Format::Inpcrd => "inpcrd",
+            Format::Gaussian => "com",
+            Format::QcIn => "qcin",
+            Format::QcOut => "qcout",
+        }
+    }
+}
+
+/// Read a geomeTRIC molecule from file by geomeTRIC's own readers.
+///
+/// # Arguments
+///
+/// - `path`: Path of the file.
+/// - `ftype`: Format of the file. If `None`, geomeTRIC guesses the format from
+///   the file extension.
+///
+/// Metadata read by geomeTRIC (e.g. charge and multiplicity of Q-Chem or
+/// Gaussian inputs, residues of PDB) is kept on the molecule.
+pub fn init_molecule_from_path(
+    path: impl AsRef<Path>,
+    ftype: Option<Format>,
+) -> PyResult<PyObject> {
+    let path = path.as_ref();
+    if !path.is_file() {
+        return Err(PyValueError::new_err(format!("Molecule file not found: {}", path.display())));
+    }
+    Python::with_gil(|py| {
+        let molecule_cls = molecule_cls(py)?;
+        let kwargs = PyDict::new(py);
+        if let Some(ftype) = ftype {
+            kwargs.set_item("ftype", ftype.as_str())?;
+        }
+        Ok(molecule_cls.call((path.to_string_lossy(),), Some(&kwargs))?.unbind())
+    })
+}
+
 /// Call `geometric.molecule.build_topology` function to build the topology.
 pub fn molecule_build_topology(
     molecule: &PyObject,
@@ -120,3 +925,148 @@ pub fn molecule_build_topology(
         Ok(())
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Harmonic potential `E = sum(x^2)`, optionally returning forces instead
+    /// of gradient.
+    struct Harmonic {
+        flip_sign: bool,
+    }
+
+    impl GeomDriverAPI for Harmonic {
+        fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+            let energy = coords.iter().map(|x| x * x).sum();
+            let sign = if self.flip_sign { -2.0 } else { 2.0 };
+            Ok(GradOutput::new(energy, coords.iter().map(|x| sign * x).collect()))
+        }
+    }
+
+    #[test]
+    fn test_check_gradient_sign() {
+        let coords = [0.1, -0.2, 0.3];
+        for flip_sign in [false, true] {
+            let mut driver = Harmonic { flip_sign };
+            let result = driver.calc_new(&coords, "").unwrap();
+            let check = check_gradient_sign(&mut driver, &coords, "", &result);
+            assert_eq!(check.is_err(), flip_sign);
+        }
+    }
+
+    /// Engine of a one-atom molecule stand-in, with a harmonic driver.
+    fn harmonic_engine(py: Python<'_>) -> (Bound<'_, PyAny>, EngineMixin) {
+        let types = py.import("types").unwrap();
+        let molecule = types.getattr("SimpleNamespace").unwrap().call0().unwrap();
+        molecule.setattr("elem", vec!["H"]).unwrap();
+        let mut engine = EngineMixin::new(molecule.clone()).unwrap();
+        engine.set_driver(&PyGeomDriver::from(Harmonic { flip_sign: true }));
+        (molecule, engine)
+    }
+
+    #[test]
+    fn test_engine_reused_for_runs() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (molecule, mut engine) = harmonic_engine(py);
+            engine.begin_run();
+            engine.evaluate(py, &[0.1, 0.2, 0.3], "").unwrap();
+            engine.evaluate(py, &[0.0, 0.1, 0.0], "").unwrap();
+
+            // The gradient sign is probed again at the first evaluation
+            engine.set_check_gradient_sign(true);
+            engine.begin_run();
+            assert!(engine.evaluate(py, &[0.1, 0.2, 0.3], "").is_err());
+
+            // Results only hold evaluations of the last run
+            engine.set_check_gradient_sign(false);
+            engine.begin_run();
+            engine.evaluate(py, &[0.2, 0.0, 0.0], "").unwrap();
+            assert_eq!(engine.call_stats().n_gradient, 1);
+            let best = engine.best_result(&molecule).unwrap();
+            assert_eq!(best.energies.len(), 1);
+            assert!((best.energies[0] - 0.04).abs() < 1e-12);
+        });
+    }
+
+    #[test]
+    fn test_last_result_of_reused_engine() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (molecule, mut engine) = harmonic_engine(py);
+            // A not converged run, then a second run on the same engine
+            engine.begin_run();
+            for x in [0.3, 0.2, 0.1] {
+                engine.evaluate(py, &[x, 0.0, 0.0], "").unwrap();
+            }
+            engine.begin_run();
+            engine.evaluate(py, &[0.0, 0.4, 0.0], "").unwrap();
+            engine.evaluate(py, &[0.0, 0.5, 0.0], "").unwrap();
+            let last = engine.last_result(&molecule).unwrap();
+            assert_eq!(last.energies.len(), 2);
+            assert_eq!(last.gradients.len(), 2);
+            assert!((last.energies[1] - 0.25).abs() < 1e-12);
+            assert!((last.xyzs[0][1] - 0.4 * BOHR2ANG).abs() < 1e-12);
+        });
+    }
+
+    #[test]
+    fn test_optimizer_trust() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (_, mut engine) = harmonic_engine(py);
+            assert_eq!(engine.optimizer_trust(py), None);
+            let optimizer_cls = py.eval(c"type('Optimizer', (), {'trust': 0.1})", None, None);
+            let optimizer = optimizer_cls.unwrap().call0().unwrap();
+            engine.set_optimizer(&optimizer).unwrap();
+            assert_eq!(engine.optimizer_trust(py), Some(0.1));
+            // Only weakly referenced
+            drop(optimizer);
+            assert_eq!(engine.optimizer_trust(py), None);
+        });
+    }
+
+    /// Driver recording the states passed by atom data.
+    struct StateRecorder(Arc<std::sync::Mutex<Vec<usize>>>);
+
+    impl GeomDriverAPI for StateRecorder {
+        fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+            Ok(GradOutput::new(0.0, vec![0.0; coords.len()]))
+        }
+
+        fn set_atom_data(&mut self, atom_data: &AtomData) {
+            self.0.lock().unwrap().push(atom_data.state);
+        }
+    }
+
+    #[test]
+    fn test_set_state() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let (molecule, _) = harmonic_engine(py);
+            let states = Arc::new(std::sync::Mutex::new(vec![]));
+
+            // State set before the driver
+            let mut engine = EngineMixin::new(molecule.clone()).unwrap();
+            engine.set_state(1);
+            engine.set_driver(&PyGeomDriver::from(StateRecorder(states.clone())));
+            assert_eq!(*states.lock().unwrap(), vec![1]);
+
+            // State set after the driver is passed again
+            states.lock().unwrap().clear();
+            let mut engine = EngineMixin::new(molecule).unwrap();
+            engine.set_driver(&PyGeomDriver::from(StateRecorder(states.clone())));
+            engine.set_state(2);
+            assert_eq!(*states.lock().unwrap(), vec![0, 2]);
+        });
+    }
+
+    #[test]
+    fn test_atomic_rms_max() {
+        let (rms, max) = atomic_rms_max(&[3.0, 4.0, 0.0, 0.0, 0.0, 0.0]);
+        assert!((rms - 12.5_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(max, 5.0);
+        assert_eq!(atomic_rms_max(&[]), (0.0, 0.0));
+    }
+}
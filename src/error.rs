@@ -0,0 +1,170 @@
+//! Typed errors of the optimization entry points.
+//!
+//! geomeTRIC reports all failures as python exceptions. [`GeometricError`]
+//! classifies them by failure mode, keeping the original [`PyErr`] as source
+//! for debugging. Lower-level helpers handling python objects (molecules,
+//! parameter dictionaries) keep returning [`PyResult`]; both convert into
+//! each other with `?`.
+
+use std::fmt;
+
+use crate::interface::DriverFailed;
+use crate::version::UnsupportedGeometricVersion;
+use pyo3::exceptions::{PyImportError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+/// Failure modes of geomeTRIC runs.
+///
+/// - `NotConverged`: geomeTRIC did not converge within `maxiter` steps
+///   (`GeomOptNotConvergedError`).
+/// - `Engine`: Calculation of the engine failed (geomeTRIC's `EngineError`, or
+///   a [`DriverError`](crate::interface::DriverError) of the driver).
+/// - `InvalidParameters`: Parameters or input rejected (`ValueError`,
+///   `TypeError`, geomeTRIC's `ParamError` and `InputError`).
+/// - `NotInstalled`: The geomeTRIC python package can not be imported.
+/// - `Unsupported`: The feature is not available in the installed geomeTRIC
+///   version (see [`geometric_version`](crate::version::geometric_version)).
+/// - `Python`: Any other python exception.
+#[derive(Debug)]
+pub enum GeometricError {
+    NotConverged(PyErr),
+    Engine(PyErr),
+    InvalidParameters(PyErr),
+    NotInstalled(PyErr),
+    Unsupported(PyErr),
+    Python(PyErr),
+}
+
+impl GeometricError {
+    /// The original python exception.
+    pub fn py_err(&self) -> &PyErr {
+        match self {
+            GeometricError::NotConverged(err)
+            | GeometricError::Engine(err)
+            | GeometricError::InvalidParameters(err)
+            | GeometricError::NotInstalled(err)
+            | GeometricError::Unsupported(err)
+            | GeometricError::Python(err) => err,
+        }
+    }
+
+    /// Convert into the original python exception.
+    pub fn into_py_err(self) -> PyErr {
+        match self {
+            GeometricError::NotConverged(err)
+            | GeometricError::Engine(err)
+            | GeometricError::InvalidParameters(err)
+            | GeometricError::NotInstalled(err)
+            | GeometricError::Unsupported(err)
+            | GeometricError::Python(err) => err,
+        }
+    }
+}
+
+impl fmt::Display for GeometricError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            GeometricError::NotConverged(_) => "Optimization not converged",
+            GeometricError::Engine(_) => "Engine calculation failed",
+            GeometricError::InvalidParameters(_) => "Invalid parameters",
+            GeometricError::NotInstalled(_) => "geomeTRIC is not installed",
+            GeometricError::Unsupported(_) => "Unsupported by installed geomeTRIC",
+            GeometricError::Python(_) => "Python exception",
+        };
+        write!(f, "{}: {}", kind, self.py_err())
+    }
+}
+
+impl std::error::Error for GeometricError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.py_err())
+    }
+}
+
+impl From<PyErr> for GeometricError {
+    fn from(err: PyErr) -> Self {
+        Python::with_gil(|py| {
+            if err.is_instance_of::<DriverFailed>(py) {
+                return GeometricError::Engine(err);
+            }
+            if err.is_instance_of::<UnsupportedGeometricVersion>(py) {
+                return GeometricError::Unsupported(err);
+            }
+            if err.is_instance_of::<PyImportError>(py) {
+                let name = err.value(py).getattr("name").and_then(|n| n.extract::<String>());
+                if name.is_ok_and(|name| name == "geometric" || name.starts_with("geometric.")) {
+                    return GeometricError::NotInstalled(err);
+                }
+            }
+            let names = class_names(py, &err);
+            let is = |name: &str| names.iter().any(|n| n == name);
+            if is("GeomOptNotConvergedError") {
+                GeometricError::NotConverged(err)
+            } else if is("EngineError") {
+                GeometricError::Engine(err)
+            } else if is("ParamError")
+                || is("InputError")
+                || err.is_instance_of::<PyValueError>(py)
+                || err.is_instance_of::<PyTypeError>(py)
+            {
+                GeometricError::InvalidParameters(err)
+            } else {
+                GeometricError::Python(err)
+            }
+        })
+    }
+}
+
+/// Names of the exception class and its bases.
+///
+/// Exceptions of geomeTRIC are matched by class name, so that classification
+/// does not depend on importing `geometric.errors`.
+fn class_names(py: Python<'_>, err: &PyErr) -> Vec<String> {
+    err.get_type(py)
+        .mro()
+        .iter()
+        .filter_map(|cls| cls.getattr("__name__").ok()?.extract::<String>().ok())
+        .collect()
+}
+
+/// Whether the exception is geomeTRIC's `GeomOptNotConvergedError`.
+pub(crate) fn is_not_converged(py: Python<'_>, err: &PyErr) -> bool {
+    class_names(py, err).iter().any(|name| name == "GeomOptNotConvergedError")
+}
+
+impl From<std::io::Error> for GeometricError {
+    fn from(err: std::io::Error) -> Self {
+        GeometricError::Python(err.into())
+    }
+}
+
+impl From<GeometricError> for PyErr {
+    fn from(err: GeometricError) -> PyErr {
+        err.into_py_err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::DriverError;
+    use pyo3::exceptions::{PyModuleNotFoundError, PyRuntimeError};
+
+    #[test]
+    fn test_classify() {
+        pyo3::prepare_freethreaded_python();
+        let err = GeometricError::from(PyErr::from(DriverError::CalculationFailed("SCF".into())));
+        assert!(matches!(err, GeometricError::Engine(_)));
+        let err = GeometricError::from(PyValueError::new_err("bad"));
+        assert!(matches!(err, GeometricError::InvalidParameters(_)));
+        let err = Python::with_gil(|py| {
+            let missing = PyModuleNotFoundError::new_err("No module named 'geometric'");
+            missing.value(py).setattr("name", "geometric").unwrap();
+            GeometricError::from(missing)
+        });
+        assert!(matches!(err, GeometricError::NotInstalled(_)));
+        let err = GeometricError::from(PyRuntimeError::new_err("other"));
+        assert!(matches!(err, GeometricError::Python(_)));
+        assert!(err.to_string().starts_with("Python exception: RuntimeError: other"));
+    }
+}
@@ -0,0 +1,118 @@
+//! Python module exposing drivers of this crate to python users.
+//!
+//! This is the reverse of the usual direction: a python geomeTRIC user imports
+//! the module and uses rust drivers as custom engines:
+//!
+//! ```python
+//! import geometric, geometric_pyo3
+//!
+//! molecule = geometric.molecule.Molecule("input.xyz")
+//! driver = geometric_pyo3.create_driver("my_potential")
+//! engine = geometric_pyo3.make_engine(molecule, driver)
+//! geometric.optimize.run_optimizer(customengine=engine, input="input.xyz")
+//! ```
+//!
+//! Drivers are made available by [`register_driver`], which the crate building
+//! the extension calls before python uses them (e.g. in its own `#[pymodule]`
+//! function, which then calls [`register_module`]). With feature
+//! `extension-module`, this crate also defines the `geometric_pyo3` module
+//! itself, exported from the `cdylib` of the extension crate (e.g. built by
+//! maturin).
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use crate::engine::{get_pyo3_engine_cls, init_pyo3_engine, EngineMixin};
+use crate::interface::PyGeomDriver;
+use pyo3::exceptions::PyKeyError;
+use pyo3::prelude::*;
+
+/// Factory creating a new driver instance.
+type DriverFactory = Arc<dyn Fn() -> PyGeomDriver + Send + Sync>;
+
+/// Registered driver factories, by name.
+static DRIVERS: Mutex<BTreeMap<String, DriverFactory>> = Mutex::new(BTreeMap::new());
+
+/// Register a driver under `name`, replacing a driver of the same name.
+///
+/// `factory` is called for each `create_driver(name)` from python, so every
+/// engine gets its own driver instance.
+pub fn register_driver(
+    name: impl Into<String>,
+    factory: impl Fn() -> PyGeomDriver + Send + Sync + 'static,
+) {
+    DRIVERS.lock().unwrap().insert(name.into(), Arc::new(factory));
+}
+
+/// Names of registered drivers, sorted.
+#[pyfunction]
+pub fn driver_names() -> Vec<String> {
+    DRIVERS.lock().unwrap().keys().cloned().collect()
+}
+
+/// Create a new instance of the driver registered under `name`.
+#[pyfunction]
+pub fn create_driver(name: &str) -> PyResult<PyGeomDriver> {
+    // The lock is released before calling the factory.
+    let factory = DRIVERS.lock().unwrap().get(name).cloned();
+    match factory {
+        Some(factory) => Ok(factory()),
+        None => Err(PyKeyError::new_err(format!(
+            "No driver registered as {:?}; registered drivers: {:?}",
+            name,
+            driver_names()
+        ))),
+    }
+}
+
+/// The `PyO3Engine` class (see [`get_pyo3_engine_cls`]).
+#[pyfunction]
+pub fn engine_class() -> PyResult<PyObject> {
+    get_pyo3_engine_cls()
+}
+
+/// Create a `PyO3Engine` of the molecule with driver set (see
+/// [`init_pyo3_engine`]).
+#[pyfunction]
+pub fn make_engine(molecule: PyObject, driver: PyGeomDriver) -> PyResult<PyObject> {
+    init_pyo3_engine(&molecule, &driver)
+}
+
+/// Add the classes and functions of this module to `m`.
+pub fn register_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGeomDriver>()?;
+    m.add_class::<EngineMixin>()?;
+    m.add_function(wrap_pyfunction!(driver_names, m)?)?;
+    m.add_function(wrap_pyfunction!(create_driver, m)?)?;
+    m.add_function(wrap_pyfunction!(engine_class, m)?)?;
+    m.add_function(wrap_pyfunction!(make_engine, m)?)?;
+    Ok(())
+}
+
+/// The `geometric_pyo3` python module (feature `extension-module`).
+#[cfg(feature = "extension-module")]
+#[pymodule]
+fn geometric_pyo3(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    register_module(m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::GradOutput;
+
+    #[test]
+    fn test_driver_registry() {
+        pyo3::prepare_freethreaded_python();
+        register_driver("zero", || {
+            PyGeomDriver::from_fn(|coords: &[f64], _: &str| {
+                GradOutput::new(0.0, vec![0.0; coords.len()])
+            })
+        });
+        assert!(driver_names().contains(&"zero".to_string()));
+        let driver = create_driver("zero").unwrap();
+        let output = driver.pointer.lock().unwrap().calc_new(&[0.0; 3], "").unwrap();
+        assert_eq!(output.gradient, vec![0.0; 3]);
+        assert!(create_driver("unknown").is_err());
+    }
+}
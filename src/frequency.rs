@@ -0,0 +1,244 @@
+//! Vibrational frequency analysis by geomeTRIC (`geometric.normal_modes`).
+
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::PyGeomDriver;
+use crate::molecule::Molecule;
+use crate::result::BOHR2ANG;
+use pyo3::exceptions::{PyNotImplementedError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use tempfile::TempDir;
+
+/// Conversion factor from Hartree to cm⁻¹.
+const HARTREE2WAVENUMBER: f64 = 219474.6313705;
+
+/// Boltzmann constant in Hartree/K.
+const BOLTZMANN_HARTREE: f64 = 3.166811563e-6;
+
+/// Conversion factor from amu to electron mass.
+const AMU2AU: f64 = 1822.888486;
+
+/// Frequencies (cm⁻¹) below which modes are not sampled.
+const WIGNER_MIN_FREQUENCY: f64 = 1.0;
+
+/// Harmonic vibrational frequencies.
+///
+/// - `frequencies`: Frequencies in cm⁻¹, excluding translations and rotations.
+///   Imaginary frequencies are represented as negative values (the convention
+///   of geomeTRIC).
+/// - `modes`: Cartesian normal mode vector of each frequency (as given by
+///   geomeTRIC), flattened (natom * 3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frequencies {
+    pub frequencies: Vec<f64>,
+    pub modes: Vec<Vec<f64>>,
+}
+
+impl Frequencies {
+    /// Harmonic zero-point energy in Hartree; imaginary frequencies are
+    /// excluded.
+    pub fn zero_point_energy(&self) -> f64 {
+        0.5 * self.frequencies.iter().filter(|&&f| f > 0.0).sum::<f64>() / HARTREE2WAVENUMBER
+    }
+
+    /// Number of imaginary frequencies.
+    ///
+    /// Zero for a true minimum, and one for a first-order saddle point
+    /// (transition state).
+    pub fn n_imaginary(&self) -> usize {
+        self.frequencies.iter().filter(|&&f| f < 0.0).count()
+    }
+}
+
+/// Options of Wigner sampling, see [`Frequencies::wigner_sample`].
+///
+/// - `n_samples`: Number of sampled initial conditions.
+/// - `temperature`: Temperature in K; `0.0` samples the vibrational ground
+///   state.
+/// - `seed`: Seed of the random number generator (numpy's `default_rng`), for
+///   reproducible samples.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WignerOptions {
+    pub n_samples: usize,
+    pub temperature: f64,
+    pub seed: Option<u64>,
+}
+
+/// Initial conditions sampled from the Wigner distribution.
+///
+/// - `geometries`: Coordinates of each sample in Angstrom, flattened (natom *
+///   3).
+/// - `velocities`: Velocities of each sample in atomic units (Bohr per atomic
+///   time unit), flattened (natom * 3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct WignerSamples {
+    pub geometries: Vec<Vec<f64>>,
+    pub velocities: Vec<Vec<f64>>,
+}
+
+impl Frequencies {
+    /// Sample initial conditions of dynamics from the harmonic Wigner
+    /// distribution around the geometry of `molecule` (the geometry of the
+    /// frequency calculation).
+    ///
+    /// Imaginary and near-zero frequency modes are not sampled. Masses are
+    /// those of geomeTRIC's periodic table.
+    pub fn wigner_sample(
+        &self,
+        molecule: &Molecule,
+        options: &WignerOptions,
+    ) -> PyResult<WignerSamples> {
+        if options.temperature < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "Temperature must be non-negative, got {}",
+                options.temperature
+            )));
+        }
+        let xyz = molecule.xyzs()?.swap_remove(0);
+        let nmode = self.frequencies.len();
+        let (masses, normals) = Python::with_gil(|py| -> PyResult<(Vec<f64>, Vec<f64>)> {
+            let table = py.import("geometric.molecule")?.getattr("PeriodicTable")?;
+            let masses = molecule
+                .elem()?
+                .iter()
+                .map(|elem| table.get_item(elem)?.extract::<f64>())
+                .collect::<PyResult<Vec<_>>>()?;
+            let rng = py.import("numpy.random")?.call_method1("default_rng", (options.seed,))?;
+            let normals = rng
+                .call_method1("standard_normal", (options.n_samples * nmode * 2,))?
+                .call_method0("tolist")?
+                .extract()?;
+            Ok((masses, normals))
+        })?;
+        let mut samples = WignerSamples { geometries: vec![], velocities: vec![] };
+        for sample in 0..options.n_samples {
+            let draws = &normals[sample * nmode * 2..(sample + 1) * nmode * 2];
+            let (displacement, velocity) =
+                wigner_displacement(self, &masses, options.temperature, draws);
+            samples
+                .geometries
+                .push(xyz.iter().zip(displacement).map(|(x, d)| x + d * BOHR2ANG).collect());
+            samples.velocities.push(velocity);
+        }
+        Ok(samples)
+    }
+}
+
+/// Cartesian displacement (Bohr) and velocity (a.u.) of one Wigner sample.
+///
+/// - `masses`: Atomic masses in amu.
+/// - `draws`: Standard normal random numbers, two per mode (position and
+///   momentum).
+fn wigner_displacement(
+    frequencies: &Frequencies,
+    masses: &[f64],
+    temperature: f64,
+    draws: &[f64],
+) -> (Vec<f64>, Vec<f64>) {
+    let sqrt_mass = masses.iter().map(|m| (m * AMU2AU).sqrt()).collect::<Vec<_>>();
+    let mut displacement = vec![0.0; masses.len() * 3];
+    let mut velocity = vec![0.0; masses.len() * 3];
+    for (i, (&freq, mode)) in frequencies.frequencies.iter().zip(&frequencies.modes).enumerate() {
+        if freq < WIGNER_MIN_FREQUENCY {
+            continue;
+        }
+        // mass-weighted orthonormal mode vector
+        let mut l = mode.iter().enumerate().map(|(k, c)| c * sqrt_mass[k / 3]).collect::<Vec<_>>();
+        let norm = l.iter().map(|x| x * x).sum::<f64>().sqrt();
+        l.iter_mut().for_each(|x| *x /= norm);
+
+        let omega = freq / HARTREE2WAVENUMBER;
+        let coth = match temperature {
+            0.0 => 1.0,
+            _ => 1.0 / (omega / (2.0 * BOLTZMANN_HARTREE * temperature)).tanh(),
+        };
+        let q = draws[2 * i] * (coth / (2.0 * omega)).sqrt();
+        let p = draws[2 * i + 1] * (omega * coth / 2.0).sqrt();
+        for (k, lk) in l.iter().enumerate() {
+            displacement[k] += q * lk / sqrt_mass[k / 3];
+            velocity[k] += p * lk / sqrt_mass[k / 3];
+        }
+    }
+    (displacement, velocity)
+}
+
+/// Run frequency analysis at the (first frame) geometry of the molecule.
+///
+/// The Cartesian Hessian is the driver's analytic Hessian if available (see
+/// [`GeomDriverAPI::calc_hessian`](crate::interface::GeomDriverAPI::calc_hessian)),
+/// otherwise it is evaluated by geomeTRIC's `calc_cartesian_hessian`, by finite
+/// difference of gradients from the driver (6N gradient evaluations).
+pub fn run_frequencies(
+    molecule: &Molecule,
+    driver: &PyGeomDriver,
+) -> Result<Frequencies, GeometricError> {
+    let custom_engine = init_pyo3_engine(&molecule.pyobj, driver)?;
+    // Scratch directory of hessian calculation; removed when dropped.
+    let scratch = TempDir::new()?;
+    Python::with_gil(|py| {
+        let normal_modes = py.import("geometric.normal_modes")?;
+        let numpy = py.import("numpy")?;
+
+        let xyz = molecule.xyzs()?.swap_remove(0);
+        let coords = xyz.iter().map(|x| x / BOHR2ANG).collect::<Vec<_>>();
+        let coords = numpy.call_method1("array", (PyList::new(py, coords)?,))?;
+
+        let dirname = scratch.path().to_str().unwrap();
+        let hessian = match custom_engine.call_method1(py, "calc_hessian", (&coords, dirname)) {
+            Ok(hessian) => hessian.into_bound(py),
+            Err(err) if err.is_instance_of::<PyNotImplementedError>(py) => {
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("read_data", false)?;
+                normal_modes
+                    .getattr("calc_cartesian_hessian")?
+                    .call((&coords, &molecule.pyobj, custom_engine, dirname), Some(&kwargs))?
+            },
+            Err(err) => return Err(err),
+        };
+
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("elem", molecule.elem()?)?;
+        kwargs.set_item("verbose", 0)?;
+        let analysis =
+            normal_modes.getattr("frequency_analysis")?.call((&coords, hessian), Some(&kwargs))?;
+        let frequencies = analysis.get_item(0)?.call_method0("tolist")?.extract()?;
+        let modes = analysis
+            .get_item(1)?
+            .try_iter()?
+            .map(|mode| mode?.call_method0("flatten")?.call_method0("tolist")?.extract())
+            .collect::<PyResult<_>>()?;
+        Ok(Frequencies { frequencies, modes })
+    })
+    .map_err(GeometricError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_point_energy() {
+        let frequencies =
+            Frequencies { frequencies: vec![-500.0, 1600.0, 3700.0, 3800.0], modes: vec![] };
+        assert_eq!(frequencies.n_imaginary(), 1);
+        assert!((frequencies.zero_point_energy() - 0.0207313).abs() < 1.0e-7);
+    }
+
+    #[test]
+    fn test_wigner_displacement() {
+        // H2 stretching mode, ground state
+        let frequencies = Frequencies {
+            frequencies: vec![4400.0],
+            modes: vec![vec![0.0, 0.0, -1.0, 0.0, 0.0, 1.0]],
+        };
+        let masses = [1.008, 1.008];
+        let (displacement, velocity) = wigner_displacement(&frequencies, &masses, 0.0, &[1.0, 0.0]);
+        let omega = 4400.0 / HARTREE2WAVENUMBER;
+        let sqrt_mass = (1.008 * AMU2AU).sqrt();
+        let expected = (1.0 / (2.0 * omega)).sqrt() / 2.0_f64.sqrt() / sqrt_mass;
+        assert!((displacement[5] - expected).abs() < 1.0e-12);
+        assert!((displacement[2] + expected).abs() < 1.0e-12);
+        assert!(velocity.iter().all(|&v| v == 0.0));
+    }
+}
@@ -0,0 +1,147 @@
+//! Numerical-gradient check of driver implementations.
+//!
+//! This is intended for driver development: unit tests of a driver can assert
+//! [`GradCheckReport::passed`] at a few representative geometries.
+
+use crate::interface::{DriverError, GeomDriverAPI, PyGeomDriver};
+use crate::molecule::Molecule;
+use crate::result::BOHR2ANG;
+use pyo3::prelude::*;
+use tempfile::TempDir;
+
+/// Comparison of the driver's gradient against central finite differences of
+/// its energy.
+///
+/// - `analytic`: Gradient reported by the driver (Hartree/Bohr).
+/// - `numerical`: Finite-difference gradient (Hartree/Bohr).
+/// - `tol`: Tolerance of absolute error per component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradCheckReport {
+    pub analytic: Vec<f64>,
+    pub numerical: Vec<f64>,
+    pub tol: f64,
+}
+
+impl GradCheckReport {
+    /// Absolute error of each gradient component.
+    pub fn errors(&self) -> Vec<f64> {
+        self.analytic.iter().zip(&self.numerical).map(|(a, n)| (a - n).abs()).collect()
+    }
+
+    /// Maximum absolute error over all components.
+    pub fn max_error(&self) -> f64 {
+        self.errors().into_iter().fold(0.0, f64::max)
+    }
+
+    /// Indices (in the flattened natom * 3 gradient) of components whose
+    /// error exceeds `tol`.
+    pub fn failed_components(&self) -> Vec<usize> {
+        self.errors().iter().enumerate().filter(|(_, &e)| e > self.tol).map(|(i, _)| i).collect()
+    }
+
+    /// Whether all components are within `tol`.
+    pub fn passed(&self) -> bool {
+        self.max_error() <= self.tol
+    }
+}
+
+/// Check the driver's gradient at the (first frame) geometry of the molecule.
+///
+/// - `h`: Finite-difference step (Bohr); `1e-4` to `1e-3` is usually adequate.
+/// - `tol`: Tolerance of absolute error per component (Hartree/Bohr).
+///
+/// This costs 6N + 1 driver evaluations.
+pub fn check_gradient(
+    driver: &PyGeomDriver,
+    molecule: &Molecule,
+    h: f64,
+    tol: f64,
+) -> PyResult<GradCheckReport> {
+    let xyz = molecule.xyzs()?.swap_remove(0);
+    let coords = xyz.iter().map(|x| x / BOHR2ANG).collect::<Vec<_>>();
+    let scratch = TempDir::new()?;
+    let mut driver = driver.pointer.lock().unwrap();
+    Ok(check_gradient_at(&mut *driver, &coords, scratch.path().to_str().unwrap(), h, tol)?)
+}
+
+/// Check the driver's gradient at coordinates (Bohr, flattened natom * 3),
+/// without a molecule.
+///
+/// The driver is evaluated in a temporary directory; see [`check_gradient`]
+/// for the other arguments.
+pub fn check_gradient_coords(
+    driver: &mut dyn GeomDriverAPI,
+    coords: &[f64],
+    h: f64,
+    tol: f64,
+) -> PyResult<GradCheckReport> {
+    let scratch = TempDir::new()?;
+    Ok(check_gradient_at(driver, coords, scratch.path().to_str().unwrap(), h, tol)?)
+}
+
+/// Check the driver's gradient at coordinates (Bohr, flattened natom * 3),
+/// evaluating the driver in `dirname`.
+///
+/// See [`check_gradient`] for the arguments.
+pub fn check_gradient_at(
+    driver: &mut dyn GeomDriverAPI,
+    coords: &[f64],
+    dirname: &str,
+    h: f64,
+    tol: f64,
+) -> Result<GradCheckReport, DriverError> {
+    let analytic = driver.calc_new(coords, dirname)?.gradient;
+    let mut displaced = coords.to_vec();
+    let numerical = (0..coords.len())
+        .map(|i| {
+            displaced[i] = coords[i] + h;
+            let e_plus = driver.calc_new(&displaced, dirname)?.energy;
+            displaced[i] = coords[i] - h;
+            let e_minus = driver.calc_new(&displaced, dirname)?.energy;
+            displaced[i] = coords[i];
+            Ok((e_plus - e_minus) / (2.0 * h))
+        })
+        .collect::<Result<_, DriverError>>()?;
+    Ok(GradCheckReport { analytic, numerical, tol })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::GradOutput;
+
+    /// `E = sum(x^3)`, with gradient optionally scaled by mistake.
+    struct Cubic {
+        scale: f64,
+    }
+
+    impl GeomDriverAPI for Cubic {
+        fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+            let energy = coords.iter().map(|x| x.powi(3)).sum();
+            Ok(GradOutput::new(energy, coords.iter().map(|x| self.scale * 3.0 * x * x).collect()))
+        }
+    }
+
+    #[test]
+    fn test_check_gradient() {
+        let coords = [0.1, -0.2, 0.3, 0.4, 0.5, -0.6];
+        let report =
+            check_gradient_at(&mut Cubic { scale: 1.0 }, &coords, "", 1.0e-4, 1.0e-6).unwrap();
+        assert!(report.passed());
+
+        let report =
+            check_gradient_at(&mut Cubic { scale: 0.5 }, &coords, "", 1.0e-4, 1.0e-6).unwrap();
+        assert!(!report.passed());
+        assert_eq!(report.failed_components(), (0..6).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_check_gradient_coords() {
+        let coords = [0.1, -0.2, 0.3];
+        let report = check_gradient_coords(&mut Cubic { scale: 1.0 }, &coords, 1.0e-4, 1.0e-6);
+        let report = report.unwrap();
+        assert!(report.passed());
+        assert_eq!(report.errors().len(), 3);
+        assert!(report.max_error() < 1.0e-6);
+    }
+}
@@ -0,0 +1,281 @@
+//! Exchange of Cartesian Hessians with geomeTRIC through text files.
+//!
+//! geomeTRIC reads an initial Hessian with `hessian = "file:<path>"`, and
+//! writes the Hessians it computes to `<prefix>.tmp/hessian/hessian.txt`. Both
+//! are plain text matrices (as by `numpy.savetxt`) of the Cartesian Hessian in
+//! Hartree/Bohr², with natom * 3 rows and columns.
+//!
+//! For drivers without analytic second derivatives, [`numerical_hessian`]
+//! computes the Hessian from gradients in rust, e.g. to be given as
+//! [`RunOptions::initial_hessian`](crate::optimize::RunOptions) of a
+//! transition-state search.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::interface::{DriverError, GeomDriverAPI, GeomDriverSync};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// When geomeTRIC computes the Cartesian Hessian (`hessian` keyword).
+///
+/// - `Never`: Use a guess Hessian (geomeTRIC default).
+/// - `First`: At the starting geometry.
+/// - `Last`: At the final geometry, with a frequency analysis.
+/// - `FirstLast`: At the starting and final geometries.
+/// - `Each`: At every step (expensive).
+/// - `Stop`: At the starting geometry, then stop without optimizing.
+/// - `File`: Read the initial Hessian from a file, see [`write_hessian`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HessianMode {
+    Never,
+    First,
+    Last,
+    FirstLast,
+    Each,
+    Stop,
+    File(PathBuf),
+}
+
+impl HessianMode {
+    /// Value of the `hessian` keyword recognized by geomeTRIC.
+    pub fn to_param(&self) -> String {
+        match self {
+            HessianMode::Never => "never".into(),
+            HessianMode::First => "first".into(),
+            HessianMode::Last => "last".into(),
+            HessianMode::FirstLast => "first+last".into(),
+            HessianMode::Each => "each".into(),
+            HessianMode::Stop => "stop".into(),
+            HessianMode::File(path) => format!("file:{}", path.display()),
+        }
+    }
+}
+
+/// Path of the Hessian file written by geomeTRIC for output `prefix`.
+pub fn final_hessian_path(prefix: &str) -> PathBuf {
+    PathBuf::from(format!("{}.tmp", prefix)).join("hessian").join("hessian.txt")
+}
+
+/// Write a Cartesian Hessian (Hartree/Bohr², flattened natom * 3 by natom * 3)
+/// in the text format read by geomeTRIC.
+pub fn write_hessian(path: impl AsRef<Path>, hessian: &[f64]) -> PyResult<()> {
+    let ncoord = hessian_dim(hessian.len())?;
+    let mut text = String::new();
+    for row in hessian.chunks(ncoord) {
+        let row = row.iter().map(|h| format!("{:.18e}", h)).collect::<Vec<_>>();
+        writeln!(text, "{}", row.join(" ")).unwrap();
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Read a Cartesian Hessian (flattened natom * 3 by natom * 3) from a text
+/// file, e.g. the one written by geomeTRIC (see [`final_hessian_path`]).
+pub fn read_hessian(path: impl AsRef<Path>) -> PyResult<Vec<f64>> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let mut hessian = vec![];
+    let mut nrow = 0;
+    for line in text.lines().filter(|line| !line.trim().is_empty() && !line.starts_with('#')) {
+        for value in line.split_whitespace() {
+            hessian.push(value.parse::<f64>().map_err(|e| {
+                PyValueError::new_err(format!(
+                    "Invalid value {:?} in Hessian file {}: {}",
+                    value,
+                    path.display(),
+                    e
+                ))
+            })?);
+        }
+        nrow += 1;
+    }
+    if hessian_dim(hessian.len())? != nrow {
+        return Err(PyValueError::new_err(format!(
+            "Hessian file {} has {} rows, but {} values",
+            path.display(),
+            nrow,
+            hessian.len()
+        )));
+    }
+    Ok(hessian)
+}
+
+/// Cartesian Hessian (Hartree/Bohr², flattened natom * 3 by natom * 3) by
+/// central differences of the driver's gradients.
+///
+/// - `coords`: Coordinates in Bohr, flattened (natom * 3).
+/// - `dirname`: Directory passed to the driver.
+/// - `step`: Displacement (Bohr) of each coordinate; `1e-3` is usually
+///   adequate.
+///
+/// This costs 6N gradient evaluations. The result is symmetrized.
+pub fn numerical_hessian(
+    driver: &mut dyn GeomDriverAPI,
+    coords: &[f64],
+    dirname: &str,
+    step: f64,
+) -> Result<Vec<f64>, DriverError> {
+    check_displacement(coords, step)?;
+    let rows = (0..coords.len())
+        .map(|i| hessian_row(|x| Ok(driver.calc_new(x, dirname)?.gradient), coords, i, step))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(symmetrize(rows))
+}
+
+/// [`numerical_hessian`] with displaced gradients evaluated concurrently on
+/// `threads` threads.
+///
+/// The driver must be safe to call concurrently (see [`GeomDriverSync`]); all
+/// evaluations get the same `dirname`.
+pub fn numerical_hessian_parallel(
+    driver: &dyn GeomDriverSync,
+    coords: &[f64],
+    dirname: &str,
+    step: f64,
+    threads: usize,
+) -> Result<Vec<f64>, DriverError> {
+    check_displacement(coords, step)?;
+    let next = AtomicUsize::new(0);
+    let rows = Mutex::new(vec![None; coords.len()]);
+    let worker = || -> Result<(), DriverError> {
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            if i >= coords.len() {
+                return Ok(());
+            }
+            let row = hessian_row(|x| Ok(driver.calc_new(x, dirname)?.gradient), coords, i, step)?;
+            rows.lock().unwrap()[i] = Some(row);
+        }
+    };
+    thread::scope(|scope| {
+        let handles = (0..threads.max(1)).map(|_| scope.spawn(worker)).collect::<Vec<_>>();
+        handles.into_iter().try_for_each(|handle| handle.join().unwrap())
+    })?;
+    let rows = rows.into_inner().unwrap().into_iter().map(Option::unwrap).collect();
+    Ok(symmetrize(rows))
+}
+
+/// Check the arguments of finite differences.
+pub(crate) fn check_displacement(coords: &[f64], step: f64) -> Result<(), DriverError> {
+    if !(step.is_finite() && step > 0.0) {
+        return Err(DriverError::CalculationFailed(format!(
+            "Finite-difference step must be positive, got {}",
+            step
+        )));
+    }
+    if coords.is_empty() || !coords.len().is_multiple_of(3) {
+        return Err(DriverError::CalculationFailed(format!(
+            "Length of coordinates ({}) is not a multiple of 3",
+            coords.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Row `i` of the Hessian, the central difference of gradients along
+/// coordinate `i`.
+fn hessian_row(
+    mut gradient: impl FnMut(&[f64]) -> Result<Vec<f64>, DriverError>,
+    coords: &[f64],
+    i: usize,
+    step: f64,
+) -> Result<Vec<f64>, DriverError> {
+    let mut displaced = coords.to_vec();
+    displaced[i] = coords[i] + step;
+    let g_plus = gradient(&displaced)?;
+    displaced[i] = coords[i] - step;
+    let g_minus = gradient(&displaced)?;
+    if g_plus.len() != coords.len() || g_minus.len() != coords.len() {
+        return Err(DriverError::CalculationFailed(format!(
+            "Length of gradient ({}) does not match number of coordinates ({})",
+            g_plus.len(),
+            coords.len()
+        )));
+    }
+    Ok(g_plus.iter().zip(&g_minus).map(|(p, m)| (p - m) / (2.0 * step)).collect())
+}
+
+/// Flattened symmetric Hessian `(H + H^T) / 2` of rows `H`.
+fn symmetrize(rows: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = rows.len();
+    (0..n * n).map(|k| 0.5 * (rows[k / n][k % n] + rows[k % n][k / n])).collect()
+}
+
+/// Number of coordinates of a flattened square Hessian, being a multiple of 3.
+fn hessian_dim(len: usize) -> PyResult<usize> {
+    let ncoord = (len as f64).sqrt().round() as usize;
+    if ncoord == 0 || ncoord * ncoord != len || !ncoord.is_multiple_of(3) {
+        return Err(PyValueError::new_err(format!(
+            "Length of Hessian ({}) is not the square of number of coordinates (natom * 3)",
+            len
+        )));
+    }
+    Ok(ncoord)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::GradOutput;
+
+    #[test]
+    fn test_hessian_file_roundtrip() {
+        pyo3::prepare_freethreaded_python();
+        let hessian = (0..36).map(|i| (i as f64 - 17.5) * 1.0e-3 / 7.0).collect::<Vec<_>>();
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("hessian.txt");
+        write_hessian(&path, &hessian).unwrap();
+        assert_eq!(read_hessian(&path).unwrap(), hessian);
+        assert!(write_hessian(&path, &hessian[..30]).is_err());
+        assert_eq!(final_hessian_path("run/opt"), Path::new("run/opt.tmp/hessian/hessian.txt"));
+    }
+
+    /// `E = sum_i k_i x_i^2 / 2 + c x_0 x_1`, with constant Hessian.
+    struct Quadratic;
+
+    impl Quadratic {
+        fn hessian(n: usize) -> Vec<f64> {
+            let mut hessian = vec![0.0; n * n];
+            (0..n).for_each(|i| hessian[i * n + i] = 1.0 + i as f64);
+            hessian[1] = 0.3;
+            hessian[n] = 0.3;
+            hessian
+        }
+    }
+
+    impl GeomDriverSync for Quadratic {
+        fn calc_new(&self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+            let n = coords.len();
+            let hessian = Quadratic::hessian(n);
+            let gradient = (0..n)
+                .map(|i| (0..n).map(|j| hessian[i * n + j] * coords[j]).sum())
+                .collect::<Vec<f64>>();
+            let energy = 0.5 * gradient.iter().zip(coords).map(|(g, x)| g * x).sum::<f64>();
+            Ok(GradOutput::new(energy, gradient))
+        }
+    }
+
+    impl GeomDriverAPI for Quadratic {
+        fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+            GeomDriverSync::calc_new(self, coords, dirname)
+        }
+    }
+
+    #[test]
+    fn test_numerical_hessian() {
+        let coords = [0.1, -0.2, 0.3, 0.4, 0.5, -0.6];
+        let expected = Quadratic::hessian(6);
+        let serial = numerical_hessian(&mut Quadratic, &coords, "", 1.0e-3).unwrap();
+        let parallel = numerical_hessian_parallel(&Quadratic, &coords, "", 1.0e-3, 4).unwrap();
+        for ((s, p), e) in serial.iter().zip(&parallel).zip(&expected) {
+            assert!((s - e).abs() < 1.0e-8 && (p - e).abs() < 1.0e-8);
+        }
+        assert!(numerical_hessian(&mut Quadratic, &coords, "", 0.0).is_err());
+        assert!(numerical_hessian(&mut Quadratic, &coords[..4], "", 1.0e-3).is_err());
+    }
+}
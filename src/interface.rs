@@ -1,22 +1,223 @@
 //! Interface that electronic structure codes should implement.
 
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
 use std::mem::transmute;
+use std::pin::Pin;
+use std::ptr::NonNull;
 use std::sync::{Arc, Mutex};
 
+use crate::molecule::lattice_vectors;
+use crate::params::CoordSys;
+use pyo3::create_exception;
+use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
 
+create_exception!(
+    geometric_pyo3,
+    DriverFailed,
+    PyRuntimeError,
+    "Raised for a `DriverError` that is not passed to geomeTRIC as `EngineError`."
+);
+
 /// Gradient output from the electronic structure code.
 ///
-/// - `energy`: The energy of the system, scalar.
-/// - `gradient`: The gradient of the system, flattened (natom * 3), with
-///   dimension of coordinate (3) to be contiguous.
+/// - `energy`: The energy of the system in Hartree, scalar.
+/// - `gradient`: The gradient of the system in Hartree/Bohr, flattened (natom *
+///   3), with dimension of coordinate (3) to be contiguous.
+/// - `energy_components`: Optional labeled decomposition of the energy (e.g.
+///   electronic, nuclear repulsion, dispersion). This is only carried through
+///   to [`OptResult::energy_components`](crate::result::OptResult); geomeTRIC
+///   always optimizes against the scalar `energy`.
+/// - `extras`: Optional per-step properties (e.g. `"dipole"`, `"charges"`,
+///   `"s2"`). These are added to the result dictionary of `calc_new` passed to
+///   geomeTRIC, and carried through to
+///   [`OptResult::extras`](crate::result::OptResult). Keys `energy` and
+///   `gradient` are reserved and ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct GradOutput {
     pub energy: f64,
     pub gradient: Vec<f64>,
+    pub energy_components: HashMap<String, f64>,
+    pub extras: HashMap<String, serde_json::Value>,
+}
+
+impl GradOutput {
+    pub fn new(energy: f64, gradient: Vec<f64>) -> Self {
+        GradOutput { energy, gradient, ..Default::default() }
+    }
+
+    /// Attach labeled energy components.
+    pub fn with_energy_components(mut self, energy_components: HashMap<String, f64>) -> Self {
+        self.energy_components = energy_components;
+        self
+    }
+
+    /// Attach extra per-step properties.
+    pub fn with_extras(mut self, extras: HashMap<String, serde_json::Value>) -> Self {
+        self.extras = extras;
+        self
+    }
+
+    /// Whether energy and all gradient values are finite (not NaN or
+    /// infinity).
+    pub fn is_finite(&self) -> bool {
+        self.energy.is_finite() && self.gradient.iter().all(|g| g.is_finite())
+    }
+}
+
+/// Hessian output from the electronic structure code.
+///
+/// - `hessian`: Cartesian Hessian (Hartree/Bohr²), flattened row-major (natom *
+///   3, natom * 3), with coordinate ordering same to `coords`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HessOutput {
+    pub hessian: Vec<f64>,
+}
+
+/// Gradient calculation to be run remotely by a Work Queue worker, see
+/// [`GeomDriverAPI::prepare_wq_job`].
+///
+/// - `command`: Shell command run by the worker in its working directory.
+/// - `input_files`: Names of files (in `dirname`) sent to the worker.
+/// - `output_files`: Names of files copied back to `dirname` afterwards.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkQueueJob {
+    pub command: String,
+    pub input_files: Vec<String>,
+    pub output_files: Vec<String>,
+}
+
+/// Errors related to the electronic structure driver.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DriverError {
+    /// Energy increased when moving along the reported negative gradient.
+    ///
+    /// `energy_changes` are the energy differences (Hartree) of the probing
+    /// displacements, all being positive.
+    LikelyGradientSignError { energy_changes: Vec<f64> },
+    /// Energy or gradient returned by the driver is not finite (NaN or
+    /// infinity).
+    ///
+    /// `energy` is the returned energy, and `n_nonfinite_gradient` the number
+    /// of non-finite gradient values.
+    NonFiniteOutput { energy: f64, n_nonfinite_gradient: usize },
+    /// The calculation failed (e.g. SCF not converged), with a message from
+    /// the driver.
+    CalculationFailed(String),
+}
+
+impl fmt::Display for DriverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DriverError::LikelyGradientSignError { energy_changes } => write!(
+                f,
+                "Energy increased when stepping along the negative gradient (changes: {:?} Eh). \
+                 The driver probably returns forces (-gradient) instead of the gradient; \
+                 please check the sign convention of `GradOutput::gradient`.",
+                energy_changes
+            ),
+            DriverError::NonFiniteOutput { energy, n_nonfinite_gradient } => write!(
+                f,
+                "Driver returned non-finite output (energy: {}, non-finite gradient values: {}).",
+                energy, n_nonfinite_gradient
+            ),
+            DriverError::CalculationFailed(message) => {
+                write!(f, "Driver calculation failed: {}", message)
+            },
+        }
+    }
+}
+
+impl std::error::Error for DriverError {}
+
+impl From<DriverError> for PyErr {
+    fn from(err: DriverError) -> PyErr {
+        DriverFailed::new_err(err.to_string())
+    }
+}
+
+impl DriverError {
+    /// Convert to geomeTRIC's `geometric.errors.EngineError`, which is the
+    /// exception geomeTRIC expects from a failed engine calculation.
+    ///
+    /// Falls back to [`DriverFailed`] if `geometric.errors` can not be
+    /// imported.
+    pub fn into_engine_error(self, py: Python<'_>) -> PyErr {
+        let engine_error = py.import("geometric.errors").and_then(|m| m.getattr("EngineError"));
+        match engine_error.and_then(|cls| cls.call1((self.to_string(),))) {
+            Ok(err) => PyErr::from_value(err),
+            Err(_) => self.into(),
+        }
+    }
+}
+
+/// Per-atom data of the molecule, passed to the driver by
+/// [`GeomDriverAPI::set_atom_data`].
+///
+/// - `elem`: Element symbols.
+/// - `ghost`: Whether each atom is a ghost atom (see
+///   [`Molecule::set_ghost_atoms`](crate::molecule::Molecule::set_ghost_atoms)).
+/// - `charge`, `mult`: Total charge and spin multiplicity, if they have been
+///   set on the molecule (see [`MoleculeSpec`](crate::molecule::MoleculeSpec)).
+/// - `lattice`: Lattice vectors (Angstrom, one vector per row) of periodic
+///   systems (see
+///   [`Molecule::set_lattice_vectors`](crate::molecule::Molecule::set_lattice_vectors)).
+/// - `state`: Electronic state to be optimized, 0 for the ground state and `n`
+///   for the `n`-th excited state (see
+///   [`RunOptions::state`](crate::optimize::RunOptions)). geomeTRIC itself has
+///   no notion of the state of custom engines; drivers serving excited states
+///   compute energy and gradient of this state, and others should reject states
+///   they do not support.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AtomData {
+    pub elem: Vec<String>,
+    pub ghost: Vec<bool>,
+    pub charge: Option<i64>,
+    pub mult: Option<i64>,
+    pub lattice: Option<[[f64; 3]; 3]>,
+    pub state: usize,
+}
+
+impl AtomData {
+    /// Read atom data from a geomeTRIC molecule.
+    ///
+    /// Ghost atoms are read from the `ghost_atoms` attribute, charge and
+    /// multiplicity from the `charge` and `mult` attributes, and lattice
+    /// vectors from the `boxes` attribute, if present.
+    pub fn from_molecule(molecule: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let elem = molecule.getattr("elem")?.extract::<Vec<String>>()?;
+        let mut ghost = vec![false; elem.len()];
+        if molecule.hasattr("ghost_atoms")? {
+            for idx in molecule.getattr("ghost_atoms")?.extract::<Vec<usize>>()? {
+                if let Some(flag) = ghost.get_mut(idx) {
+                    *flag = true;
+                }
+            }
+        }
+        let optional = |key: &str| -> PyResult<Option<i64>> {
+            match molecule.hasattr(key)? {
+                true => molecule.getattr(key)?.extract().map(Some),
+                false => Ok(None),
+            }
+        };
+        Ok(AtomData {
+            elem,
+            ghost,
+            charge: optional("charge")?,
+            mult: optional("mult")?,
+            lattice: lattice_vectors(molecule)?,
+            state: 0,
+        })
+    }
 }
 
 /// Trait API to be implemented in electronic structure code for geomeTRIC PyO3
 /// binding.
+///
+/// Methods are called with the GIL released; drivers that call into Python
+/// must acquire it themselves with [`Python::with_gil`].
 pub trait GeomDriverAPI: Send {
     /// Calculate the energy and gradient of the system.
     ///
@@ -26,14 +227,239 @@ pub trait GeomDriverAPI: Send {
     /// # Arguments
     ///
     /// - `coords` - The coordinates of the system, flattened (natom * 3), with
-    ///   dimension of coordinate (3) to be contiguous.
+    ///   dimension of coordinate (3) to be contiguous. The unit is **Bohr**,
+    ///   while molecules are constructed in Angstrom by default (see
+    ///   [`LengthUnit`](crate::molecule::LengthUnit)).
     /// - `dirname` - The directory to run the calculation in. Can be set to
     ///   dummy if directory is not required for gradient computation.
     ///
     /// # Returns
     ///
-    /// A `GradOutput` struct containing the energy and gradient of the system.
-    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> GradOutput;
+    /// A `GradOutput` struct containing the energy and gradient of the system,
+    /// or a [`DriverError`] if the calculation failed (e.g. SCF convergence
+    /// failure). Errors abort the optimization cleanly as geomeTRIC's
+    /// `EngineError`; drivers should return errors instead of panicking.
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError>;
+
+    /// Calculate the analytic Cartesian Hessian of the system.
+    ///
+    /// Arguments are the same to [`GeomDriverAPI::calc_new`]. This corresponds
+    /// to the `calc_hessian` method of geomeTRIC's `Engine`, which is used
+    /// (e.g. for transition-state searches and frequency analysis) instead of
+    /// finite differences of gradients when available. The default
+    /// implementation returns `None`, so geomeTRIC falls back to finite
+    /// differences.
+    fn calc_hessian(&mut self, _coords: &[f64], _dirname: &str) -> Option<HessOutput> {
+        None
+    }
+
+    /// Receive per-atom data of the molecule to be optimized.
+    ///
+    /// This is called once when the driver is set to the engine, before any
+    /// `calc_new`. The default implementation ignores the data.
+    ///
+    /// Ghost atoms (`atom_data.ghost`) should carry basis functions but no
+    /// nuclear charge or electrons. They are still part of geomeTRIC's
+    /// coordinate system, so `calc_new` must return their gradient (which is
+    /// generally non-zero, from the basis functions centered on them).
+    fn set_atom_data(&mut self, _atom_data: &AtomData) {}
+
+    /// Prepare a gradient calculation to be run remotely by Work Queue.
+    ///
+    /// With a Work Queue port (see
+    /// [`OptimizerParams::port`](crate::params::OptimizerParams::port)),
+    /// geomeTRIC distributes independent gradients (finite-difference Hessians
+    /// and NEB images) to workers. The driver writes input files of the
+    /// calculation at `coords` (Bohr) to `dirname` and returns the job; when
+    /// the worker finished, output files are in `dirname` and read by
+    /// [`read_wq_result`](Self::read_wq_result). The default implementation
+    /// returns `None`, meaning Work Queue is not supported.
+    fn prepare_wq_job(
+        &mut self,
+        _coords: &[f64],
+        _dirname: &str,
+    ) -> Result<Option<WorkQueueJob>, DriverError> {
+        Ok(None)
+    }
+
+    /// Read the energy and gradient of a finished Work Queue job from the
+    /// output files in `dirname`, see [`prepare_wq_job`](Self::prepare_wq_job).
+    fn read_wq_result(&mut self, _dirname: &str) -> Result<GradOutput, DriverError> {
+        Err(DriverError::CalculationFailed("Work Queue is not supported by the driver".into()))
+    }
+
+    /// Coordinate system that works best with this driver.
+    ///
+    /// [`run_optimization`](crate::optimize::run_optimization) uses this only
+    /// when `coordsys` is not given explicitly in parameters. Precedence is:
+    /// explicit user option > driver preference > geomeTRIC default. The
+    /// default implementation has no preference.
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        None
+    }
+
+    /// Release resources held by the driver (e.g. remote connections, scratch
+    /// files).
+    ///
+    /// This is called by [`shutdown`](crate::runtime::shutdown) for drivers
+    /// registered by
+    /// [`register_finalizer`](crate::runtime::register_finalizer).
+    /// The default implementation does nothing.
+    fn finalize(&mut self) {}
+}
+
+/// Driver API of stateless (or internally synchronized) drivers.
+///
+/// Unlike [`GeomDriverAPI`], methods take `&self`, so one driver instance
+/// serves concurrent evaluations, e.g. optimizations of
+/// [`ParallelOptimizer`](crate::parallel::ParallelOptimizer) or parallel
+/// scans sharing the driver. See [`PyGeomDriver::from_sync`]; methods are
+/// the same to those of [`GeomDriverAPI`].
+pub trait GeomDriverSync: Send + Sync {
+    fn calc_new(&self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError>;
+
+    fn calc_hessian(&self, _coords: &[f64], _dirname: &str) -> Option<HessOutput> {
+        None
+    }
+
+    fn set_atom_data(&self, _atom_data: &AtomData) {}
+
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        None
+    }
+
+    /// Called for each handle registered by
+    /// [`register_finalizer`](crate::runtime::register_finalizer), so it may
+    /// run more than once.
+    fn finalize(&self) {}
+}
+
+/// Handle of a shared [`GeomDriverSync`].
+struct SharedDriver(Arc<dyn GeomDriverSync>);
+
+impl GeomDriverAPI for SharedDriver {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        self.0.calc_new(coords, dirname)
+    }
+
+    fn calc_hessian(&mut self, coords: &[f64], dirname: &str) -> Option<HessOutput> {
+        self.0.calc_hessian(coords, dirname)
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.0.set_atom_data(atom_data);
+    }
+
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        self.0.preferred_coordsys()
+    }
+
+    fn finalize(&mut self) {
+        self.0.finalize();
+    }
+}
+
+/// Closures computing the energy and gradient are drivers, for prototypes and
+/// tests; see [`PyGeomDriver::from_fn`].
+///
+/// Arguments are the same to [`GeomDriverAPI::calc_new`]. The closure can not
+/// report errors; implement [`GeomDriverAPI`] on a struct for that.
+impl<F> GeomDriverAPI for F
+where
+    F: FnMut(&[f64], &str) -> GradOutput + Send,
+{
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        Ok(self(coords, dirname))
+    }
+}
+
+/// Boxed future of [`AsyncGeomDriverAPI::calc_new`].
+pub type GradFuture<'a> =
+    Pin<Box<dyn Future<Output = Result<GradOutput, DriverError>> + Send + 'a>>;
+
+/// Asynchronous variant of [`GeomDriverAPI`], for drivers that dispatch
+/// gradients to remote workers.
+///
+/// Wrap the driver in [`AsyncDriver`] to use it as a [`GeomDriverAPI`]. The
+/// returned future is awaited with the GIL released, so other python threads
+/// can proceed while the remote computation runs.
+///
+/// # GIL-release safety
+///
+/// - The future must not access python objects without re-acquiring the GIL
+///   (`Python::with_gil`); this is mostly enforced by the `Send` bound, as
+///   GIL-bound references (`Bound`, `Python`) are not `Send`.
+/// - The future is driven on the thread running the optimization by the
+///   executor passed to [`AsyncDriver::new`], which should be the one of the
+///   runtime the driver is written for (timers, IO reactor).
+pub trait AsyncGeomDriverAPI: Send {
+    /// Calculate the energy and gradient of the system asynchronously.
+    ///
+    /// Arguments are the same to [`GeomDriverAPI::calc_new`].
+    fn calc_new<'a>(&'a mut self, coords: &'a [f64], dirname: &'a str) -> GradFuture<'a>;
+
+    /// See [`GeomDriverAPI::calc_hessian`]; this is blocking, as geomeTRIC
+    /// waits for the Hessian anyway.
+    fn calc_hessian(&mut self, _coords: &[f64], _dirname: &str) -> Option<HessOutput> {
+        None
+    }
+
+    /// See [`GeomDriverAPI::set_atom_data`].
+    fn set_atom_data(&mut self, _atom_data: &AtomData) {}
+
+    /// See [`GeomDriverAPI::preferred_coordsys`].
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        None
+    }
+
+    /// See [`GeomDriverAPI::finalize`].
+    fn finalize(&mut self) {}
+}
+
+/// Adapter to use an [`AsyncGeomDriverAPI`] as [`GeomDriverAPI`].
+///
+/// Each `calc_new` blocks until the future completes; the engine calls drivers
+/// with the GIL released, so other python threads are not blocked meanwhile.
+pub struct AsyncDriver<T: AsyncGeomDriverAPI> {
+    driver: T,
+    block_on: Box<BlockOn>,
+}
+
+/// Executor of [`AsyncDriver`].
+type BlockOn = dyn Fn(GradFuture<'_>) -> Result<GradOutput, DriverError> + Send;
+
+impl<T: AsyncGeomDriverAPI> AsyncDriver<T> {
+    /// Wrap `driver`, whose futures are run to completion by `block_on`, the
+    /// blocking entry point of an async runtime (e.g. `move |future|
+    /// handle.block_on(future)` with a tokio runtime `Handle`).
+    pub fn new<F>(driver: T, block_on: F) -> Self
+    where
+        F: Fn(GradFuture<'_>) -> Result<GradOutput, DriverError> + Send + 'static,
+    {
+        AsyncDriver { driver, block_on: Box::new(block_on) }
+    }
+}
+
+impl<T: AsyncGeomDriverAPI> GeomDriverAPI for AsyncDriver<T> {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        (self.block_on)(self.driver.calc_new(coords, dirname))
+    }
+
+    fn calc_hessian(&mut self, coords: &[f64], dirname: &str) -> Option<HessOutput> {
+        self.driver.calc_hessian(coords, dirname)
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.driver.set_atom_data(atom_data);
+    }
+
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        self.driver.preferred_coordsys()
+    }
+
+    fn finalize(&mut self) {
+        self.driver.finalize();
+    }
 }
 
 /// Python wrapper for the `GeomDriverAPI` trait implementations.
@@ -41,30 +467,244 @@ pub trait GeomDriverAPI: Send {
 /// `GeomDriverAPI` is defined as rust trait, which is not directly usable in
 /// Python. This makes the glue between the rust trait and the python class.
 ///
-/// # Safety
+/// # Lifetime
 ///
-/// This struct is marked as `unsafe` because it uses `transmute` to convert
-/// local lifetime to static lifetime.
-///
-/// If your class (to be implemented with trait `GeomDriverAPI`) have lifetime
-/// parameters, then this lifetime will be transmuted to static lifetime when it
-/// is converted to python object. As long as you don't disturb the lifetime of
-/// reference, this transmute should be safe.
+/// Python objects holding the driver may live arbitrarily long, so the
+/// conversion `From<T>` takes owned drivers (`T: 'static`) only. Drivers with
+/// lifetime parameters (borrowing local data) are lent by [`with_driver`]
+/// instead, which guarantees that the borrowed driver is not called after the
+/// borrow ends.
 #[pyclass]
 #[derive(Clone)]
 pub struct PyGeomDriver {
     pub pointer: Arc<Mutex<dyn GeomDriverAPI>>,
 }
 
-impl<T> From<T> for PyGeomDriver
+impl PyGeomDriver {
+    /// Driver from a closure computing the energy and gradient.
+    ///
+    /// ```ignore
+    /// let driver = PyGeomDriver::from_fn(|coords: &[f64], _dirname: &str| {
+    ///     let energy = coords.iter().map(|x| x * x).sum();
+    ///     GradOutput::new(energy, coords.iter().map(|x| 2.0 * x).collect())
+    /// });
+    /// ```
+    ///
+    /// The closure must own its captures (`move`); closures borrowing local
+    /// data are lent by [`with_driver`] instead.
+    pub fn from_fn<F>(f: F) -> Self
+    where
+        F: FnMut(&[f64], &str) -> GradOutput + Send + 'static,
+    {
+        f.into()
+    }
+
+    /// Handle of a shared driver.
+    ///
+    /// Each handle has its own lock, so evaluations through different handles
+    /// of the same driver run concurrently; create one handle per engine.
+    pub fn from_sync(driver: Arc<dyn GeomDriverSync>) -> Self {
+        SharedDriver(driver).into()
+    }
+}
+
+/// Driver borrowed by [`with_driver`], revoked when the scope ends.
+///
+/// The lifetime of the pointer is erased; it is cleared before the borrow
+/// ends.
+struct ScopedDriver {
+    driver: Option<NonNull<dyn GeomDriverAPI>>,
+}
+
+// Safety: the pointee is `Send` (as required by `GeomDriverAPI`), and is only
+// accessed under the mutex of `PyGeomDriver`.
+unsafe impl Send for ScopedDriver {}
+
+impl ScopedDriver {
+    fn get(&mut self) -> Option<&mut dyn GeomDriverAPI> {
+        // Safety: the pointer is valid and exclusively borrowed until cleared.
+        self.driver.as_mut().map(|driver| unsafe { driver.as_mut() })
+    }
+}
+
+impl GeomDriverAPI for ScopedDriver {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        match self.get() {
+            Some(driver) => driver.calc_new(coords, dirname),
+            None => Err(DriverError::CalculationFailed(
+                "Driver called after the end of its `with_driver` scope".to_string(),
+            )),
+        }
+    }
+
+    fn calc_hessian(&mut self, coords: &[f64], dirname: &str) -> Option<HessOutput> {
+        self.get()?.calc_hessian(coords, dirname)
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        if let Some(driver) = self.get() {
+            driver.set_atom_data(atom_data);
+        }
+    }
+
+    fn preferred_coordsys(&self) -> Option<CoordSys> {
+        // Safety: see `get`.
+        self.driver.as_ref().and_then(|driver| unsafe { driver.as_ref() }.preferred_coordsys())
+    }
+
+    fn finalize(&mut self) {
+        if let Some(driver) = self.get() {
+            driver.finalize();
+        }
+    }
+}
+
+/// Lend a driver to python for the duration of `f`.
+///
+/// This is the lifetime-safe alternative to converting a borrowing driver by
+/// `From`:
+///
+/// ```ignore
+/// let mut driver = ModelDriver { model: &mut model };
+/// let res = with_driver(&mut driver, |driver| {
+///     let custom_engine = init_pyo3_engine(&molecule, driver)?;
+///     run_optimization(custom_engine, &params, None)
+/// })?;
+/// ```
+///
+/// Python objects (e.g. engines) may outlive `f` while holding the driver;
+/// after `f` returns (or panics) the driver is revoked, and later calculations
+/// fail with [`DriverError::CalculationFailed`]. Calculations in flight on
+/// other threads are waited for.
+pub fn with_driver<T, R>(driver: &mut T, f: impl FnOnce(&PyGeomDriver) -> R) -> R
 where
     T: GeomDriverAPI,
+{
+    /// Clears the pointer of the scoped driver when dropped.
+    struct Revoke(Arc<Mutex<ScopedDriver>>);
+
+    impl Drop for Revoke {
+        fn drop(&mut self) {
+            let mut scoped = self.0.lock().unwrap_or_else(|err| err.into_inner());
+            scoped.driver = None;
+        }
+    }
+
+    let ptr = NonNull::from(driver as &mut dyn GeomDriverAPI);
+    // Safety: only the lifetime is erased; `Revoke` clears the pointer before
+    // the borrow of `driver` ends.
+    let ptr: NonNull<dyn GeomDriverAPI> = unsafe { transmute(ptr) };
+    let scoped = Arc::new(Mutex::new(ScopedDriver { driver: Some(ptr) }));
+    let _revoke = Revoke(scoped.clone());
+    f(&PyGeomDriver { pointer: scoped })
+}
+
+impl<T> From<T> for PyGeomDriver
+where
+    T: GeomDriverAPI + 'static,
 {
     fn from(driver: T) -> Self {
-        let a: Arc<Mutex<dyn GeomDriverAPI>> = Arc::new(Mutex::new(driver));
-        // Safety not checked, and should be provided by the caller.
-        // This will convert local lifetime (of `T`) to static lifetime (`'static`) for
-        // python calls.
-        unsafe { transmute(a) }
+        PyGeomDriver { pointer: Arc::new(Mutex::new(driver)) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+
+    /// Driver computing `E = sum(x^2)` on a worker thread.
+    struct RemoteHarmonic;
+
+    impl AsyncGeomDriverAPI for RemoteHarmonic {
+        fn calc_new<'a>(&'a mut self, coords: &'a [f64], _dirname: &'a str) -> GradFuture<'a> {
+            let coords = coords.to_vec();
+            let worker = thread::spawn(move || {
+                let energy = coords.iter().map(|x| x * x).sum();
+                Ok(GradOutput::new(energy, coords.iter().map(|x| 2.0 * x).collect()))
+            });
+            Box::pin(async move { worker.join().unwrap() })
+        }
+    }
+
+    /// Executor polling the future until it completes.
+    fn spin_on(mut future: GradFuture<'_>) -> Result<GradOutput, DriverError> {
+        let mut context = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+                return output;
+            }
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn test_async_driver() {
+        let mut driver = AsyncDriver::new(RemoteHarmonic, spin_on);
+        let result = driver.calc_new(&[1.0, 2.0], "");
+        assert_eq!(result, Ok(GradOutput::new(5.0, vec![2.0, 4.0])));
+    }
+
+    #[test]
+    fn test_with_driver() {
+        let mut ncall = 0;
+        let mut driver = |coords: &[f64], _dirname: &str| {
+            ncall += 1;
+            GradOutput::new(0.0, coords.to_vec())
+        };
+        let escaped = with_driver(&mut driver, |driver| {
+            assert!(driver.pointer.lock().unwrap().calc_new(&[1.0], "").is_ok());
+            driver.clone()
+        });
+        assert!(escaped.pointer.lock().unwrap().calc_new(&[1.0], "").is_err());
+        assert_eq!(ncall, 1);
+    }
+
+    /// Driver that only succeeds if two evaluations are in flight together.
+    struct Rendezvous(Mutex<usize>, std::sync::Condvar);
+
+    impl GeomDriverSync for Rendezvous {
+        fn calc_new(&self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+            let mut arrived = self.0.lock().unwrap();
+            *arrived += 1;
+            self.1.notify_all();
+            let timeout = std::time::Duration::from_secs(5);
+            let (arrived, _) = self.1.wait_timeout_while(arrived, timeout, |n| *n < 2).unwrap();
+            match *arrived >= 2 {
+                true => Ok(GradOutput::new(0.0, coords.to_vec())),
+                false => Err(DriverError::CalculationFailed("Evaluations serialized".into())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_sync_driver_concurrent() {
+        let shared: Arc<dyn GeomDriverSync> =
+            Arc::new(Rendezvous(Mutex::new(0), std::sync::Condvar::new()));
+        let handles = [PyGeomDriver::from_sync(shared.clone()), PyGeomDriver::from_sync(shared)];
+        thread::scope(|s| {
+            let workers = handles
+                .iter()
+                .map(|handle| s.spawn(|| handle.pointer.lock().unwrap().calc_new(&[1.0], "")))
+                .collect::<Vec<_>>();
+            for worker in workers {
+                assert!(worker.join().unwrap().is_ok());
+            }
+        });
+    }
+
+    #[test]
+    fn test_closure_driver() {
+        let ncall = Arc::new(AtomicUsize::new(0));
+        let counter = ncall.clone();
+        let driver = PyGeomDriver::from_fn(move |coords: &[f64], _dirname: &str| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            GradOutput::new(coords.iter().map(|x| x * x).sum(), coords.to_vec())
+        });
+        let result = driver.pointer.lock().unwrap().calc_new(&[1.0, 2.0], "");
+        assert_eq!(result, Ok(GradOutput::new(5.0, vec![1.0, 2.0])));
+        assert_eq!(ncall.load(Ordering::Relaxed), 1);
     }
 }
@@ -0,0 +1,152 @@
+//! Values of geomeTRIC's internal coordinates at a geometry.
+//!
+//! The primitive internal coordinates are built as geomeTRIC does for TRIC
+//! (bonds, angles, dihedrals, and translations/rotations of fragments), so the
+//! report shows what the optimizer worked with.
+
+use crate::molecule::Molecule;
+use crate::pyarray::PyArray1;
+use crate::result::{OptResult, BOHR2ANG};
+use pyo3::exceptions::PyIndexError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Kind of a primitive internal coordinate.
+///
+/// `Cartesian`, `Translation` and `Rotation` cover the x, y and z (or a, b and
+/// c) components of geomeTRIC; `Other` is any coordinate unknown to this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InternalCoordKind {
+    Distance,
+    Angle,
+    LinearAngle,
+    OutOfPlane,
+    Dihedral,
+    Cartesian,
+    Translation,
+    Rotation,
+    Other,
+}
+
+impl InternalCoordKind {
+    /// Kind of geomeTRIC's coordinate class name (e.g. `CartesianX`).
+    fn from_class_name(name: &str) -> Self {
+        match name {
+            "Distance" => InternalCoordKind::Distance,
+            "Angle" => InternalCoordKind::Angle,
+            "LinearAngle" => InternalCoordKind::LinearAngle,
+            "OutOfPlane" => InternalCoordKind::OutOfPlane,
+            "Dihedral" => InternalCoordKind::Dihedral,
+            _ if name.starts_with("Cartesian") => InternalCoordKind::Cartesian,
+            _ if name.starts_with("Translation") => InternalCoordKind::Translation,
+            _ if name.starts_with("Rotation") => InternalCoordKind::Rotation,
+            _ => InternalCoordKind::Other,
+        }
+    }
+
+    /// Whether the value is an angle (reported in degrees).
+    pub fn is_angular(&self) -> bool {
+        matches!(
+            self,
+            InternalCoordKind::Angle
+                | InternalCoordKind::LinearAngle
+                | InternalCoordKind::OutOfPlane
+                | InternalCoordKind::Dihedral
+        )
+    }
+}
+
+/// A primitive internal coordinate and its value.
+///
+/// - `kind`: Kind of the coordinate.
+/// - `atoms`: Atoms (0-based) defining the coordinate; all atoms of the
+///   fragment for translations and rotations.
+/// - `description`: geomeTRIC's description (e.g. `Distance 1-2`, 1-based).
+/// - `value`: Value in Angstrom (distances, Cartesian components and
+///   translations) or degrees (angles and dihedrals); rotations are in
+///   geomeTRIC's units (radians scaled by the fragment size in Bohr).
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternalCoord {
+    pub kind: InternalCoordKind,
+    pub atoms: Vec<usize>,
+    pub description: String,
+    pub value: f64,
+}
+
+/// Primitive internal coordinates of the molecule's first frame, with their
+/// values at coordinates `xyz` (Angstrom, flattened natom * 3).
+pub fn internal_coords(molecule: &Molecule, xyz: &[f64]) -> PyResult<Vec<InternalCoord>> {
+    Python::with_gil(|py| {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("build", true)?;
+        kwargs.set_item("connect", false)?;
+        kwargs.set_item("addcart", false)?;
+        let prims = py
+            .import("geometric.internal")?
+            .getattr("PrimitiveInternalCoordinates")?
+            .call((molecule.pyobj.bind(py),), Some(&kwargs))?;
+        let coords = xyz.iter().map(|x| x / BOHR2ANG).collect::<Vec<_>>();
+        let coords = PyArray1::from_vec(py, coords);
+        prims
+            .getattr("Internals")?
+            .try_iter()?
+            .map(|prim| internal_coord(&prim?, &coords))
+            .collect()
+    })
+}
+
+/// Convert one geomeTRIC coordinate object, evaluated at `coords` (Bohr).
+pub(crate) fn internal_coord(
+    prim: &Bound<'_, PyAny>,
+    coords: &Bound<'_, PyArray1<f64>>,
+) -> PyResult<InternalCoord> {
+    let class_name = prim.get_type().getattr("__name__")?.extract::<String>()?;
+    let kind = InternalCoordKind::from_class_name(&class_name);
+    // Atoms are attributes `a` to `d`; fragments are lists of atoms.
+    let mut atoms = vec![];
+    for attr in ["a", "b", "c", "d"] {
+        if !prim.hasattr(attr)? {
+            continue;
+        }
+        let atom = prim.getattr(attr)?;
+        match atom.extract::<usize>() {
+            Ok(atom) => atoms.push(atom),
+            Err(_) => atoms.extend(atom.extract::<Vec<usize>>()?),
+        }
+    }
+    let value = prim.call_method1("value", (coords,))?.extract::<f64>()?;
+    let value = match kind {
+        kind if kind.is_angular() => value.to_degrees(),
+        InternalCoordKind::Distance
+        | InternalCoordKind::Cartesian
+        | InternalCoordKind::Translation => value * BOHR2ANG,
+        _ => value,
+    };
+    Ok(InternalCoord { kind, atoms, description: prim.str()?.to_string(), value })
+}
+
+impl OptResult {
+    /// Primitive internal coordinates (see [`internal_coords`]) at the final
+    /// geometry.
+    pub fn final_internal_coords(&self) -> PyResult<Vec<InternalCoord>> {
+        let Some(xyz) = self.xyzs.last() else {
+            return Err(PyIndexError::new_err("Internal coordinates of an empty trajectory"));
+        };
+        internal_coords(&self.frame_molecule(self.xyzs.len() - 1)?, xyz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_coord_kind() {
+        assert_eq!(InternalCoordKind::from_class_name("Distance"), InternalCoordKind::Distance);
+        assert_eq!(InternalCoordKind::from_class_name("CartesianY"), InternalCoordKind::Cartesian);
+        assert_eq!(InternalCoordKind::from_class_name("RotationB"), InternalCoordKind::Rotation);
+        assert_eq!(InternalCoordKind::from_class_name("Custom"), InternalCoordKind::Other);
+        assert!(InternalCoordKind::Dihedral.is_angular());
+        assert!(!InternalCoordKind::Translation.is_angular());
+    }
+}
@@ -0,0 +1,156 @@
+//! Intrinsic reaction coordinate (IRC) following by geomeTRIC.
+
+use crate::error::GeometricError;
+use crate::optimize::{run_optimization_with_options, RunOptions};
+use crate::result::OptResult;
+use crate::version::{require_geometric, IRC_MIN_VERSION};
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Direction of IRC following from the transition state (`irc_direction`
+/// keyword).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IrcDirection {
+    #[default]
+    Both,
+    Forward,
+    Backward,
+}
+
+impl IrcDirection {
+    /// String recognized by geomeTRIC.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IrcDirection::Both => "both",
+            IrcDirection::Forward => "forward",
+            IrcDirection::Backward => "backward",
+        }
+    }
+}
+
+/// IRC-specific options.
+///
+/// - `direction`: Direction(s) to follow.
+/// - `step_size`: IRC step size in Bohr amu^1/2 (geomeTRIC's `trust` keyword,
+///   which sets the IRC step). If `None`, geomeTRIC's default is used.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct IrcOptions {
+    pub direction: IrcDirection,
+    pub step_size: Option<f64>,
+}
+
+/// Result of IRC following.
+///
+/// - `forward`, `backward`: Reaction path in each direction. Both start from
+///   the transition state (first frame) and end at the final geometry of the
+///   direction. Paths of directions not followed are empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IrcResult {
+    pub forward: OptResult,
+    pub backward: OptResult,
+}
+
+impl IrcResult {
+    /// Split the combined trajectory of geomeTRIC into the two directions.
+    ///
+    /// For `Both`, geomeTRIC gives the backward path (reversed) followed by
+    /// the forward path, so the trajectory is split at the highest-energy
+    /// frame (the transition state).
+    pub fn from_trajectory(result: &OptResult, direction: IrcDirection) -> Self {
+        let nframe = result.xyzs.len().min(result.energies.len());
+        let empty = result.select_frames(&[]);
+        match direction {
+            IrcDirection::Forward => IrcResult { forward: result.clone(), backward: empty },
+            IrcDirection::Backward => IrcResult { forward: empty, backward: result.clone() },
+            IrcDirection::Both => {
+                let ts = (0..nframe)
+                    .max_by(|&i, &j| result.energies[i].total_cmp(&result.energies[j]))
+                    .unwrap_or(0);
+                let forward = (ts..nframe).collect::<Vec<_>>();
+                let backward = (0..=ts).rev().filter(|&i| i < nframe).collect::<Vec<_>>();
+                IrcResult {
+                    forward: result.select_frames(&forward),
+                    backward: result.select_frames(&backward),
+                }
+            },
+        }
+    }
+
+    /// Whole reaction path from the backward to the forward end, with the
+    /// transition state appearing once.
+    pub fn full_path(&self) -> OptResult {
+        let nback = self.backward.xyzs.len();
+        let mut path = self.backward.select_frames(&(0..nback).rev().collect::<Vec<_>>());
+        let skip = usize::from(nback > 0);
+        let forward =
+            self.forward.select_frames(&(skip..self.forward.xyzs.len()).collect::<Vec<_>>());
+        path.xyzs.extend(forward.xyzs);
+        path.energies.extend(forward.energies);
+        path.gradients.extend(forward.gradients);
+        path.energy_components.extend(forward.energy_components);
+        path.extras.extend(forward.extras);
+        path
+    }
+}
+
+/// Follow the IRC from the transition state geometry of the custom engine's
+/// molecule.
+///
+/// - `params`: Parameters of geomeTRIC; `irc = true` and the IRC options are
+///   set on a copy.
+/// - `irc`: IRC-specific options.
+/// - `options`: Other run options, see [`RunOptions`].
+///
+/// Requires geomeTRIC >= 1.1.
+pub fn run_irc(
+    custom_engine: PyObject,
+    params: &Py<PyDict>,
+    irc: &IrcOptions,
+    options: &RunOptions,
+) -> Result<IrcResult, GeometricError> {
+    require_geometric("IRC", IRC_MIN_VERSION)?;
+    if let Some(step_size) = irc.step_size {
+        if !(step_size.is_finite() && step_size > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "IRC step size must be positive, got {}",
+                step_size
+            ))
+            .into());
+        }
+    }
+    let params = Python::with_gil(|py| -> PyResult<Py<PyDict>> {
+        let params = params.bind(py).copy()?;
+        params.set_item("irc", true)?;
+        params.set_item("irc_direction", irc.direction.as_str())?;
+        if let Some(step_size) = irc.step_size {
+            params.set_item("trust", step_size)?;
+        }
+        Ok(params.unbind())
+    })?;
+    let outcome = run_optimization_with_options(custom_engine, &params, None, options)?;
+    Ok(IrcResult::from_trajectory(outcome.result(), irc.direction))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_irc_split() {
+        let nframe = 5;
+        let result = OptResult::new(
+            vec!["H".into()],
+            (0..nframe).map(|i| vec![i as f64, 0.0, 0.0]).collect(),
+            vec![-1.2, -1.1, -1.0, -1.1, -1.3],
+        );
+        let irc = IrcResult::from_trajectory(&result, IrcDirection::Both);
+        assert_eq!(irc.forward.energies, vec![-1.0, -1.1, -1.3]);
+        assert_eq!(irc.backward.energies, vec![-1.0, -1.1, -1.2]);
+        assert_eq!(irc.full_path(), result);
+
+        let irc = IrcResult::from_trajectory(&result, IrcDirection::Forward);
+        assert!(irc.backward.xyzs.is_empty());
+        assert_eq!(irc.full_path(), result);
+    }
+}
@@ -3,7 +3,45 @@
 
 pub mod prelude;
 
+#[cfg(feature = "ndarray")]
+pub mod array;
+pub mod cache;
+pub mod callback;
+pub mod checkpoint;
+pub mod constraints;
+pub mod convergence;
 pub mod engine;
+pub mod error;
+pub mod extension;
+pub mod frequency;
+pub mod gradcheck;
+pub mod hessian;
 pub mod interface;
+pub mod internal;
+pub mod irc;
+pub mod logging;
+pub mod meci;
+pub mod memory;
+pub mod molecule;
+pub mod neb;
+pub mod numgrad;
 pub mod optimize;
+pub mod parallel;
+pub mod params;
+mod pyarray;
+#[cfg(feature = "qcengine")]
+pub mod qcengine;
+pub mod qcschema;
+pub mod qdata;
+pub mod result;
+pub mod runtime;
+pub mod scan;
+pub mod scratch;
+pub mod staged;
+pub mod testing;
+pub mod trajectory;
 pub mod util;
+pub mod version;
+pub mod wilson;
+#[cfg(feature = "xtb")]
+pub mod xtb;
@@ -0,0 +1,204 @@
+//! Bridge of geomeTRIC's python logging into the Rust [`log`] ecosystem.
+//!
+//! geomeTRIC reports progress through python loggers under `geometric`. After
+//! [`install_log_bridge`], these records are also forwarded to the [`log`]
+//! facade, with the python logger name as target (e.g. `geometric.nifty`).
+//! Applications using `tracing` receive them through `tracing-log`'s
+//! `LogTracer`.
+//!
+//! The amount of output is controlled by [`set_log_level`].
+
+use pyo3::prelude::*;
+
+/// Name of the python logger that all geomeTRIC loggers descend from.
+const GEOMETRIC_LOGGER: &str = "geometric";
+
+/// Attribute marking the bridge handler on the python side.
+const BRIDGE_MARKER: &str = "_geometric_pyo3_bridge";
+
+/// Callable set as `emit` of the python handler.
+#[pyclass]
+struct LogForwarder;
+
+#[pymethods]
+impl LogForwarder {
+    fn __call__(&self, record: &Bound<'_, PyAny>) -> PyResult<()> {
+        let levelno: i64 = record.getattr("levelno")?.extract()?;
+        let target: String = record.getattr("name")?.extract()?;
+        // Malformed format arguments must not raise in geomeTRIC's code.
+        let message = match record.call_method0("getMessage") {
+            Ok(message) => message.str()?.to_string(),
+            Err(_) => record.getattr("msg")?.str()?.to_string(),
+        };
+        // geomeTRIC's handlers do not append newlines, so messages carry their
+        // own; blank lines are dropped.
+        let message = message.trim_end();
+        if !message.is_empty() {
+            log::log!(target: &target, python_level(levelno), "{}", message);
+        }
+        Ok(())
+    }
+}
+
+/// Map python logging level numbers to [`log::Level`].
+fn python_level(levelno: i64) -> log::Level {
+    match levelno {
+        40.. => log::Level::Error,
+        30..=39 => log::Level::Warn,
+        20..=29 => log::Level::Info,
+        10..=19 => log::Level::Debug,
+        _ => log::Level::Trace,
+    }
+}
+
+/// Verbosity of geomeTRIC's logging.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogLevel {
+    /// No output at all, for embedded use.
+    Quiet,
+    Error,
+    Warning,
+    /// geomeTRIC default.
+    #[default]
+    Info,
+    /// Only effective for handlers that accept debug records (e.g. the
+    /// [`install_log_bridge`] handler); geomeTRIC's console and file handlers
+    /// are configured at info level.
+    Debug,
+}
+
+impl LogLevel {
+    /// Python logging level number.
+    pub fn python_level(&self) -> i64 {
+        match self {
+            // Above `logging.CRITICAL`
+            LogLevel::Quiet => 60,
+            LogLevel::Error => 40,
+            LogLevel::Warning => 30,
+            LogLevel::Info => 20,
+            LogLevel::Debug => 10,
+        }
+    }
+}
+
+/// Set the level of geomeTRIC's python loggers, process-wide.
+///
+/// This affects all output of geomeTRIC (console, `.log` file, and
+/// [`install_log_bridge`]) of subsequent optimizations. For the amount of
+/// detail printed at info level, see
+/// [`OptimizerParams::verbose`](crate::params::OptimizerParams::verbose).
+pub fn set_log_level(level: LogLevel) -> PyResult<()> {
+    Python::with_gil(|py| {
+        // Loggers of geomeTRIC set their own level on import, so import them
+        // before overriding.
+        py.import("geometric.optimize")?;
+        let logging = py.import("logging")?;
+        let manager = logging.getattr("Logger")?.getattr("manager")?;
+        let prefix = format!("{}.", GEOMETRIC_LOGGER);
+        let mut names = vec![GEOMETRIC_LOGGER.to_string()];
+        for name in manager.getattr("loggerDict")?.try_iter()? {
+            let name: String = name?.extract()?;
+            if name.starts_with(&prefix) {
+                names.push(name);
+            }
+        }
+        for name in names {
+            let logger = logging.call_method1("getLogger", (name,))?;
+            logger.call_method1("setLevel", (level.python_level(),))?;
+        }
+        Ok(())
+    })
+}
+
+/// Forward records of geomeTRIC's python loggers to the [`log`] facade.
+///
+/// geomeTRIC's own handlers (console and `.log` file) are kept. Calling this
+/// again has no effect.
+pub fn install_log_bridge() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let logging = py.import("logging")?;
+        let logger = logging.call_method1("getLogger", (GEOMETRIC_LOGGER,))?;
+        if !bridge_handlers(&logger)?.is_empty() {
+            return Ok(());
+        }
+        let handler = logging.call_method0("Handler")?;
+        handler.setattr("emit", Py::new(py, LogForwarder)?)?;
+        handler.setattr(BRIDGE_MARKER, true)?;
+        logger.call_method1("addHandler", (handler,))?;
+        Ok(())
+    })
+}
+
+/// Stop forwarding records installed by [`install_log_bridge`].
+pub fn remove_log_bridge() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let logger = py.import("logging")?.call_method1("getLogger", (GEOMETRIC_LOGGER,))?;
+        for handler in bridge_handlers(&logger)? {
+            logger.call_method1("removeHandler", (handler,))?;
+        }
+        Ok(())
+    })
+}
+
+fn bridge_handlers<'py>(logger: &Bound<'py, PyAny>) -> PyResult<Vec<Bound<'py, PyAny>>> {
+    let mut handlers = vec![];
+    for handler in logger.getattr("handlers")?.try_iter()? {
+        let handler = handler?;
+        if handler.hasattr(BRIDGE_MARKER)? {
+            handlers.push(handler);
+        }
+    }
+    Ok(handlers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<(log::Level, String, String)>> = Mutex::new(Vec::new());
+
+    struct Capture;
+
+    impl log::Log for Capture {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let record = (record.level(), record.target().to_string(), record.args().to_string());
+            RECORDS.lock().unwrap().push(record);
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn test_log_bridge() {
+        log::set_logger(&Capture).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+        pyo3::prepare_freethreaded_python();
+        install_log_bridge().unwrap();
+        install_log_bridge().unwrap();
+        Python::with_gil(|py| {
+            let logger = py.import("logging")?.call_method1("getLogger", ("geometric.nifty",))?;
+            logger.call_method1("setLevel", (10,))?;
+            logger.call_method1("info", ("Step %i\n", 3))?;
+            logger.call_method1("warning", ("\n",))?;
+            logger.call_method1("error", ("failed",))?;
+            PyResult::Ok(())
+        })
+        .unwrap();
+        remove_log_bridge().unwrap();
+        Python::with_gil(|py| {
+            let logger = py.import("logging")?.call_method1("getLogger", ("geometric.nifty",))?;
+            logger.call_method1("info", ("ignored",))?;
+            PyResult::Ok(())
+        })
+        .unwrap();
+        assert_eq!(*RECORDS.lock().unwrap(), vec![
+            (log::Level::Info, "geometric.nifty".to_string(), "Step 3".to_string()),
+            (log::Level::Error, "geometric.nifty".to_string(), "failed".to_string()),
+        ]);
+    }
+}
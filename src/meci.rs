@@ -0,0 +1,163 @@
+//! Minimum-energy conical intersection (MECI) optimization.
+//!
+//! geomeTRIC optimizes MECIs by a penalty function of two electronic states
+//! (`geometric.engine.ConicalIntersection`), each state being evaluated by its
+//! own engine. Here both states are computed by one [`TwoStateDriverAPI`],
+//! which usually obtains them from the same calculation (e.g. state-averaged
+//! CASSCF or TDDFT).
+
+use std::sync::{Arc, Mutex};
+
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::{AtomData, DriverError, GeomDriverAPI, GradOutput, PyGeomDriver};
+use crate::molecule::Molecule;
+use crate::optimize::{run_optimization_with_options, RunOptions};
+use crate::result::OptimizationOutcome;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Interface of drivers computing two electronic states at once.
+pub trait TwoStateDriverAPI: Send {
+    /// Calculate the energies and gradients of both states (lower state
+    /// first).
+    ///
+    /// Arguments are the same to [`GeomDriverAPI::calc_new`].
+    fn calc_states(
+        &mut self,
+        coords: &[f64],
+        dirname: &str,
+    ) -> Result<[GradOutput; 2], DriverError>;
+
+    /// See [`GeomDriverAPI::set_atom_data`].
+    fn set_atom_data(&mut self, _atom_data: &AtomData) {}
+}
+
+/// Two-state driver with the result of the last geometry.
+struct SharedStates {
+    driver: Box<dyn TwoStateDriverAPI>,
+    last: Option<(Vec<f64>, [GradOutput; 2])>,
+}
+
+/// Single-state view of a [`TwoStateDriverAPI`].
+///
+/// geomeTRIC evaluates both state engines at the same geometry, so the second
+/// evaluation is served from the cached result of the first.
+struct StateDriver {
+    shared: Arc<Mutex<SharedStates>>,
+    state: usize,
+}
+
+impl GeomDriverAPI for StateDriver {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        let mut shared = self.shared.lock().unwrap();
+        match &shared.last {
+            Some((last_coords, states)) if last_coords.as_slice() == coords => {
+                Ok(states[self.state].clone())
+            },
+            _ => {
+                let states = shared.driver.calc_states(coords, dirname)?;
+                let output = states[self.state].clone();
+                shared.last = Some((coords.to_vec(), states));
+                Ok(output)
+            },
+        }
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        if self.state == 0 {
+            self.shared.lock().unwrap().driver.set_atom_data(atom_data);
+        }
+    }
+}
+
+/// Penalty-function options of MECI optimization.
+///
+/// - `sigma`: Penalty strength (`meci_sigma` keyword; geomeTRIC default 3.5).
+/// - `alpha`: Smoothing of the penalty in Hartree (`meci_alpha` keyword;
+///   geomeTRIC default 0.025).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeciOptions {
+    pub sigma: f64,
+    pub alpha: f64,
+}
+
+impl Default for MeciOptions {
+    fn default() -> Self {
+        MeciOptions { sigma: 3.5, alpha: 0.025 }
+    }
+}
+
+/// Optimize the MECI of two states computed by `driver`.
+///
+/// The result's energies are the penalty-function values of geomeTRIC.
+/// Engine-level options of `options` (`non_finite_policy`, `stop_at_energy`,
+/// `callback`) and the driver's preferred coordinate system are not applied.
+pub fn run_meci(
+    molecule: &Molecule,
+    driver: impl TwoStateDriverAPI + 'static,
+    params: &Py<PyDict>,
+    meci: &MeciOptions,
+    options: &RunOptions,
+) -> Result<OptimizationOutcome, GeometricError> {
+    if !(meci.sigma > 0.0 && meci.alpha > 0.0) {
+        return Err(PyValueError::new_err(format!(
+            "MECI sigma and alpha must be positive, got {} and {}",
+            meci.sigma, meci.alpha
+        ))
+        .into());
+    }
+    let shared = Arc::new(Mutex::new(SharedStates { driver: Box::new(driver), last: None }));
+    let engines = (0..2)
+        .map(|state| {
+            let driver = PyGeomDriver::from(StateDriver { shared: shared.clone(), state });
+            init_pyo3_engine(&molecule.pyobj, &driver)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    let custom_engine = Python::with_gil(|py| -> PyResult<PyObject> {
+        let conical_intersection = py.import("geometric.engine")?.getattr("ConicalIntersection")?;
+        let engine =
+            conical_intersection.call1((&molecule.pyobj, engines, meci.sigma, meci.alpha))?;
+        Ok(engine.unbind())
+    })?;
+    run_optimization_with_options(custom_engine, params, None, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two harmonic states; counts calculations.
+    struct TwoHarmonic {
+        ncalc: usize,
+    }
+
+    impl TwoStateDriverAPI for TwoHarmonic {
+        fn calc_states(
+            &mut self,
+            coords: &[f64],
+            _dirname: &str,
+        ) -> Result<[GradOutput; 2], DriverError> {
+            self.ncalc += 1;
+            let e = coords.iter().map(|x| x * x).sum::<f64>();
+            let g = coords.iter().map(|x| 2.0 * x).collect::<Vec<_>>();
+            Ok([GradOutput::new(e, g.clone()), GradOutput::new(e + 0.1, g)])
+        }
+    }
+
+    #[test]
+    fn test_state_driver_cache() {
+        let shared = Arc::new(Mutex::new(SharedStates {
+            driver: Box::new(TwoHarmonic { ncalc: 0 }),
+            last: None,
+        }));
+        let mut lower = StateDriver { shared: shared.clone(), state: 0 };
+        let mut upper = StateDriver { shared: shared.clone(), state: 1 };
+        let coords = [0.1, 0.2, 0.3];
+        assert_eq!(lower.calc_new(&coords, "").unwrap().energy, 0.14);
+        assert!((upper.calc_new(&coords, "").unwrap().energy - 0.24).abs() < 1.0e-12);
+        upper.calc_new(&[0.0, 0.0, 0.0], "").unwrap();
+        assert_eq!(shared.lock().unwrap().last.as_ref().unwrap().0, vec![0.0; 3]);
+    }
+}
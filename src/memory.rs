@@ -0,0 +1,104 @@
+//! Estimation of geomeTRIC memory footprint before running an optimization.
+//!
+//! geomeTRIC works with dense matrices, whose sizes grow quadratically with
+//! number of atoms. For very large systems, it is advisable to check the
+//! estimate (and probably use Cartesian coordinates) before launching.
+
+use std::ffi::CString;
+
+use crate::molecule::Molecule;
+use crate::params::CoordSys;
+use pyo3::exceptions::PyUserWarning;
+use pyo3::prelude::*;
+use pyo3::PyTypeInfo;
+
+/// Estimated memory (bytes) above which [`estimate_memory`] warns (8 GiB).
+pub const MEMORY_WARN_BYTES: usize = 8 << 30;
+
+/// Approximate number of primitive internal coordinates per atom (bonds,
+/// angles, dihedrals, and translations/rotations for TRIC) of typical
+/// covalent molecules.
+const PRIMITIVES_PER_ATOM: usize = 8;
+
+/// Size of `f64` in bytes.
+const F64_BYTES: usize = 8;
+
+/// Approximate memory footprint of geomeTRIC's dense matrices (bytes).
+///
+/// - `hessian`: Hessian in the optimization coordinates.
+/// - `internal`: Internal coordinate structures (Wilson B-matrix, G-matrix, and
+///   delocalization vectors); zero for Cartesian coordinates.
+///
+/// This is an order-of-magnitude estimate; actual usage may be a few times
+/// larger due to temporaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryEstimate {
+    pub hessian: usize,
+    pub internal: usize,
+}
+
+impl MemoryEstimate {
+    /// Estimate the memory for a molecule of `natom` atoms.
+    pub fn new(natom: usize, coordsys: CoordSys) -> Self {
+        let ncart = 3 * natom;
+        if coordsys == CoordSys::Cart {
+            return MemoryEstimate { hessian: ncart * ncart * F64_BYTES, internal: 0 };
+        }
+        let nprim = PRIMITIVES_PER_ATOM * natom;
+        // B-matrix (nprim * ncart) and G-matrix (nprim * nprim)
+        let mut internal = nprim * ncart + nprim * nprim;
+        // optimization coordinates: primitives, or delocalized combinations of them
+        let ncoord = match coordsys {
+            CoordSys::Prim | CoordSys::TricP => nprim,
+            _ => {
+                // delocalization vectors (nprim * nprim)
+                internal += nprim * nprim;
+                ncart
+            },
+        };
+        MemoryEstimate { hessian: ncoord * ncoord * F64_BYTES, internal: internal * F64_BYTES }
+    }
+
+    /// Total estimated memory (bytes).
+    pub fn total(&self) -> usize {
+        self.hessian + self.internal
+    }
+}
+
+/// Estimate the memory footprint of optimizing the molecule.
+///
+/// A python `UserWarning` is emitted if the estimate exceeds
+/// [`MEMORY_WARN_BYTES`].
+pub fn estimate_memory(molecule: &Molecule, coordsys: CoordSys) -> PyResult<MemoryEstimate> {
+    let natom = molecule.natom()?;
+    let estimate = MemoryEstimate::new(natom, coordsys);
+    if estimate.total() > MEMORY_WARN_BYTES {
+        let message = format!(
+            "Estimated memory of optimizing {} atoms with coordsys `{}` is {:.1} GiB, which may \
+             exceed available memory.",
+            natom,
+            coordsys.as_str(),
+            estimate.total() as f64 / (1u64 << 30) as f64
+        );
+        Python::with_gil(|py| {
+            let message = CString::new(message).unwrap();
+            PyErr::warn(py, PyUserWarning::type_object(py).as_any(), &message, 0)
+        })?;
+    }
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_estimate() {
+        let cart = MemoryEstimate::new(10_000, CoordSys::Cart);
+        assert_eq!(cart.total(), 30_000 * 30_000 * 8);
+        let tric = MemoryEstimate::new(10_000, CoordSys::Tric);
+        assert_eq!(tric.hessian, cart.hessian);
+        assert!(tric.total() > MEMORY_WARN_BYTES);
+        assert!(MemoryEstimate::new(100, CoordSys::Tric).total() < MEMORY_WARN_BYTES);
+    }
+}
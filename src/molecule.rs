@@ -0,0 +1,475 @@
+//! Thin Rust wrapper of `geometric.molecule.Molecule`.
+
+use std::path::Path;
+
+use crate::engine::{init_pyo3_molecule, init_pyo3_molecule_with_comments};
+use crate::result::BOHR2ANG;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+/// Rust handle of a geomeTRIC `Molecule` python object.
+///
+/// The python object itself is kept in field `pyobj`, which can be passed to
+/// geomeTRIC (e.g. as argument of the `PyO3Engine` class) directly.
+pub struct Molecule {
+    pub pyobj: PyObject,
+}
+
+impl From<PyObject> for Molecule {
+    fn from(pyobj: PyObject) -> Self {
+        Molecule { pyobj }
+    }
+}
+
+/// Unit of length of coordinates.
+///
+/// geomeTRIC molecules and results ([`OptResult`](crate::result::OptResult))
+/// are in Angstrom, while drivers receive coordinates in Bohr
+/// ([`GeomDriverAPI::calc_new`](crate::interface::GeomDriverAPI::calc_new)).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LengthUnit {
+    #[default]
+    Angstrom,
+    Bohr,
+}
+
+impl LengthUnit {
+    /// Convert coordinates in this unit to Angstrom.
+    pub fn to_angstrom(&self, coords: &[f64]) -> Vec<f64> {
+        match self {
+            LengthUnit::Angstrom => coords.to_vec(),
+            LengthUnit::Bohr => coords.iter().map(|x| x * BOHR2ANG).collect(),
+        }
+    }
+
+    /// Convert coordinates in Angstrom to this unit.
+    pub fn from_angstrom(&self, coords: &[f64]) -> Vec<f64> {
+        match self {
+            LengthUnit::Angstrom => coords.to_vec(),
+            LengthUnit::Bohr => coords.iter().map(|x| x / BOHR2ANG).collect(),
+        }
+    }
+}
+
+/// Specification of a molecule with total charge and spin multiplicity.
+///
+/// - `elem`, `xyzs`: Element symbols and coordinates (in `unit`), see
+///   [`init_pyo3_molecule`].
+/// - `unit`: Unit of `xyzs` (and of `lattice`); Angstrom by default.
+/// - `charge`, `mult`: Total charge and spin multiplicity. They are set as
+///   `charge` and `mult` attributes of the geomeTRIC molecule, and passed to
+///   the driver in [`AtomData`](crate::interface::AtomData).
+/// - `lattice`: Lattice vectors (one vector per row) of periodic systems, see
+///   [`Molecule::set_lattice_vectors`].
+/// - `bonds`: Explicit bond list (0-based atom pairs) replacing geomeTRIC's
+///   distance-based guess, see [`Molecule::set_bonds`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MoleculeSpec {
+    pub elem: Vec<String>,
+    pub xyzs: Vec<Vec<f64>>,
+    pub unit: LengthUnit,
+    pub charge: Option<i64>,
+    pub mult: Option<i64>,
+    pub lattice: Option<[[f64; 3]; 3]>,
+    pub bonds: Option<Vec<(usize, usize)>>,
+}
+
+impl MoleculeSpec {
+    /// Build the geomeTRIC molecule.
+    pub fn build(&self) -> PyResult<Molecule> {
+        if let Some(mult) = self.mult {
+            if mult < 1 {
+                return Err(PyValueError::new_err(format!(
+                    "Spin multiplicity must be positive, got {}",
+                    mult
+                )));
+            }
+        }
+        let elem = self.elem.iter().map(String::as_str).collect::<Vec<_>>();
+        let xyzs = self.xyzs.iter().map(|xyz| self.unit.to_angstrom(xyz)).collect::<Vec<_>>();
+        let molecule = Molecule::new(&elem, &xyzs)?;
+        if let Some(charge) = self.charge {
+            molecule.set_charge(charge)?;
+        }
+        if let Some(mult) = self.mult {
+            molecule.set_mult(mult)?;
+        }
+        if let Some(lattice) = &self.lattice {
+            let lattice = lattice.map(|v| self.unit.to_angstrom(&v).try_into().unwrap());
+            molecule.set_lattice_vectors(&lattice)?;
+        }
+        if let Some(bonds) = &self.bonds {
+            molecule.set_bonds(bonds)?;
+        }
+        Ok(molecule)
+    }
+}
+
+impl Molecule {
+    /// Create molecule from element symbols and coordinates (Angstrom).
+    ///
+    /// See [`init_pyo3_molecule`] for the layout of arguments.
+    pub fn new(elem: &[&str], xyzs: &[Vec<f64>]) -> PyResult<Self> {
+        Ok(Molecule { pyobj: init_pyo3_molecule(elem, xyzs)? })
+    }
+
+    /// Clone the handle (not the underlying python object).
+    pub fn clone_ref(&self, py: Python<'_>) -> Self {
+        Molecule { pyobj: self.pyobj.clone_ref(py) }
+    }
+
+    /// Deep-copy this molecule with a new geometry (Angstrom, flattened
+    /// natom * 3).
+    ///
+    /// All other metadata (elements, charge, multiplicity, masses, topology,
+    /// etc.) is kept. The copy has a single frame; for multi-frame molecules,
+    /// per-frame data (comments, boxes, ...) of the first frame is kept.
+    pub fn with_coords(&self, coords_angstrom: &[f64]) -> PyResult<Molecule> {
+        let natom = self.natom()?;
+        if coords_angstrom.len() != natom * 3 {
+            return Err(PyValueError::new_err(format!(
+                "Length of coordinates ({}) does not match number of atoms ({}) * 3",
+                coords_angstrom.len(),
+                natom
+            )));
+        }
+        Python::with_gil(|py| {
+            let frame = self.pyobj.bind(py).get_item(0)?;
+            let molecule = py.import("copy")?.call_method1("deepcopy", (frame,))?;
+            let xyz = py
+                .import("numpy")?
+                .call_method1("array", (PyList::new(py, coords_angstrom)?,))?
+                .call_method1("reshape", (-1, 3))?;
+            molecule.setattr("xyzs", vec![xyz])?;
+            // not a geomeTRIC attribute, so may not survive frame slicing
+            if self.pyobj.bind(py).hasattr("ghost_atoms")? {
+                molecule.setattr("ghost_atoms", self.pyobj.getattr(py, "ghost_atoms")?)?;
+            }
+            Ok(Molecule { pyobj: molecule.unbind() })
+        })
+    }
+
+    /// Element symbols.
+    pub fn elem(&self) -> PyResult<Vec<String>> {
+        Python::with_gil(|py| self.pyobj.getattr(py, "elem")?.extract(py))
+    }
+
+    /// Number of atoms.
+    pub fn natom(&self) -> PyResult<usize> {
+        Ok(self.elem()?.len())
+    }
+
+    /// Coordinates of all frames in Angstrom, each flattened (natom * 3).
+    pub fn xyzs(&self) -> PyResult<Vec<Vec<f64>>> {
+        Python::with_gil(|py| {
+            self.pyobj
+                .bind(py)
+                .getattr("xyzs")?
+                .try_iter()?
+                .map(|xyz| xyz?.call_method0("flatten")?.call_method0("tolist")?.extract())
+                .collect()
+        })
+    }
+
+    /// Total charge, if it has been set on the molecule.
+    pub fn charge(&self) -> PyResult<Option<i64>> {
+        self.get_optional("charge")
+    }
+
+    /// Spin multiplicity, if it has been set on the molecule.
+    pub fn mult(&self) -> PyResult<Option<i64>> {
+        self.get_optional("mult")
+    }
+
+    /// Set total charge.
+    pub fn set_charge(&self, charge: i64) -> PyResult<()> {
+        Python::with_gil(|py| self.pyobj.setattr(py, "charge", charge))
+    }
+
+    /// Set spin multiplicity.
+    pub fn set_mult(&self, mult: i64) -> PyResult<()> {
+        Python::with_gil(|py| self.pyobj.setattr(py, "mult", mult))
+    }
+
+    /// Set lattice vectors (Angstrom, one vector per row) of a periodic system
+    /// for all frames.
+    ///
+    /// They are stored as geomeTRIC's `boxes` (built by
+    /// `geometric.molecule.BuildLatticeFromVectors`), written to output files
+    /// that support them, and passed to the driver in
+    /// [`AtomData::lattice`](crate::interface::AtomData). geomeTRIC itself
+    /// optimizes atomic positions only; the cell is kept fixed.
+    pub fn set_lattice_vectors(&self, lattice: &[[f64; 3]; 3]) -> PyResult<()> {
+        if !lattice.iter().flatten().all(|x| x.is_finite()) {
+            return Err(PyValueError::new_err("Lattice vectors must be finite"));
+        }
+        let nframe = self.xyzs()?.len();
+        Python::with_gil(|py| {
+            let numpy = py.import("numpy")?;
+            let vectors = lattice
+                .iter()
+                .map(|v| numpy.call_method1("array", (PyList::new(py, v)?,)))
+                .collect::<PyResult<Vec<_>>>()?;
+            let build = py.import("geometric.molecule")?.getattr("BuildLatticeFromVectors")?;
+            let lattice = build.call1((&vectors[0], &vectors[1], &vectors[2]))?;
+            self.pyobj.setattr(py, "boxes", vec![lattice; nframe])
+        })
+    }
+
+    /// Lattice vectors (Angstrom, one vector per row) of the first frame, if
+    /// the molecule is periodic.
+    pub fn lattice_vectors(&self) -> PyResult<Option<[[f64; 3]; 3]>> {
+        Python::with_gil(|py| lattice_vectors(self.pyobj.bind(py)))
+    }
+
+    /// Set explicit bond connectivity (0-based atom pairs), and build the
+    /// topology from it.
+    ///
+    /// The quality of TRIC coordinates depends on connectivity, and
+    /// geomeTRIC's distance-based guess can be wrong for weak interactions
+    /// (e.g. hydrogen bonds or coordination bonds). The bonds are set as
+    /// `bonds` with `top_settings["read_bonds"]` enabled, which makes geomeTRIC
+    /// keep them instead of guessing from distances.
+    pub fn set_bonds(&self, bonds: &[(usize, usize)]) -> PyResult<()> {
+        let natom = self.natom()?;
+        let mut pairs = Vec::with_capacity(bonds.len());
+        for &(i, j) in bonds {
+            if i >= natom || j >= natom || i == j {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid bond ({}, {}) for {} atoms",
+                    i, j, natom
+                )));
+            }
+            pairs.push((i.min(j), i.max(j)));
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        Python::with_gil(|py| {
+            let pyobj = self.pyobj.bind(py);
+            pyobj.setattr("bonds", pairs)?;
+            pyobj.getattr("top_settings")?.set_item("read_bonds", true)?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("force_bonds", false)?;
+            pyobj.call_method("build_topology", (), Some(&kwargs))?;
+            Ok(())
+        })
+    }
+
+    /// Mark atoms (0-based indices) as ghost atoms.
+    ///
+    /// Ghost atoms are used in counterpoise (BSSE) calculations: they remain in
+    /// the geometry (and in geomeTRIC's coordinate system), but the driver
+    /// should treat them as basis functions without nuclear charge. The
+    /// designation is stored in the `ghost_atoms` attribute of the python
+    /// object, and passed to the driver by
+    /// [`GeomDriverAPI::set_atom_data`](crate::interface::GeomDriverAPI::set_atom_data).
+    pub fn set_ghost_atoms(&self, indices: &[usize]) -> PyResult<()> {
+        let natom = self.natom()?;
+        if let Some(&idx) = indices.iter().find(|&&idx| idx >= natom) {
+            return Err(PyValueError::new_err(format!(
+                "Ghost atom index {} out of range for {} atoms",
+                idx, natom
+            )));
+        }
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        Python::with_gil(|py| self.pyobj.setattr(py, "ghost_atoms", indices))
+    }
+
+    /// Set the bond detection factor (`top_settings["Fac"]` of geomeTRIC,
+    /// default 1.2).
+    ///
+    /// Two atoms are bonded if their distance is below `factor` times the sum
+    /// of their covalent radii, so the factor is dimensionless. Increase it for
+    /// stretched inputs (e.g. near transition states), or decrease it for
+    /// compressed ones. The topology is not rebuilt here; call
+    /// [`molecule_build_topology`](crate::engine::molecule_build_topology)
+    /// afterwards if it has already been built.
+    pub fn set_bond_factor(&self, factor: f64) -> PyResult<()> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(PyValueError::new_err(format!(
+                "Bond detection factor must be positive, got {}",
+                factor
+            )));
+        }
+        Python::with_gil(|py| {
+            self.pyobj.getattr(py, "top_settings")?.bind(py).set_item("Fac", factor)
+        })
+    }
+
+    fn get_optional(&self, key: &str) -> PyResult<Option<i64>> {
+        Python::with_gil(|py| {
+            let pyobj = self.pyobj.bind(py);
+            match pyobj.hasattr(key)? {
+                true => pyobj.getattr(key)?.extract().map(Some),
+                false => Ok(None),
+            }
+        })
+    }
+}
+
+/// Lattice vectors of the first frame of a geomeTRIC molecule (`boxes`
+/// attribute), if present.
+pub(crate) fn lattice_vectors(molecule: &Bound<'_, PyAny>) -> PyResult<Option<[[f64; 3]; 3]>> {
+    if !molecule.hasattr("boxes")? {
+        return Ok(None);
+    }
+    let lattice = molecule.getattr("boxes")?.get_item(0)?;
+    let vector = |key: &str| -> PyResult<[f64; 3]> {
+        lattice.getattr(key)?.call_method0("tolist")?.extract::<[f64; 3]>()
+    };
+    Ok(Some([vector("A")?, vector("B")?, vector("C")?]))
+}
+
+/// Frames parsed from XYZ format.
+///
+/// - `elem`: Element symbols (capitalized, e.g. `Cl`), same for all frames.
+/// - `xyzs`: Coordinates of each frame in Angstrom, flattened (natom * 3).
+/// - `comments`: Comment line of each frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct XyzData {
+    pub elem: Vec<String>,
+    pub xyzs: Vec<Vec<f64>>,
+    pub comments: Vec<String>,
+}
+
+/// Parse standard XYZ format (single or multi-frame).
+///
+/// Each frame is a line of atom count, a comment line, and one line per atom
+/// of element symbol and coordinates (further columns are ignored). All
+/// frames must have the same elements.
+pub fn parse_xyz(s: &str) -> PyResult<XyzData> {
+    let err = |lineno: usize, msg: &str| {
+        PyValueError::new_err(format!("Invalid XYZ at line {}: {}", lineno + 1, msg))
+    };
+    let mut data = XyzData { elem: vec![], xyzs: vec![], comments: vec![] };
+    let mut lines = s.lines().enumerate();
+    while let Some((lineno, line)) = lines.next() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let natom =
+            line.trim().parse::<usize>().map_err(|_| err(lineno, "expected number of atoms"))?;
+        let (_, comment) = lines.next().ok_or_else(|| err(lineno + 1, "missing comment line"))?;
+        let mut elem = Vec::with_capacity(natom);
+        let mut xyz = Vec::with_capacity(natom * 3);
+        for i in 0..natom {
+            let (lineno, line) =
+                lines.next().ok_or_else(|| err(lineno + 2 + i, "missing atom line"))?;
+            let tokens = line.split_whitespace().collect::<Vec<_>>();
+            if tokens.len() < 4 {
+                return Err(err(lineno, "expected element and 3 coordinates"));
+            }
+            let mut chars = tokens[0].chars();
+            let symbol = chars.next().unwrap().to_ascii_uppercase().to_string()
+                + &chars.as_str().to_ascii_lowercase();
+            elem.push(symbol);
+            for token in &tokens[1..4] {
+                xyz.push(token.parse::<f64>().map_err(|_| err(lineno, "invalid coordinate"))?);
+            }
+        }
+        if data.xyzs.is_empty() {
+            data.elem = elem;
+        } else if elem != data.elem {
+            return Err(err(lineno, "elements differ from the first frame"));
+        }
+        data.xyzs.push(xyz);
+        data.comments.push(comment.trim().to_string());
+    }
+    if data.xyzs.is_empty() {
+        return Err(PyValueError::new_err("Invalid XYZ: no frames found"));
+    }
+    Ok(data)
+}
+
+/// Build a geomeTRIC molecule from XYZ format string (see [`parse_xyz`]).
+///
+/// Comment lines are kept as `Molecule.comms`.
+pub fn molecule_from_xyz_str(s: &str) -> PyResult<Molecule> {
+    let data = parse_xyz(s)?;
+    let elem = data.elem.iter().map(String::as_str).collect::<Vec<_>>();
+    let comments = data.comments.iter().map(String::as_str).collect::<Vec<_>>();
+    Ok(Molecule::from(init_pyo3_molecule_with_comments(&elem, &data.xyzs, &comments)?))
+}
+
+/// Build a geomeTRIC molecule from an XYZ file (see [`parse_xyz`]).
+pub fn molecule_from_file(path: impl AsRef<Path>) -> PyResult<Molecule> {
+    molecule_from_xyz_str(&std::fs::read_to_string(path)?)
+}
+
+/// Whether a geometry (flattened natom * 3) is near-linear.
+///
+/// All atoms are projected on the axis through the two atoms farthest apart;
+/// the geometry is near-linear if every atom is within `tol` (same unit as
+/// coordinates) of this axis. Geometries of less than three atoms are not
+/// considered.
+pub fn is_near_linear(xyz: &[f64], tol: f64) -> bool {
+    let atoms = xyz.chunks(3).collect::<Vec<_>>();
+    if atoms.len() < 3 {
+        return false;
+    }
+    let diff = |a: &[f64], b: &[f64]| [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    let norm = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+
+    let mut axis = (0, 0, 0.0);
+    for i in 0..atoms.len() {
+        for j in (i + 1)..atoms.len() {
+            let dist = norm(diff(atoms[j], atoms[i]));
+            if dist > axis.2 {
+                axis = (i, j, dist);
+            }
+        }
+    }
+    let (origin, end, length) = axis;
+    if length == 0.0 {
+        return false;
+    }
+    let u = diff(atoms[end], atoms[origin]).map(|x| x / length);
+    atoms.iter().all(|atom| {
+        let v = diff(atom, atoms[origin]);
+        let cross =
+            [u[1] * v[2] - u[2] * v[1], u[2] * v[0] - u[0] * v[2], u[0] * v[1] - u[1] * v[0]];
+        norm(cross) < tol
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_near_linear() {
+        let co2 = [0.0, 0.0, 0.0, 0.0, 0.0, 1.16, 0.0, 0.0, -1.16];
+        assert!(is_near_linear(&co2, 1.0e-2));
+        let water = [0.0, 0.3, 0.0, 0.9, 0.8, 0.0, -0.9, 0.5, 0.0];
+        assert!(!is_near_linear(&water, 1.0e-2));
+        assert!(!is_near_linear(&co2[..6], 1.0e-2));
+    }
+
+    #[test]
+    fn test_length_unit() {
+        let bohr = [0.0, 0.0, 1.0 / BOHR2ANG];
+        assert_eq!(LengthUnit::Bohr.to_angstrom(&bohr)[2], 1.0);
+        assert_eq!(LengthUnit::Bohr.from_angstrom(&[0.0, 0.0, 1.0]), bohr);
+        assert_eq!(LengthUnit::Angstrom.to_angstrom(&bohr), bohr);
+    }
+
+    #[test]
+    fn test_parse_xyz() {
+        let xyz =
+            "2\nframe 1\nH 0.0 0.0 0.0\nCL 0.0 0.0 1.27\n2\nframe 2\nh 0 0 0\ncl 0 0 1.3 0.1\n\n";
+        let data = parse_xyz(xyz).unwrap();
+        assert_eq!(data.elem, vec!["H", "Cl"]);
+        assert_eq!(data.xyzs, vec![vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.27], vec![
+            0.0, 0.0, 0.0, 0.0, 0.0, 1.3
+        ]]);
+        assert_eq!(data.comments, vec!["frame 1", "frame 2"]);
+
+        assert!(parse_xyz("").is_err());
+        assert!(parse_xyz("2\n\nH 0 0 0\n").is_err());
+        assert!(parse_xyz("1\n\nH 0 0 x\n").is_err());
+        assert!(parse_xyz("1\n\nH 0 0 0\n1\n\nO 0 0 0\n").is_err());
+    }
+}
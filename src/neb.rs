@@ -0,0 +1,223 @@
+//! Nudged elastic band (NEB) calculations by geomeTRIC (`geometric.neb`).
+
+use crate::engine::EngineMixin;
+use crate::error::GeometricError;
+use crate::molecule::Molecule;
+use crate::util::toml2py;
+use crate::version::{require_geometric, NEB_MIN_VERSION};
+use pyo3::exceptions::{PyRuntimeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+use tempfile::NamedTempFile;
+
+/// Parameters of NEB calculations (builder).
+///
+/// Options left as `None` are not passed to geomeTRIC, so geomeTRIC's own
+/// defaults apply.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NebParams {
+    prefix: Option<String>,
+    images: Option<i64>,
+    nebk: Option<f64>,
+    climb: Option<f64>,
+    maxcyc: Option<i64>,
+}
+
+impl NebParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix of geomeTRIC output files (`prefix` keyword).
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Number of images of the band (`images` keyword). The input chain is
+    /// interpolated to this number of images.
+    pub fn images(mut self, images: i64) -> Self {
+        self.images = Some(images);
+        self
+    }
+
+    /// Spring constant between images in Hartree/Bohr^2 (`nebk` keyword).
+    pub fn spring_constant(mut self, nebk: f64) -> Self {
+        self.nebk = Some(nebk);
+        self
+    }
+
+    /// RMS gradient threshold (eV/Angstrom) below which the highest-energy
+    /// image becomes a climbing image (`climb` keyword).
+    pub fn climb(mut self, threshold: f64) -> Self {
+        self.climb = Some(threshold);
+        self
+    }
+
+    /// Maximum number of band optimization cycles (`maxcyc` keyword).
+    pub fn maxcyc(mut self, maxcyc: i64) -> Self {
+        self.maxcyc = Some(maxcyc);
+        self
+    }
+
+    /// Check the parameters for invalid values.
+    pub fn validate(&self) -> PyResult<()> {
+        if let Some(images) = self.images {
+            if images < 3 {
+                return Err(PyValueError::new_err(format!(
+                    "Parameter `images` must be at least 3, got {}",
+                    images
+                )));
+            }
+        }
+        for (key, value) in [("nebk", self.nebk), ("climb", self.climb)] {
+            if let Some(value) = value {
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(PyValueError::new_err(format!(
+                        "Parameter `{}` must be positive, got {}",
+                        key, value
+                    )));
+                }
+            }
+        }
+        if let Some(maxcyc) = self.maxcyc {
+            if maxcyc <= 0 {
+                return Err(PyValueError::new_err(format!(
+                    "Parameter `maxcyc` must be positive, got {}",
+                    maxcyc
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert to TOML table, with geomeTRIC keywords as keys.
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::Table::new();
+        let mut insert = |key: &str, value: Option<toml::Value>| {
+            if let Some(value) = value {
+                table.insert(key.into(), value);
+            }
+        };
+        insert("prefix", self.prefix.clone().map(Into::into));
+        insert("images", self.images.map(Into::into));
+        insert("nebk", self.nebk.map(Into::into));
+        insert("climb", self.climb.map(Into::into));
+        insert("maxcyc", self.maxcyc.map(Into::into));
+        toml::Value::Table(table)
+    }
+
+    /// Validate and convert to `Py<PyDict>`.
+    pub fn to_py(&self) -> PyResult<Py<PyDict>> {
+        self.validate()?;
+        toml2py(&self.to_toml())
+    }
+}
+
+/// Result of a NEB calculation.
+///
+/// - `elem`: Element symbols of the molecule.
+/// - `images`: Coordinates of each image of the final band in Angstrom,
+///   flattened (natom * 3).
+/// - `energies`: Energy of each image in Hartree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NebResult {
+    pub elem: Vec<String>,
+    pub images: Vec<Vec<f64>>,
+    pub energies: Vec<f64>,
+}
+
+impl NebResult {
+    /// Index of the highest-energy image, a guess of the transition state.
+    pub fn highest_image(&self) -> Option<usize> {
+        (0..self.energies.len()).max_by(|&i, &j| self.energies[i].total_cmp(&self.energies[j]))
+    }
+
+    /// Energy barrier from the first image (Hartree).
+    pub fn barrier(&self) -> Option<f64> {
+        Some(self.energies[self.highest_image()?] - self.energies[0])
+    }
+}
+
+/// Run NEB with the custom engine.
+///
+/// - `custom_engine`: The custom engine, whose molecule holds the initial chain
+///   as frames (at least reactant and product).
+/// - `params`: NEB parameters.
+///
+/// Image energies are taken from geomeTRIC if available, otherwise from the
+/// engine's record of driver evaluations. Requires geomeTRIC >= 1.1.
+pub fn run_neb(custom_engine: PyObject, params: &NebParams) -> Result<NebResult, GeometricError> {
+    require_geometric("NEB", NEB_MIN_VERSION)?;
+    let kwargs = params.to_py()?;
+    Python::with_gil(|py| {
+        let nframe = Molecule::from(custom_engine.getattr(py, "M")?).xyzs()?.len();
+        if nframe < 2 {
+            return Err(PyValueError::new_err(format!(
+                "NEB requires a chain of at least 2 frames, got {}",
+                nframe
+            )));
+        }
+        let kwargs = kwargs.bind(py);
+        let tmpfile = NamedTempFile::new()?;
+        kwargs.set_item("input", tmpfile.path().to_str().unwrap())?;
+        kwargs.set_item("customengine", custom_engine.clone_ref(py))?;
+
+        let run_neb = py.import("geometric.neb")?.getattr("run_neb")?;
+        let mut chain = run_neb.call((), Some(kwargs))?;
+        if let Ok(tuple) = chain.downcast::<PyTuple>() {
+            chain = tuple.get_item(0)?;
+        }
+        if chain.hasattr("M")? {
+            chain = chain.getattr("M")?;
+        }
+
+        let molecule = Molecule::from(chain.clone().unbind());
+        let elem = molecule.elem()?;
+        let images = molecule.xyzs()?;
+        let energies = match chain.getattr("qm_energies") {
+            Ok(energies) => energies.extract::<Vec<f64>>()?,
+            Err(_) => {
+                let engine = custom_engine.bind(py).downcast::<EngineMixin>()?.borrow();
+                images
+                    .iter()
+                    .map(|xyz| {
+                        engine
+                            .find_record(xyz)
+                            .map(|r| r.energy)
+                            .ok_or_else(|| PyRuntimeError::new_err("Energy of NEB image not found"))
+                    })
+                    .collect::<PyResult<_>>()?
+            },
+        };
+        Ok(NebResult { elem, images, energies })
+    })
+    .map_err(GeometricError::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neb_params() {
+        let params = NebParams::new().images(11).spring_constant(1.0).climb(0.5);
+        assert!(params.validate().is_ok());
+        assert_eq!(params.to_toml()["images"].as_integer(), Some(11));
+        assert_eq!(params.to_toml()["nebk"].as_float(), Some(1.0));
+
+        assert!(NebParams::new().images(2).validate().is_err());
+        assert!(NebParams::new().spring_constant(-1.0).validate().is_err());
+    }
+
+    #[test]
+    fn test_neb_result() {
+        let result = NebResult {
+            elem: vec!["H".into()],
+            images: vec![vec![0.0; 3]; 3],
+            energies: vec![-1.0, -0.9, -1.05],
+        };
+        assert_eq!(result.highest_image(), Some(1));
+        assert!((result.barrier().unwrap() - 0.1).abs() < 1.0e-12);
+    }
+}
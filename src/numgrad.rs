@@ -0,0 +1,129 @@
+//! Numerical gradients of drivers providing only energies.
+//!
+//! [`EnergyOnlyDriver`] implements [`GeomDriverAPI`] by central differences of
+//! energies, so engines without analytic gradients (e.g. some semi-empirical
+//! or multi-reference methods) can still be optimized by geomeTRIC. This costs
+//! 6N + 1 energy evaluations per gradient.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::hessian::check_displacement;
+use crate::interface::{AtomData, DriverError, GeomDriverAPI, GradOutput};
+
+/// Energy of the system, for engines without gradients.
+///
+/// Arguments are the same to [`GeomDriverAPI::calc_new`]. Evaluations take
+/// `&self`, so that displacements can be evaluated concurrently; closures
+/// `Fn(&[f64], &str) -> Result<f64, DriverError> + Send + Sync` implement this
+/// trait.
+pub trait EnergyDriverAPI: Send + Sync {
+    fn calc_energy(&self, coords: &[f64], dirname: &str) -> Result<f64, DriverError>;
+
+    /// See [`GeomDriverAPI::set_atom_data`].
+    fn set_atom_data(&mut self, _atom_data: &AtomData) {}
+}
+
+impl<F> EnergyDriverAPI for F
+where
+    F: Fn(&[f64], &str) -> Result<f64, DriverError> + Send + Sync,
+{
+    fn calc_energy(&self, coords: &[f64], dirname: &str) -> Result<f64, DriverError> {
+        self(coords, dirname)
+    }
+}
+
+/// Adapter computing gradients of an [`EnergyDriverAPI`] by central
+/// differences.
+///
+/// - `driver`: The energy driver.
+/// - `step`: Displacement (Bohr) of each coordinate (default 1e-3).
+/// - `threads`: Number of threads evaluating displaced energies concurrently
+///   (default 1); all evaluations get the same `dirname`.
+pub struct EnergyOnlyDriver<E: EnergyDriverAPI> {
+    pub driver: E,
+    pub step: f64,
+    pub threads: usize,
+}
+
+impl<E: EnergyDriverAPI> EnergyOnlyDriver<E> {
+    pub fn new(driver: E) -> Self {
+        EnergyOnlyDriver { driver, step: 1e-3, threads: 1 }
+    }
+
+    /// Displacement (Bohr) of each coordinate.
+    pub fn step(mut self, step: f64) -> Self {
+        self.step = step;
+        self
+    }
+
+    /// Number of threads evaluating displacements.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Gradient component `i`.
+    fn component(&self, coords: &[f64], dirname: &str, i: usize) -> Result<f64, DriverError> {
+        let mut displaced = coords.to_vec();
+        displaced[i] = coords[i] + self.step;
+        let e_plus = self.driver.calc_energy(&displaced, dirname)?;
+        displaced[i] = coords[i] - self.step;
+        let e_minus = self.driver.calc_energy(&displaced, dirname)?;
+        Ok((e_plus - e_minus) / (2.0 * self.step))
+    }
+}
+
+impl<E: EnergyDriverAPI> GeomDriverAPI for EnergyOnlyDriver<E> {
+    fn calc_new(&mut self, coords: &[f64], dirname: &str) -> Result<GradOutput, DriverError> {
+        check_displacement(coords, self.step)?;
+        let energy = self.driver.calc_energy(coords, dirname)?;
+        if self.threads <= 1 {
+            let gradient = (0..coords.len())
+                .map(|i| self.component(coords, dirname, i))
+                .collect::<Result<_, _>>()?;
+            return Ok(GradOutput::new(energy, gradient));
+        }
+        let this = &*self;
+        let next = AtomicUsize::new(0);
+        let gradient = Mutex::new(vec![0.0; coords.len()]);
+        let worker = || -> Result<(), DriverError> {
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= coords.len() {
+                    return Ok(());
+                }
+                let component = this.component(coords, dirname, i)?;
+                gradient.lock().unwrap()[i] = component;
+            }
+        };
+        thread::scope(|scope| {
+            let handles = (0..this.threads).map(|_| scope.spawn(worker)).collect::<Vec<_>>();
+            handles.into_iter().try_for_each(|handle| handle.join().unwrap())
+        })?;
+        Ok(GradOutput::new(energy, gradient.into_inner().unwrap()))
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.driver.set_atom_data(atom_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_only_driver() {
+        // E = sum(x^3), with exact central differences up to step^2
+        let energy = |coords: &[f64], _: &str| Ok(coords.iter().map(|x| x.powi(3)).sum());
+        let coords = [0.1, -0.2, 0.3, 0.5, 0.0, -0.4];
+        let exact = coords.iter().map(|x| 3.0 * x * x + 1e-6).collect::<Vec<_>>();
+        let serial = EnergyOnlyDriver::new(energy).calc_new(&coords, "").unwrap();
+        assert!(serial.gradient.iter().zip(&exact).all(|(g, e)| (g - e).abs() < 1e-12));
+        let parallel = EnergyOnlyDriver::new(energy).threads(4).calc_new(&coords, "").unwrap();
+        assert_eq!(parallel, serial);
+        assert!(EnergyOnlyDriver::new(energy).step(0.0).calc_new(&coords, "").is_err());
+    }
+}
@@ -1,23 +1,298 @@
 //! Main optimizer interface for geomeTRIC.
 
+use std::ffi::CString;
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::callback::{CallbackHandle, StepInfo};
+use crate::checkpoint::Checkpoint;
+use crate::constraints::Constraints;
+use crate::engine::{
+    init_pyo3_engine, CancelToken, DriverTimeBudgetExceeded, EnergyTarget, EnergyTargetReached,
+    EngineMixin, NonFinitePolicy, OptimizationCancelled,
+};
+use crate::error::{is_not_converged, GeometricError};
+use crate::frequency::{run_frequencies, Frequencies};
+use crate::hessian::{final_hessian_path, read_hessian, write_hessian};
+use crate::interface::{with_driver, GeomDriverAPI, PyGeomDriver};
+use crate::molecule::{is_near_linear, LengthUnit, Molecule, MoleculeSpec};
+use crate::params::{ConvergencePreset, CoordSys, OptimizerParams};
+use crate::pyarray::{PyArray1, PyArrayMethods};
+use crate::result::{atom_permutation, OptResult, OptimizationOutcome, Progress, BOHR2ANG};
+use crate::runtime::initialize_python;
+use crate::scratch::ScratchManager;
+use crate::util::toml2py;
+use pyo3::exceptions::{PyRuntimeError, PyUserWarning, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
-use tempfile::NamedTempFile;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyDict, PyString};
+use pyo3::PyTypeInfo;
+use tempfile::{NamedTempFile, TempDir};
+
+/// Collinearity tolerance (Angstrom) of near-linear molecule detection.
+///
+/// A molecule is considered near-linear if all atoms are within this distance
+/// of the axis through the two atoms farthest apart (see
+/// [`is_near_linear`]).
+pub const LINEAR_TOLERANCE: f64 = 1.0e-2;
+
+/// Function `geometric.optimize.run_optimizer`, imported once per process.
+static RUN_OPTIMIZER: GILOnceCell<PyObject> = GILOnceCell::new();
+
+/// Convergence keywords of geomeTRIC, replaced by
+/// [`RunOptions::convergence`].
+const CONVERGENCE_KEYS: [&str; 6] = [
+    "convergence_set",
+    "convergence_energy",
+    "convergence_grms",
+    "convergence_gmax",
+    "convergence_drms",
+    "convergence_dmax",
+];
+
+/// Floating-point error handling mode of numpy (argument of `numpy.seterr`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumpyErrorMode {
+    Ignore,
+    Warn,
+    Raise,
+    Print,
+    Log,
+}
+
+impl NumpyErrorMode {
+    /// String recognized by `numpy.seterr`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NumpyErrorMode::Ignore => "ignore",
+            NumpyErrorMode::Warn => "warn",
+            NumpyErrorMode::Raise => "raise",
+            NumpyErrorMode::Print => "print",
+            NumpyErrorMode::Log => "log",
+        }
+    }
+}
+
+/// Handling of near-linear input geometries.
+///
+/// Internal coordinates (especially TRIC) can become singular for linear
+/// arrangements of atoms (e.g. CO2 at equilibrium), which leads to confusing
+/// failures. Near-linearity is detected with [`LINEAR_TOLERANCE`].
+///
+/// - `Ignore`: Do not check.
+/// - `Warn`: Emit a python `UserWarning` recommending a robust coordinate
+///   system (default).
+/// - `SwitchCoordSys`: Switch to the given coordinate system (e.g.
+///   [`CoordSys::Cart`]) and warn. This is only applied when `coordsys` is not
+///   given explicitly in parameters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinearMoleculePolicy {
+    Ignore,
+    #[default]
+    Warn,
+    SwitchCoordSys(CoordSys),
+}
+
+/// User hook executed under the GIL, see [`RunOptions::setup`].
+#[derive(Clone)]
+pub struct RunHook(pub Arc<dyn Fn(Python<'_>) -> PyResult<()> + Send + Sync>);
+
+impl RunHook {
+    pub fn new(hook: impl Fn(Python<'_>) -> PyResult<()> + Send + Sync + 'static) -> Self {
+        RunHook(Arc::new(hook))
+    }
+}
+
+impl fmt::Debug for RunHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RunHook")
+    }
+}
+
+/// Options of [`run_optimization_with_options`] that are handled by this
+/// crate, instead of being passed to geomeTRIC.
+///
+/// - `numpy_errstate`: If set, `numpy.seterr(all=...)` is applied during the
+///   optimization, and the previous error state is restored afterwards
+///   (regardless of success or failure). Use [`NumpyErrorMode::Raise`] to raise
+///   `FloatingPointError` at the first NaN/overflow, which gives a traceback
+///   near the cause when debugging numerically unstable drivers.
+/// - `linear_policy`: Handling of near-linear input geometries, see
+///   [`LinearMoleculePolicy`].
+/// - `non_finite_policy`: Handling of non-finite energy or gradient returned by
+///   the driver, see [`NonFinitePolicy`].
+/// - `initial_coords`: Starting coordinates (Angstrom, flattened natom * 3)
+///   overriding those of the engine's molecule, e.g. a geometry optimized by a
+///   cheaper method. The engine's molecule is replaced by a copy with these
+///   coordinates (see [`Molecule::with_coords`]) during the run, so all other
+///   metadata is kept; the original molecule object is not modified, and is set
+///   back to the engine after the run regardless of outcome.
+/// - `run_id`: Label of the run (e.g. a job id), for correlating geomeTRIC
+///   output with external tracing. It is appended to the output `prefix`
+///   (`<prefix>_<run_id>`, or `<run_id>` in the directory of the input file if
+///   no prefix is given), so output files of concurrent runs are distinguished,
+///   and is kept in [`OptResult::run_id`]. Only ASCII alphanumerics, `-`, `_`
+///   and `.` are allowed.
+/// - `stop_at_energy`: Stop once the energy crosses the target (from above or
+///   below, see [`EnergyTarget`]), returning
+///   [`OptimizationOutcome::EnergyTarget`] with the geometry at the crossing
+///   point. This produces a non-stationary geometry, and is distinct from
+///   normal convergence.
+/// - `cancel_token`: Cancel the optimization from another thread (see
+///   [`CancelToken`]), returning [`OptimizationOutcome::Cancelled`] with the
+///   trajectory evaluated so far. The token is checked before each driver
+///   evaluation.
+/// - `timeout`: Wall-clock time limit of the optimization, checked before each
+///   driver evaluation; once exceeded, the optimization stops and returns
+///   [`OptimizationOutcome::MaxTime`] with the partial result, instead of the
+///   caller having to kill the process. With `coordsys_fallback`, each attempt
+///   has its own limit.
+/// - `state`: Electronic state to be optimized (0 for the ground state, `n` for
+///   the `n`-th excited state), passed to the driver as
+///   [`AtomData::state`](crate::interface::AtomData). If `None`, the state set
+///   on the engine is kept (ground state by default). geomeTRIC has no option
+///   for the state of custom engines, so nothing is passed to geomeTRIC.
+/// - `constraints`: Constraints of the optimization (see [`Constraints`]),
+///   written to a temporary constraints file passed to geomeTRIC.
+/// - `coordsys_fallback`: If not empty, the optimization is attempted with each
+///   coordinate system in order (overriding `coordsys` in parameters), until
+///   one converges; the one used is recorded in [`OptResult::coordsys`]. A
+///   common choice is `[Tric, Cart]`. Note that failed attempts are not free:
+///   each may cost up to `maxiter` driver evaluations before geomeTRIC gives
+///   up. If all attempts fail, the error or non-converged outcome of the last
+///   attempt is returned.
+/// - `convergence`: Convergence criteria (see [`ConvergencePreset`]),
+///   overriding those given in parameters.
+/// - `in_memory`: Write all output files of geomeTRIC (log, `_optim.xyz`,
+///   scratch directory, etc.) to a temporary directory instead of next to
+///   `prefix` or `input`, and remove it after the run. The log text is kept in
+///   [`OptResult::log`]; the trajectory is in the result anyway.
+/// - `initial_hessian`: Cartesian Hessian (Hartree/Bohr², flattened natom * 3
+///   by natom * 3) used as the initial Hessian, written to a temporary file
+///   passed to geomeTRIC (`hessian = "file:<path>"`, overriding `hessian` in
+///   parameters).
+/// - `callback`: Callback invoked after every geomeTRIC iteration, see
+///   [`OptCallback`](crate::callback::OptCallback).
+/// - `scratch`: Create a scratch directory for every driver evaluation (see
+///   [`ScratchManager`]), passed to the driver as `dirname`. If `run_id` is
+///   set, directories are placed under `<root>/<run_id>`, so that concurrent
+///   runs do not collide.
+/// - `setup`, `teardown`: Escape hatch to run custom python code (e.g. tweak
+///   geomeTRIC's global state or monkeypatch a writer). `setup` runs right
+///   before `run_optimizer` is called, after all engine and driver setup
+///   (including the options above) is done; an error of `setup` aborts the run.
+///   `teardown` runs right after `run_optimizer` returns, regardless of success
+///   or failure, before results are extracted. An error of `teardown` is
+///   returned only if the optimization itself succeeded.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    pub numpy_errstate: Option<NumpyErrorMode>,
+    pub linear_policy: LinearMoleculePolicy,
+    pub non_finite_policy: NonFinitePolicy,
+    pub initial_coords: Option<Vec<f64>>,
+    pub run_id: Option<String>,
+    pub stop_at_energy: Option<EnergyTarget>,
+    pub cancel_token: Option<CancelToken>,
+    pub timeout: Option<Duration>,
+    pub state: Option<usize>,
+    pub constraints: Option<Constraints>,
+    pub coordsys_fallback: Vec<CoordSys>,
+    pub convergence: Option<ConvergencePreset>,
+    pub in_memory: bool,
+    pub initial_hessian: Option<Vec<f64>>,
+    pub callback: Option<CallbackHandle>,
+    pub scratch: Option<ScratchManager>,
+    pub setup: Option<RunHook>,
+    pub teardown: Option<RunHook>,
+}
+
+impl RunOptions {
+    /// Freeze Cartesian coordinates of atoms (0-based indices), e.g. substrate
+    /// atoms of surface or cluster models.
+    ///
+    /// This adds a `$freeze xyz` block to `constraints` (see
+    /// [`Constraints::freeze_cartesian`]), keeping other constraints.
+    pub fn freeze_atoms(mut self, atoms: &[usize]) -> Self {
+        let constraints = self.constraints.take().unwrap_or_default();
+        self.constraints = Some(constraints.freeze_cartesian(atoms));
+        self
+    }
+}
 
 /// Run the optimization using the custom engine and parameters.
 ///
 /// - `custom_engine`: The custom engine to use for the optimization.
-/// - `params`: The parameters for the optimization.
+/// - `params`: The parameters for the optimization. If `coordsys` is not given,
+///   the driver's
+///   [`preferred_coordsys`](crate::interface::GeomDriverAPI::preferred_coordsys)
+///   is used, falling back to geomeTRIC's default.
 /// - `input`: Optional input file path. If `None`, a temporary file will be
 ///   created.
+///
+/// If a driver time budget is set on the engine (`set_time_budget`) or a
+/// wall-clock [`RunOptions::timeout`] is given, and it is exhausted, the
+/// optimization stops and [`OptimizationOutcome::MaxTime`] is returned instead
+/// of an error. Likewise, if geomeTRIC gives up without
+/// convergence (e.g. `maxiter` is reached),
+/// [`OptimizationOutcome::NotConverged`] is returned with the trajectory so
+/// far. Other failures are classified by [`GeometricError`].
 pub fn run_optimization(
     custom_engine: PyObject,
     params: &Py<PyDict>,
     input: Option<&str>,
-) -> PyResult<PyObject> {
+) -> Result<OptimizationOutcome, GeometricError> {
+    run_optimization_with_options(custom_engine, params, input, &RunOptions::default())
+}
+
+/// Run the optimization, sending a [`StepInfo`] through `sender` after every
+/// iteration (driver evaluation), e.g. for live display of convergence.
+///
+/// The run is not affected if the receiver is dropped. See [`run_optimization`]
+/// for the other arguments; with other options, set
+/// [`RunOptions::callback`] instead.
+pub fn run_optimization_with_progress(
+    custom_engine: PyObject,
+    params: &Py<PyDict>,
+    input: Option<&str>,
+    sender: Sender<StepInfo>,
+) -> Result<OptimizationOutcome, GeometricError> {
+    let callback = CallbackHandle::new(move |info: &StepInfo| {
+        let _ = sender.send(info.clone());
+    });
+    let options = RunOptions { callback: Some(callback), ..Default::default() };
+    run_optimization_with_options(custom_engine, params, input, &options)
+}
+
+/// Run the optimization, with additional options handled by this crate.
+///
+/// See [`run_optimization`] for the other arguments, and [`RunOptions`] for
+/// the options.
+pub fn run_optimization_with_options(
+    custom_engine: PyObject,
+    params: &Py<PyDict>,
+    input: Option<&str>,
+    options: &RunOptions,
+) -> Result<OptimizationOutcome, GeometricError> {
+    if options.timeout == Some(Duration::ZERO) {
+        let err = PyValueError::new_err("Option `timeout` must not be zero");
+        return Err(GeometricError::InvalidParameters(err));
+    }
+    if !options.coordsys_fallback.is_empty() {
+        return run_with_coordsys_fallback(custom_engine, params, input, options);
+    }
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("optimization", run_id = options.run_id.as_deref()).entered();
+
     Python::with_gil(|py| {
         // Import the geometric Python module
-        let run_optimizer = py.import("geometric.optimize")?.getattr("run_optimizer")?;
+        let run_optimizer = RUN_OPTIMIZER.get_or_try_init(py, || {
+            Ok::<_, PyErr>(py.import("geometric.optimize")?.getattr("run_optimizer")?.unbind())
+        })?;
+        let run_optimizer = run_optimizer.bind(py);
 
         // kwargs for run_optimizer: make a deep copy of the params
         let deepcopy = py.import("copy")?.getattr("deepcopy")?;
@@ -34,8 +309,860 @@ pub fn run_optimization(
         }
 
         // Update custom_engine in kwargs
-        kwargs.set_item("customengine", custom_engine)?;
-        let result = run_optimizer.call((), Some(&kwargs))?;
-        Ok(result.into())
+        kwargs.set_item("customengine", custom_engine.clone_ref(py))?;
+
+        // Wall-clock timeout is handled by the engine, not geomeTRIC
+        let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+
+        // Label output files with run id
+        if let Some(run_id) = &options.run_id {
+            validate_run_id(run_id)?;
+            let prefix = match kwargs.get_item("prefix")? {
+                Some(prefix) => format!("{}_{}", prefix.str()?, run_id),
+                None => {
+                    let input = kwargs.get_item("input")?.unwrap().str()?.to_string();
+                    let dir = Path::new(&input).parent().unwrap_or(Path::new(""));
+                    dir.join(run_id).to_string_lossy().into_owned()
+                },
+            };
+            kwargs.set_item("prefix", prefix)?;
+        }
+
+        // Relocate output files to a temporary directory, keeping the file
+        // name of the prefix; the log file is read after the run.
+        let output_dir = match options.in_memory {
+            true => {
+                let dir = TempDir::new()?;
+                let name = match kwargs.get_item("prefix")? {
+                    Some(prefix) => Path::new(&prefix.str()?.to_string())
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_else(|| "geometric".to_string()),
+                    None => "geometric".to_string(),
+                };
+                let prefix = dir.path().join(name);
+                let prefix = prefix.to_str().unwrap();
+                kwargs.set_item("prefix", prefix)?;
+                Some((dir, format!("{}.log", prefix)))
+            },
+            false => None,
+        };
+
+        // Convergence criteria; keywords in parameters are dropped, so that
+        // explicit thresholds do not take precedence over the given preset.
+        if let Some(convergence) = &options.convergence {
+            convergence.validate()?;
+            for key in CONVERGENCE_KEYS {
+                if kwargs.contains(key)? {
+                    kwargs.del_item(key)?;
+                }
+            }
+            kwargs.update(
+                toml2py(&toml::Value::Table(convergence.to_toml()))?.bind(py).as_mapping(),
+            )?;
+        }
+
+        // Override starting coordinates of the engine's molecule; the original
+        // molecule is set back when the guard is dropped, regardless of outcome.
+        let _molecule_guard = match &options.initial_coords {
+            Some(initial_coords) => {
+                let engine = custom_engine.bind(py);
+                let guard =
+                    RestoreMolecule { engine: engine.clone(), molecule: engine.getattr("M")? };
+                let molecule = Molecule::from(guard.molecule.clone().unbind());
+                engine.setattr("M", molecule.with_coords(initial_coords)?.pyobj)?;
+                Some(guard)
+            },
+            None => None,
+        };
+
+        // Write constraints file; kept until the optimization finishes
+        let constraints_file = match &options.constraints {
+            Some(constraints) => {
+                let natom = Molecule::from(custom_engine.getattr(py, "M")?).natom()?;
+                let mut file = NamedTempFile::new()?;
+                file.write_all(constraints.to_geometric_string(natom)?.as_bytes())?;
+                file.flush()?;
+                kwargs.set_item("constraints", file.path().to_str().unwrap())?;
+                Some(file)
+            },
+            None => None,
+        };
+
+        // Write initial Hessian file; kept until the optimization finishes
+        let hessian_file = match &options.initial_hessian {
+            Some(hessian) => {
+                let file = NamedTempFile::new()?;
+                write_hessian(file.path(), hessian)?;
+                kwargs.set_item("hessian", format!("file:{}", file.path().to_str().unwrap()))?;
+                Some(file)
+            },
+            None => None,
+        };
+
+        // Hessian at the final geometry is written by geomeTRIC under the
+        // output prefix, which defaults to the input path without extension.
+        let final_hessian = match kwargs.get_item("hessian")? {
+            Some(hessian) if hessian.str()?.to_string().contains("last") => {
+                let prefix = match kwargs.get_item("prefix")? {
+                    Some(prefix) => prefix.str()?.to_string(),
+                    None => {
+                        let input = kwargs.get_item("input")?.unwrap().str()?.to_string();
+                        Path::new(&input).with_extension("").to_string_lossy().into_owned()
+                    },
+                };
+                Some(final_hessian_path(&prefix))
+            },
+            _ => None,
+        };
+
+        // Engines of other types (e.g. geomeTRIC's `ConicalIntersection`
+        // wrapping engines of this crate) do not handle the options below.
+        if let Ok(engine) = custom_engine.bind(py).downcast::<EngineMixin>() {
+            // Use coordinate system preferred by the driver, if not given
+            // explicitly
+            if !kwargs.contains("coordsys")? {
+                if let Some(coordsys) = engine.borrow().preferred_coordsys() {
+                    kwargs.set_item("coordsys", coordsys.as_str())?;
+                }
+            }
+
+            // Policies handled by the engine
+            engine.borrow_mut().set_non_finite_policy(options.non_finite_policy);
+            engine.borrow_mut().set_energy_target(options.stop_at_energy);
+            engine.borrow_mut().set_cancel_token(options.cancel_token.clone());
+            engine.borrow_mut().set_deadline(deadline);
+            engine.borrow_mut().begin_run();
+            if let Some(state) = options.state {
+                engine.borrow_mut().set_state(state);
+            }
+            engine.borrow_mut().set_callback(options.callback.clone());
+            let scratch = match (&options.scratch, &options.run_id) {
+                (Some(scratch), Some(run_id)) => Some(scratch.subdirectory(run_id)),
+                (scratch, _) => scratch.clone(),
+            };
+            engine.borrow_mut().set_scratch(scratch);
+        } else if options.timeout.is_some() {
+            let message = c"Option `timeout` is ignored for engines not of this crate.";
+            PyErr::warn(py, PyUserWarning::type_object(py).as_any(), message, 0)?;
+        }
+
+        // Check near-linear input geometry, which may make internal coordinates
+        // singular.
+        let molecule = custom_engine.bind(py).getattr("M")?;
+        check_linear_molecule(&molecule, &kwargs, options.linear_policy)?;
+
+        // User setup hook, after all engine and driver setup
+        if let Some(setup) = &options.setup {
+            (setup.0)(py)?;
+        }
+
+        // Apply numpy error state; the previous state is restored after the run
+        // regardless of outcome.
+        let numpy_errstate = match options.numpy_errstate {
+            Some(mode) => {
+                let kwargs = PyDict::new(py);
+                kwargs.set_item("all", mode.as_str())?;
+                Some(py.import("numpy")?.call_method("seterr", (), Some(&kwargs))?)
+            },
+            None => None,
+        };
+        let result = run_optimizer.call((), Some(&kwargs));
+        let result = match (&options.teardown, result) {
+            (Some(teardown), Ok(progress)) => (teardown.0)(py).map(|_| progress),
+            (Some(teardown), Err(err)) => {
+                let _ = (teardown.0)(py);
+                Err(err)
+            },
+            (None, result) => result,
+        };
+        if let Some(old_errstate) = numpy_errstate {
+            let old_errstate = old_errstate.downcast_into::<PyDict>()?;
+            py.import("numpy")?.call_method("seterr", (), Some(&old_errstate))?;
+        }
+
+        let mut outcome = match result {
+            Ok(progress) => {
+                let mut result = OptResult::from_progress(&progress)?;
+                if let Ok(engine) = custom_engine.bind(py).downcast::<EngineMixin>() {
+                    engine.borrow().fill_result(&mut result);
+                }
+                // input molecule of the engine; its first frame is the starting geometry
+                let input = Molecule::from(custom_engine.getattr(py, "M")?);
+                if let (Some(ref_xyz), Some(xyz)) = (input.xyzs()?.first(), result.xyzs.first()) {
+                    result.atom_permutation =
+                        atom_permutation(&input.elem()?, ref_xyz, &result.elem, xyz);
+                }
+                result.converged = true;
+                result.hessian = final_hessian.and_then(|path| read_hessian(path).ok());
+                OptimizationOutcome::Converged(result)
+            },
+            Err(err) if err.is_instance_of::<DriverTimeBudgetExceeded>(py) => {
+                let engine = custom_engine.bind(py);
+                let molecule = engine.getattr("M")?;
+                let engine = engine.downcast::<EngineMixin>()?.borrow();
+                OptimizationOutcome::MaxTime(engine.best_result(&molecule)?)
+            },
+            Err(err) if err.is_instance_of::<EnergyTargetReached>(py) => {
+                let engine = custom_engine.bind(py);
+                let molecule = engine.getattr("M")?;
+                let engine = engine.downcast::<EngineMixin>()?.borrow();
+                OptimizationOutcome::EnergyTarget(engine.last_result(&molecule)?)
+            },
+            Err(err) if err.is_instance_of::<OptimizationCancelled>(py) => {
+                let engine = custom_engine.bind(py);
+                let molecule = engine.getattr("M")?;
+                let engine = engine.downcast::<EngineMixin>()?.borrow();
+                OptimizationOutcome::Cancelled(engine.last_result(&molecule)?)
+            },
+            Err(err) if is_not_converged(py, &err) => {
+                let engine = custom_engine.bind(py);
+                let molecule = engine.getattr("M")?;
+                let reason = err.value(py).str()?.to_string();
+                match engine.downcast::<EngineMixin>() {
+                    Ok(engine) => OptimizationOutcome::NotConverged {
+                        result: engine.borrow().last_result(&molecule)?,
+                        reason,
+                    },
+                    Err(_) => return Err(err),
+                }
+            },
+            Err(err) => return Err(err),
+        };
+        outcome.result_mut().run_id = options.run_id.clone();
+        if let Ok(engine) = custom_engine.bind(py).downcast::<EngineMixin>() {
+            outcome.result_mut().stats = engine.borrow().call_stats();
+        }
+        if let (Some(constraints), Some(xyz)) = (&options.constraints, outcome.result().xyzs.last())
+        {
+            outcome.result_mut().constraint_report = constraints.report(xyz)?;
+        }
+        if let Some((_, log_path)) = &output_dir {
+            outcome.result_mut().log = std::fs::read_to_string(log_path).ok();
+        }
+        drop(constraints_file);
+        drop(hessian_file);
+        drop(output_dir);
+        Ok(outcome)
     })
+    .map_err(GeometricError::from)
+}
+
+/// Attempt the optimization with each coordinate system of
+/// `options.coordsys_fallback` in order.
+fn run_with_coordsys_fallback(
+    custom_engine: PyObject,
+    params: &Py<PyDict>,
+    input: Option<&str>,
+    options: &RunOptions,
+) -> Result<OptimizationOutcome, GeometricError> {
+    let mut options = options.clone();
+    let coordsys_fallback = std::mem::take(&mut options.coordsys_fallback);
+    let mut last = None;
+    for coordsys in coordsys_fallback {
+        let params = Python::with_gil(|py| -> PyResult<Py<PyDict>> {
+            let params = params.bind(py).copy()?;
+            params.set_item("coordsys", coordsys.as_str())?;
+            Ok(params.unbind())
+        })?;
+        let engine = Python::with_gil(|py| custom_engine.clone_ref(py));
+        match run_optimization_with_options(engine, &params, input, &options) {
+            Ok(mut outcome) => {
+                outcome.result_mut().coordsys = Some(coordsys);
+                if !matches!(outcome, OptimizationOutcome::NotConverged { .. }) {
+                    return Ok(outcome);
+                }
+                last = Some(Ok(outcome));
+            },
+            Err(err) => last = Some(Err(err)),
+        }
+    }
+    last.unwrap()
+}
+
+/// Engines built into geomeTRIC (`engine` keyword), which run the external
+/// program themselves from an input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinEngine {
+    TeraChem,
+    QChem,
+    Psi4,
+    Gaussian,
+    Molpro,
+    CFour,
+    Gromacs,
+    OpenMM,
+}
+
+impl BuiltinEngine {
+    /// String of `engine` recognized by geomeTRIC.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuiltinEngine::TeraChem => "tera",
+            BuiltinEngine::QChem => "qchem",
+            BuiltinEngine::Psi4 => "psi4",
+            BuiltinEngine::Gaussian => "gaussian",
+            BuiltinEngine::Molpro => "molpro",
+            BuiltinEngine::CFour => "cfour",
+            BuiltinEngine::Gromacs => "gmx",
+            BuiltinEngine::OpenMM => "openmm",
+        }
+    }
+}
+
+/// Run the optimization with one of geomeTRIC's built-in engines instead of a
+/// custom driver.
+///
+/// - `engine`: The built-in engine.
+/// - `input_file`: Input of the program (e.g. a Psi4 or Q-Chem input), used by
+///   geomeTRIC as template of every calculation; the molecule is read from it.
+/// - `params`: The parameters for the optimization; `engine`, `input` and
+///   `customengine` are overridden.
+///
+/// Without a driver of this crate, the trajectory of a non-converged run is
+/// not available, so non-convergence is returned as
+/// [`GeometricError::NotConverged`].
+pub fn run_optimization_builtin(
+    engine: BuiltinEngine,
+    input_file: impl AsRef<Path>,
+    params: &Py<PyDict>,
+) -> Result<OptimizationOutcome, GeometricError> {
+    let input_file = input_file.as_ref();
+    if !input_file.is_file() {
+        return Err(GeometricError::InvalidParameters(PyValueError::new_err(format!(
+            "Input file {} of the {} engine does not exist",
+            input_file.display(),
+            engine.as_str()
+        ))));
+    }
+    initialize_python();
+    Python::with_gil(|py| -> PyResult<_> {
+        let run_optimizer = RUN_OPTIMIZER.get_or_try_init(py, || {
+            Ok::<_, PyErr>(py.import("geometric.optimize")?.getattr("run_optimizer")?.unbind())
+        })?;
+        let kwargs = py.import("copy")?.getattr("deepcopy")?.call1((params,))?;
+        let kwargs = kwargs.downcast_into::<PyDict>()?;
+        if kwargs.contains("customengine")? {
+            kwargs.del_item("customengine")?;
+        }
+        kwargs.set_item("engine", engine.as_str())?;
+        kwargs.set_item("input", input_file.to_string_lossy())?;
+        let progress = run_optimizer.bind(py).call((), Some(&kwargs))?;
+        let mut result = OptResult::from_progress(&progress)?;
+        result.converged = true;
+        Ok(OptimizationOutcome::Converged(result))
+    })
+    .map_err(GeometricError::from)
+}
+
+/// Guard setting `molecule` back as the molecule `M` of `engine` when dropped.
+struct RestoreMolecule<'py> {
+    engine: Bound<'py, PyAny>,
+    molecule: Bound<'py, PyAny>,
+}
+
+impl Drop for RestoreMolecule<'_> {
+    fn drop(&mut self) {
+        let _ = self.engine.setattr("M", &self.molecule);
+    }
+}
+
+/// Check that run id is usable in file names.
+fn validate_run_id(run_id: &str) -> PyResult<()> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.');
+    if run_id.is_empty() || !run_id.chars().all(valid) {
+        return Err(PyValueError::new_err(format!(
+            "Invalid run id {:?}: only ASCII alphanumerics, '-', '_' and '.' are allowed",
+            run_id
+        )));
+    }
+    Ok(())
+}
+
+/// Detect near-linear input geometry and apply the policy.
+fn check_linear_molecule(
+    molecule: &Bound<'_, PyAny>,
+    kwargs: &Bound<'_, PyDict>,
+    policy: LinearMoleculePolicy,
+) -> PyResult<()> {
+    let py = molecule.py();
+    if policy == LinearMoleculePolicy::Ignore {
+        return Ok(());
+    }
+    let xyz = molecule.getattr("xyzs")?.get_item(0)?;
+    let xyz = xyz.call_method0("flatten")?.call_method0("tolist")?.extract::<Vec<f64>>()?;
+    if !is_near_linear(&xyz, LINEAR_TOLERANCE) {
+        return Ok(());
+    }
+    let message = match (policy, kwargs.contains("coordsys")?) {
+        (LinearMoleculePolicy::SwitchCoordSys(coordsys), false) => {
+            kwargs.set_item("coordsys", coordsys.as_str())?;
+            format!(
+                "Input geometry is near-linear; switching coordinate system to `{}`.",
+                coordsys.as_str()
+            )
+        },
+        _ => "Input geometry is near-linear, where internal coordinates may be singular; \
+              consider `coordsys = \"cart\"`."
+            .to_string(),
+    };
+    let message = CString::new(message).unwrap();
+    PyErr::warn(py, PyUserWarning::type_object(py).as_any(), &message, 0)
+}
+
+/// Optimize the molecule, then run frequency analysis at the optimized
+/// geometry with the same driver.
+///
+/// [`Frequencies::n_imaginary`] of the returned frequencies tells whether a
+/// true minimum (zero) or a transition state (one, when `transition = true`)
+/// has been found. Frequencies are only computed at converged geometries, as
+/// those of other final geometries are meaningless: an optimization stopped
+/// before convergence (not converged, time limit exhausted or energy target
+/// reached) is returned as [`GeometricError::NotConverged`]; a cancelled one
+/// as [`GeometricError::Python`].
+pub fn optimize_and_characterize(
+    molecule: &Molecule,
+    driver: &PyGeomDriver,
+    params: &Py<PyDict>,
+) -> Result<(OptResult, Frequencies), GeometricError> {
+    let custom_engine = init_pyo3_engine(&molecule.pyobj, driver)?;
+    let result = match run_optimization(custom_engine, params, None)? {
+        OptimizationOutcome::Converged(result) => result,
+        OptimizationOutcome::NotConverged { reason, .. } => {
+            return Err(GeometricError::NotConverged(PyRuntimeError::new_err(reason)));
+        },
+        OptimizationOutcome::MaxTime(_) => {
+            return Err(GeometricError::NotConverged(DriverTimeBudgetExceeded::new_err(
+                "Time limit exhausted before convergence",
+            )));
+        },
+        OptimizationOutcome::EnergyTarget(_) => {
+            return Err(GeometricError::NotConverged(EnergyTargetReached::new_err(
+                "Energy target reached before convergence",
+            )));
+        },
+        OptimizationOutcome::Cancelled(_) => {
+            return Err(GeometricError::Python(OptimizationCancelled::new_err(
+                "Optimization cancelled",
+            )));
+        },
+    };
+    let optimized = molecule.with_coords(result.final_coords().unwrap())?;
+    let frequencies = run_frequencies(&optimized, driver)?;
+    Ok((result, frequencies))
+}
+
+/// Step-by-step optimization, wrapping geomeTRIC's `Optimizer` class.
+///
+/// Unlike [`run_optimization`], the caller owns the optimization loop:
+///
+/// ```ignore
+/// let mut session = OptimizerSession::new(custom_engine, &params, None)?;
+/// while !session.step()? {
+///     println!("{:?}", session.current_geometry()?);
+/// }
+/// let result = session.result()?;
+/// ```
+///
+/// The setup mirrors `geometric.optimize.run_optimizer` for the common
+/// parameters (`coordsys`, constraints and optimizer parameters); input files
+/// are not read, and [`RunOptions`] are not applied.
+///
+/// The state of a session can be saved to a [`Checkpoint`] (periodically with
+/// [`checkpoint_every`](Self::checkpoint_every)), and continued later by
+/// [`resume_optimization`](crate::checkpoint::resume_optimization).
+pub struct OptimizerSession {
+    optimizer: PyObject,
+    engine: PyObject,
+    started: bool,
+    _scratch: TempDir,
+    /// Parameters given to `new`, saved in checkpoints.
+    params: Py<PyDict>,
+    /// Constraints in geomeTRIC's format, saved in checkpoints.
+    constraints: Option<String>,
+    /// Cartesian Hessian replacing the guess Hessian of the first step.
+    initial_hessian: Option<Vec<f64>>,
+    /// Checkpoint file and interval (in steps).
+    checkpoint: Option<(PathBuf, usize)>,
+    /// Steps taken before this session (of a resumed optimization).
+    step_offset: usize,
+    /// Steps taken by this session.
+    steps: usize,
+}
+
+impl OptimizerSession {
+    /// Build the internal coordinates and the optimizer.
+    ///
+    /// - `custom_engine`: The custom engine, whose molecule gives the starting
+    ///   geometry.
+    /// - `params`: Parameters of geomeTRIC (as `run_optimizer` kwargs). If
+    ///   `coordsys` is not given, the driver's preferred coordinate system is
+    ///   used, falling back to `tric`.
+    /// - `constraints`: Optional constraints of the optimization.
+    ///
+    /// No driver evaluation is done until the first [`step`](Self::step).
+    pub fn new(
+        custom_engine: PyObject,
+        params: &Py<PyDict>,
+        constraints: Option<&Constraints>,
+    ) -> Result<Self, GeometricError> {
+        let constraints = match constraints {
+            Some(constraints) => Some(Python::with_gil(|py| {
+                let molecule = custom_engine.getattr(py, "M")?;
+                constraints.to_geometric_string(Molecule::from(molecule).natom()?)
+            })?),
+            None => None,
+        };
+        Self::with_constraint_string(custom_engine, params, constraints)
+    }
+
+    /// [`new`](Self::new), with constraints already in geomeTRIC's format.
+    pub(crate) fn with_constraint_string(
+        custom_engine: PyObject,
+        params: &Py<PyDict>,
+        constraints: Option<String>,
+    ) -> Result<Self, GeometricError> {
+        Python::with_gil(|py| -> PyResult<Self> {
+            let geometric_optimize = py.import("geometric.optimize")?;
+            let kwargs = params.bind(py).copy()?;
+            kwargs.set_item("customengine", custom_engine.clone_ref(py))?;
+            let opt_params = py.import("geometric.params")?.getattr("OptParams")?;
+            let opt_params = opt_params.call((), Some(&kwargs))?;
+
+            let engine = custom_engine.bind(py);
+            let molecule = engine.getattr("M")?;
+            let coordsys = match kwargs.get_item("coordsys")? {
+                Some(coordsys) => coordsys.extract::<String>()?.parse::<CoordSys>()?,
+                None => {
+                    let engine = engine.downcast::<EngineMixin>()?.borrow();
+                    engine.preferred_coordsys().unwrap_or(CoordSys::Tric)
+                },
+            };
+            let coord_class =
+                geometric_optimize.getattr("CoordSysDict")?.get_item(coordsys.as_str())?;
+            let (coord_class, connect, addcart) =
+                coord_class.extract::<(Bound<PyAny>, bool, bool)>()?;
+
+            let ic_kwargs = PyDict::new(py);
+            ic_kwargs.set_item("build", true)?;
+            ic_kwargs.set_item("connect", connect)?;
+            ic_kwargs.set_item("addcart", addcart)?;
+            ic_kwargs.set_item("conmethod", opt_params.getattr("conmethod")?)?;
+            if let Some(constraints) = &constraints {
+                let parse_constraints =
+                    py.import("geometric.prepare")?.getattr("parse_constraints")?;
+                let (cons, cvals) =
+                    parse_constraints
+                        .call1((&molecule, constraints))?
+                        .extract::<(Bound<PyAny>, Bound<PyAny>)>()?;
+                ic_kwargs.set_item("constraints", cons)?;
+                if !cvals.is_none() {
+                    ic_kwargs.set_item("cvals", cvals.get_item(0)?)?;
+                }
+            }
+            let ic = coord_class.call((&molecule,), Some(&ic_kwargs))?;
+
+            let coords = molecule
+                .getattr("xyzs")?
+                .get_item(0)?
+                .call_method0("flatten")?
+                .call_method1("__truediv__", (BOHR2ANG,))?;
+            let scratch = TempDir::new()?;
+            let dirname = scratch.path().to_str().unwrap();
+            let optimizer = geometric_optimize
+                .getattr("Optimizer")?
+                .call1((coords, &molecule, ic, engine, dirname, opt_params))?;
+            if let Ok(engine) = engine.downcast::<EngineMixin>() {
+                engine.borrow_mut().set_optimizer(&optimizer)?;
+            }
+            Ok(OptimizerSession {
+                optimizer: optimizer.unbind(),
+                engine: custom_engine,
+                started: false,
+                _scratch: scratch,
+                params: params.clone_ref(py),
+                constraints,
+                initial_hessian: None,
+                checkpoint: None,
+                step_offset: 0,
+                steps: 0,
+            })
+        })
+        .map_err(GeometricError::from)
+    }
+
+    /// Perform one iteration; returns whether the optimization has converged.
+    ///
+    /// The first call evaluates the starting geometry. Each following call
+    /// takes a step and evaluates the new geometry (one driver evaluation per
+    /// call). Returns [`GeometricError::NotConverged`] if the optimizer fails
+    /// (e.g. `maxiter` reached); calling after convergence does nothing.
+    pub fn step(&mut self) -> Result<bool, GeometricError> {
+        Python::with_gil(|py| {
+            let optimizer = self.optimizer.bind(py);
+            let opt_state = py.import("geometric.optimize")?.getattr("OPT_STATE")?;
+            if !self.started {
+                optimizer.call_method0("calcEnergyForce")?;
+                optimizer.call_method0("prepareFirstStep")?;
+                if let Some(hessian) = self.initial_hessian.take() {
+                    let ncoord = optimizer.getattr("X")?.len()?;
+                    let hessian = PyArray1::from_vec(py, hessian).reshape([ncoord, ncoord])?;
+                    let (coords, gradient) = (optimizer.getattr("X")?, optimizer.getattr("G")?);
+                    let hessian = optimizer
+                        .getattr("IC")?
+                        .call_method1("calcHess", (coords, gradient, hessian))?;
+                    optimizer.setattr("H", hessian)?;
+                }
+                self.started = true;
+            } else if !self.converged()? {
+                optimizer.call_method0("step")?;
+                if optimizer.getattr("state")?.eq(opt_state.getattr("NEEDS_EVALUATION")?)? {
+                    optimizer.call_method0("calcEnergyForce")?;
+                    optimizer.call_method0("evaluateStep")?;
+                }
+                self.steps += 1;
+                if let Some((path, every)) = &self.checkpoint {
+                    if self.steps.is_multiple_of(*every) {
+                        self.checkpoint()?.save(path)?;
+                    }
+                }
+            }
+            if optimizer.getattr("state")?.eq(opt_state.getattr("FAILED")?)? {
+                let error = py.import("geometric.errors")?.getattr("GeomOptNotConvergedError")?;
+                return Err(PyErr::from_value(error.call1(("Optimization failed to converge.",))?));
+            }
+            self.converged()
+        })
+        .map_err(GeometricError::from)
+    }
+
+    /// Whether the optimization has converged.
+    pub fn converged(&self) -> PyResult<bool> {
+        Python::with_gil(|py| {
+            let converged =
+                py.import("geometric.optimize")?.getattr("OPT_STATE")?.getattr("CONVERGED")?;
+            self.optimizer.bind(py).getattr("state")?.eq(converged)
+        })
+    }
+
+    /// Current geometry of the optimizer (Angstrom, flattened natom * 3).
+    pub fn current_geometry(&self) -> PyResult<Vec<f64>> {
+        Python::with_gil(|py| {
+            let coords = self.optimizer.bind(py).getattr("X")?;
+            let coords =
+                coords.call_method0("flatten")?.call_method0("tolist")?.extract::<Vec<f64>>()?;
+            Ok(coords.iter().map(|x| x * BOHR2ANG).collect())
+        })
+    }
+
+    /// Energy of the current geometry (Hartree), or `None` before the first
+    /// [`step`](Self::step).
+    pub fn current_energy(&self) -> PyResult<Option<f64>> {
+        if !self.started {
+            return Ok(None);
+        }
+        Python::with_gil(|py| self.optimizer.bind(py).getattr("E")?.extract().map(Some))
+    }
+
+    /// Replace the guess Hessian of the first step by a Cartesian Hessian
+    /// (Hartree/Bohr², flattened natom * 3 by natom * 3).
+    pub fn initial_hessian(mut self, hessian: Vec<f64>) -> Self {
+        self.initial_hessian = Some(hessian);
+        self
+    }
+
+    /// Save a checkpoint to `path` after every `every` steps (at least 1).
+    ///
+    /// The file is replaced atomically, so an interrupted write never leaves a
+    /// corrupt checkpoint.
+    pub fn checkpoint_every(mut self, path: impl Into<PathBuf>, every: usize) -> Self {
+        self.checkpoint = Some((path.into(), every.max(1)));
+        self
+    }
+
+    /// Number of optimization steps taken, including those before a resumed
+    /// checkpoint.
+    pub fn step_count(&self) -> usize {
+        self.step_offset + self.steps
+    }
+
+    /// Mark the session as continuing from a checkpoint, after `step_offset`
+    /// steps with the original `params`.
+    pub(crate) fn set_resumed(&mut self, step_offset: usize, params: Py<PyDict>) {
+        self.step_offset = step_offset;
+        self.params = params;
+    }
+
+    /// State of the optimization, see [`Checkpoint`].
+    ///
+    /// The Hessian is only available after the first [`step`](Self::step).
+    pub fn checkpoint(&self) -> PyResult<Checkpoint> {
+        Python::with_gil(|py| {
+            let optimizer = self.optimizer.bind(py);
+            let molecule = Molecule::from(self.engine.getattr(py, "M")?);
+            let ic = optimizer.getattr("IC")?;
+            let hessian = match self.started {
+                true => {
+                    let (coords, gradient) = (optimizer.getattr("X")?, optimizer.getattr("G")?);
+                    let gradient_ic = ic.call_method1("calcGrad", (&coords, gradient))?;
+                    let hessian = ic.call_method1(
+                        "calcHessCart",
+                        (coords, gradient_ic, optimizer.getattr("H")?),
+                    )?;
+                    Some(hessian.call_method0("flatten")?.call_method0("tolist")?.extract()?)
+                },
+                false => None,
+            };
+            // Delocalized internal coordinates are built from primitives
+            let prims = match ic.hasattr("Prims")? {
+                true => ic.getattr("Prims")?,
+                false => ic,
+            };
+            let internal_coordinates = prims
+                .getattr("Internals")?
+                .try_iter()?
+                .map(|coord| Ok(coord?.str()?.to_string()))
+                .collect::<PyResult<Vec<_>>>()?;
+            let json = py.import("json")?;
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("default", py.get_type::<PyString>())?;
+            let params = json.call_method("dumps", (self.params.bind(py),), Some(&kwargs))?;
+            Ok(Checkpoint {
+                elem: molecule.elem()?,
+                charge: molecule.charge()?,
+                mult: molecule.mult()?,
+                coords: self.current_geometry()?,
+                energy: self.current_energy()?,
+                hessian,
+                internal_coordinates,
+                step: self.step_count(),
+                params: params.extract()?,
+                constraints: self.constraints.clone(),
+                checkpoint_every: self.checkpoint.as_ref().map(|(_, every)| *every),
+            })
+        })
+    }
+
+    /// geomeTRIC's `Progress` object of the optimization so far.
+    pub fn progress(&self) -> PyResult<Progress> {
+        Python::with_gil(|py| Ok(Progress::from(self.optimizer.getattr(py, "progress")?)))
+    }
+
+    /// Trajectory of the optimization so far.
+    pub fn result(&self) -> PyResult<OptResult> {
+        Python::with_gil(|py| {
+            let mut result = self.progress()?.into_result()?;
+            self.engine.bind(py).downcast::<EngineMixin>()?.borrow().fill_result(&mut result);
+            result.converged = self.converged()?;
+            Ok(result)
+        })
+    }
+}
+
+/// High-level optimization of a molecule with a driver, hiding the python
+/// plumbing (interpreter, molecule, engine and driver wrapping):
+///
+/// ```ignore
+/// let outcome = Optimizer::new(&["O", "H", "H"], &coords, ModelDriver { model: &mut model })
+///     .params(OptimizerParams::new().convergence(ConvergencePreset::GauTight))
+///     .run()?;
+/// let energy = outcome.result().final_energy();
+/// ```
+///
+/// The driver may borrow local data; it is only lent to python during
+/// [`run`](Self::run) (see [`with_driver`]).
+pub struct Optimizer<D: GeomDriverAPI> {
+    molecule: MoleculeSpec,
+    driver: D,
+    params: OptimizerParams,
+    options: RunOptions,
+}
+
+impl<D: GeomDriverAPI> Optimizer<D> {
+    /// Optimizer of the molecule with element symbols and coordinates
+    /// (Angstrom, flattened natom * 3).
+    pub fn new(elem: &[&str], coords: &[f64], driver: D) -> Self {
+        let molecule = MoleculeSpec {
+            elem: elem.iter().map(|e| e.to_string()).collect(),
+            xyzs: vec![coords.to_vec()],
+            ..Default::default()
+        };
+        Optimizer { molecule, driver, params: OptimizerParams::new(), options: Default::default() }
+    }
+
+    /// Unit of the coordinates given to [`new`](Self::new).
+    pub fn unit(mut self, unit: LengthUnit) -> Self {
+        self.molecule.unit = unit;
+        self
+    }
+
+    /// Total charge and spin multiplicity of the molecule.
+    pub fn charge_mult(mut self, charge: i64, mult: i64) -> Self {
+        self.molecule.charge = Some(charge);
+        self.molecule.mult = Some(mult);
+        self
+    }
+
+    /// Parameters of geomeTRIC.
+    pub fn params(mut self, params: OptimizerParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Options handled by this crate, see [`RunOptions`].
+    pub fn options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Run the optimization, see [`run_optimization_with_options`].
+    pub fn run(mut self) -> Result<OptimizationOutcome, GeometricError> {
+        let natom = self.molecule.elem.len();
+        let ncoord = self.molecule.xyzs[0].len();
+        if natom == 0 || ncoord != natom * 3 {
+            return Err(PyValueError::new_err(format!(
+                "Number of coordinates ({}) does not match number of atoms ({}) times 3",
+                ncoord, natom
+            ))
+            .into());
+        }
+        initialize_python();
+        let molecule = self.molecule.build()?;
+        let params = self.params.to_py()?;
+        let options = &self.options;
+        with_driver(&mut self.driver, |driver| {
+            let custom_engine = init_pyo3_engine(&molecule.pyobj, driver)?;
+            run_optimization_with_options(custom_engine, &params, None, options)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::ConstraintCoord;
+
+    #[test]
+    fn test_freeze_atoms() {
+        let options = RunOptions::default().freeze_atoms(&[0, 5, 6]);
+        let string = options.constraints.unwrap().to_geometric_string(7).unwrap();
+        assert_eq!(string, "$freeze\nxyz 1,6-7\n");
+
+        let options = RunOptions {
+            constraints: Some(Constraints::new().set(ConstraintCoord::Distance(1, 2), 1.0)),
+            ..Default::default()
+        };
+        let string =
+            options.freeze_atoms(&[0]).constraints.unwrap().to_geometric_string(3).unwrap();
+        assert_eq!(string, "$freeze\nxyz 1\n$set\ndistance 2 3 1\n");
+    }
+
+    #[test]
+    fn test_zero_timeout() {
+        pyo3::prepare_freethreaded_python();
+        let (engine, params) = Python::with_gil(|py| (py.None(), PyDict::new(py).unbind()));
+        let options = RunOptions { timeout: Some(Duration::ZERO), ..Default::default() };
+        let err = run_optimization_with_options(engine, &params, None, &options).unwrap_err();
+        assert!(matches!(err, GeometricError::InvalidParameters(_)));
+    }
 }
@@ -0,0 +1,228 @@
+//! Concurrent optimizations from multiple threads.
+//!
+//! # Concurrency model
+//!
+//! geomeTRIC itself is pure python, so its optimizer steps are serialized by
+//! the GIL. The GIL is released while the driver evaluates the gradient (see
+//! [`EngineMixin::calc_new`](crate::engine::EngineMixin::calc_new)), so
+//! gradient evaluations of concurrent optimizations run in parallel; for
+//! expensive drivers this is where nearly all time is spent.
+//!
+//! Concurrent optimizations are safe under these conditions:
+//!
+//! - Each optimization has its own engine instance, created by
+//!   [`init_pyo3_engine`] (engines record the optimization history).
+//! - Each optimization has its own molecule object, or molecules are not
+//!   modified while shared.
+//! - Drivers are either separate instances, handles of a shared
+//!   [`GeomDriverSync`](crate::interface::GeomDriverSync) (see
+//!   [`PyGeomDriver::from_sync`]), or shared knowing that a [`PyGeomDriver`]
+//!   serializes its evaluations by its mutex.
+//!
+//! geomeTRIC's logging configuration is process-wide: each run reconfigures
+//! the python root logger, so log files of concurrent runs are unreliable.
+//! Silence it by [`set_log_level`](crate::logging::set_log_level) with
+//! [`LogLevel::Quiet`](crate::logging::LogLevel), and use the returned
+//! results instead. [`ParallelOptimizer`] handles the rest.
+
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::PyGeomDriver;
+use crate::molecule::Molecule;
+use crate::optimize::{run_optimization_with_options, RunOptions};
+use crate::result::OptimizationOutcome;
+use pyo3::panic::PanicException;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// One optimization of [`ParallelOptimizer::run`].
+pub struct OptimizationJob {
+    pub molecule: Molecule,
+    pub driver: PyGeomDriver,
+}
+
+/// Run many independent optimizations (e.g. conformers) on a pool of threads.
+///
+/// All jobs share parameters and options. Each job gets its own engine, and a
+/// run id suffixed with the job index (`job<i>`, or `<run_id>_<i>` if
+/// `options.run_id` is set), so output files never collide.
+#[derive(Debug)]
+pub struct ParallelOptimizer {
+    params: Py<PyDict>,
+    options: RunOptions,
+    threads: usize,
+}
+
+impl ParallelOptimizer {
+    /// Optimizer with the parameters of geomeTRIC, using as many threads as
+    /// available CPUs.
+    pub fn new(params: Py<PyDict>) -> Self {
+        let threads = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        ParallelOptimizer { params, options: RunOptions::default(), threads }
+    }
+
+    /// Options of every optimization.
+    pub fn options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Number of worker threads (at least 1).
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Run all jobs; results are in the same order as `jobs`.
+    ///
+    /// A failed job does not stop the others. A job that panics (e.g. in a
+    /// driver) is returned as its own [`GeometricError::Python`] error holding
+    /// a [`PanicException`].
+    pub fn run(
+        &self,
+        jobs: Vec<OptimizationJob>,
+    ) -> Vec<Result<OptimizationOutcome, GeometricError>> {
+        self.run_with(jobs, |index, job| self.run_job(index, job))
+    }
+
+    /// Run `run_job` on each job on the pool of threads.
+    fn run_with<J: Send, R: Send>(
+        &self,
+        jobs: Vec<J>,
+        run_job: impl Fn(usize, J) -> Result<R, GeometricError> + Sync,
+    ) -> Vec<Result<R, GeometricError>> {
+        let njob = jobs.len();
+        let jobs = jobs.into_iter().map(Some).map(Mutex::new).collect::<Vec<_>>();
+        let results = (0..njob).map(|_| Mutex::new(None)).collect::<Vec<_>>();
+        let next = AtomicUsize::new(0);
+
+        // The GIL of the calling thread (if held) is released, so that workers
+        // can acquire it.
+        let run_workers = || {
+            thread::scope(|s| {
+                for _ in 0..self.threads.min(njob) {
+                    s.spawn(|| loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= njob {
+                            break;
+                        }
+                        let job = jobs[index].lock().unwrap().take().unwrap();
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| run_job(index, job)))
+                            .unwrap_or_else(|payload| Err(panic_error(payload)));
+                        *results[index].lock().unwrap() = Some(result);
+                    });
+                }
+            })
+        };
+        Python::with_gil(|py| py.allow_threads(run_workers));
+
+        results.into_iter().map(|r| r.into_inner().unwrap().unwrap()).collect()
+    }
+
+    fn run_job(
+        &self,
+        index: usize,
+        job: OptimizationJob,
+    ) -> Result<OptimizationOutcome, GeometricError> {
+        let mut options = self.options.clone();
+        options.run_id = Some(match &options.run_id {
+            Some(run_id) => format!("{}_{}", run_id, index),
+            None => format!("job{}", index),
+        });
+        let custom_engine = init_pyo3_engine(&job.molecule.pyobj, &job.driver)?;
+        run_optimization_with_options(custom_engine, &self.params, None, &options)
+    }
+}
+
+/// Error of a job that panicked with `payload`.
+fn panic_error(payload: Box<dyn Any + Send>) -> GeometricError {
+    let message = match payload.downcast::<String>() {
+        Ok(message) => *message,
+        Err(payload) => match payload.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Optimization job panicked".to_string(),
+        },
+    };
+    GeometricError::Python(PanicException::new_err(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineMixin;
+    use crate::interface::{DriverError, GeomDriverAPI, GradOutput};
+    use std::sync::{Arc, Condvar};
+    use std::time::Duration;
+
+    /// Driver that only succeeds if two evaluations are in flight together.
+    struct Rendezvous(Arc<(Mutex<usize>, Condvar)>);
+
+    impl GeomDriverAPI for Rendezvous {
+        fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+            let (arrived, condvar) = &*self.0;
+            let mut arrived = arrived.lock().unwrap();
+            *arrived += 1;
+            condvar.notify_all();
+            let timeout = Duration::from_secs(5);
+            let (arrived, _) = condvar.wait_timeout_while(arrived, timeout, |n| *n < 2).unwrap();
+            match *arrived >= 2 {
+                true => Ok(GradOutput::new(0.0, coords.to_vec())),
+                false => Err(DriverError::CalculationFailed("Evaluations serialized".into())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_driver_calls_overlap() {
+        pyo3::prepare_freethreaded_python();
+        let params = Python::with_gil(|py| PyDict::new(py).unbind());
+        let rendezvous = Arc::new((Mutex::new(0), Condvar::new()));
+        let drivers = (0..2).map(|_| PyGeomDriver::from(Rendezvous(rendezvous.clone()))).collect();
+        // Each job evaluates its driver through an engine, as geomeTRIC would
+        let results = ParallelOptimizer::new(params).threads(2).run_with(drivers, |_, driver| {
+            Python::with_gil(|py| {
+                let types = py.import("types")?;
+                let molecule = types.getattr("SimpleNamespace")?.call0()?;
+                molecule.setattr("elem", vec!["H"])?;
+                let mut engine = EngineMixin::new(molecule)?;
+                engine.set_driver(&driver);
+                engine.evaluate(py, &[0.0; 3], "")
+            })
+            .map_err(GeometricError::Engine)
+        });
+        assert!(results.iter().all(Result::is_ok));
+    }
+
+    #[test]
+    fn test_parallel_job_panic() {
+        pyo3::prepare_freethreaded_python();
+        let params = Python::with_gil(|py| PyDict::new(py).unbind());
+        let results =
+            ParallelOptimizer::new(params).threads(2).run_with(vec![0, 1, 2], |_, job| match job {
+                1 => panic!("driver exploded"),
+                _ => Ok(job),
+            });
+        assert_eq!(results[0].as_ref().ok(), Some(&0));
+        assert_eq!(results[2].as_ref().ok(), Some(&2));
+        let err = results[1].as_ref().unwrap_err();
+        assert!(matches!(err, GeometricError::Python(_)));
+        assert!(err.to_string().contains("driver exploded"));
+    }
+
+    #[test]
+    fn test_parallel_optimizer_is_shareable() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ParallelOptimizer>();
+        assert_send_sync::<OptimizationJob>();
+
+        pyo3::prepare_freethreaded_python();
+        let params = Python::with_gil(|py| PyDict::new(py).unbind());
+        assert!(ParallelOptimizer::new(params).threads(4).run(vec![]).is_empty());
+    }
+}
@@ -0,0 +1,625 @@
+//! Typed parameters for geomeTRIC optimizer.
+//!
+//! Parameters are finally converted to the kwargs dictionary of
+//! `geometric.optimize.run_optimizer`, so this is a type-checked alternative to
+//! writing raw TOML strings for [`tomlstr2py`](crate::util::tomlstr2py).
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::convergence::ConvergenceCriteria;
+use crate::hessian::HessianMode;
+use crate::util::toml2py;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Coordinate system of geomeTRIC (`coordsys` keyword).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoordSys {
+    /// Translation-rotation internal coordinates (geomeTRIC default).
+    Tric,
+    /// TRIC without delocalization (primitive coordinates).
+    TricP,
+    /// Delocalized internal coordinates.
+    Dlc,
+    /// Hybrid delocalized internal coordinates.
+    Hdlc,
+    /// Cartesian coordinates.
+    Cart,
+    /// Primitive (redundant) internal coordinates.
+    Prim,
+}
+
+impl CoordSys {
+    /// All coordinate systems supported by geomeTRIC.
+    pub const ALL: [CoordSys; 6] = [
+        CoordSys::Tric,
+        CoordSys::TricP,
+        CoordSys::Dlc,
+        CoordSys::Hdlc,
+        CoordSys::Cart,
+        CoordSys::Prim,
+    ];
+
+    /// String recognized by geomeTRIC.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CoordSys::Tric => "tric",
+            CoordSys::TricP => "tric-p",
+            CoordSys::Dlc => "dlc",
+            CoordSys::Hdlc => "hdlc",
+            CoordSys::Cart => "cart",
+            CoordSys::Prim => "prim",
+        }
+    }
+}
+
+impl fmt::Display for CoordSys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parse the `coordsys` string of geomeTRIC (case-insensitive).
+impl FromStr for CoordSys {
+    type Err = PyErr;
+
+    fn from_str(s: &str) -> PyResult<Self> {
+        CoordSys::ALL.into_iter().find(|c| c.as_str().eq_ignore_ascii_case(s)).ok_or_else(|| {
+            let valid = CoordSys::ALL.map(|c| c.as_str()).join(", ");
+            PyValueError::new_err(format!("Unknown coordsys `{}`, expected one of: {}", s, valid))
+        })
+    }
+}
+
+/// Convergence criteria of geomeTRIC: a predefined set (`convergence_set`
+/// keyword), or custom thresholds.
+///
+/// `Custom` gives the `convergence_energy`, `convergence_grms`,
+/// `convergence_gmax`, `convergence_drms` and `convergence_dmax` keywords.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConvergencePreset {
+    /// Gaussian default (geomeTRIC default).
+    Gau,
+    GauLoose,
+    GauTight,
+    GauVeryTight,
+    NwchemLoose,
+    Turbomole,
+    InterfragTight,
+    Custom(ConvergenceCriteria),
+}
+
+impl ConvergencePreset {
+    /// String of `convergence_set` recognized by geomeTRIC; `None` for
+    /// `Custom`.
+    pub fn as_str(&self) -> Option<&'static str> {
+        match self {
+            ConvergencePreset::Gau => Some("GAU"),
+            ConvergencePreset::GauLoose => Some("GAU_LOOSE"),
+            ConvergencePreset::GauTight => Some("GAU_TIGHT"),
+            ConvergencePreset::GauVeryTight => Some("GAU_VERYTIGHT"),
+            ConvergencePreset::NwchemLoose => Some("NWCHEM_LOOSE"),
+            ConvergencePreset::Turbomole => Some("TURBOMOLE"),
+            ConvergencePreset::InterfragTight => Some("INTERFRAG_TIGHT"),
+            ConvergencePreset::Custom(_) => None,
+        }
+    }
+
+    /// Check custom thresholds for invalid values.
+    pub fn validate(&self) -> PyResult<()> {
+        if let ConvergencePreset::Custom(c) = self {
+            let values = [c.energy, c.grms, c.gmax, c.drms, c.dmax];
+            if !values.iter().all(|v| v.is_finite() && *v > 0.0) {
+                return Err(PyValueError::new_err("Convergence thresholds must be positive"));
+            }
+        }
+        Ok(())
+    }
+
+    /// geomeTRIC keywords of the criteria.
+    pub fn to_toml(&self) -> toml::Table {
+        let mut table = toml::Table::new();
+        match self {
+            ConvergencePreset::Custom(c) => {
+                table.insert("convergence_energy".into(), c.energy.into());
+                table.insert("convergence_grms".into(), c.grms.into());
+                table.insert("convergence_gmax".into(), c.gmax.into());
+                table.insert("convergence_drms".into(), c.drms.into());
+                table.insert("convergence_dmax".into(), c.dmax.into());
+            },
+            preset => {
+                table.insert("convergence_set".into(), preset.as_str().unwrap().into());
+            },
+        }
+        table
+    }
+}
+
+/// Options of transition state optimization, see
+/// [`OptimizerParams::transition_state`].
+///
+/// geomeTRIC optimizes towards a transition state by following the lowest
+/// eigenvector of the Hessian uphill (partitioned RFO), so the initial Hessian
+/// must already have the right negative curvature mode.
+///
+/// - `hessian`: Source of the initial Hessian (`hessian` keyword);
+///   [`HessianMode::First`] by default. Use [`HessianMode::Each`] to track the
+///   mode with an exact Hessian at every step (expensive), or
+///   [`HessianMode::File`] to start from a precomputed Hessian.
+/// - `final_hessian`: Also compute the Hessian at the final geometry, whose
+///   frequencies should show exactly one imaginary mode. Changes `First` to
+///   `FirstLast`.
+/// - `reset`: Reset the Hessian to the guess when its eigenvalues become
+///   inconsistent (`reset` keyword). geomeTRIC disables this for transition
+///   states; enabling it discards the followed mode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransitionStateOptions {
+    pub hessian: HessianMode,
+    pub final_hessian: bool,
+    pub reset: Option<bool>,
+}
+
+impl Default for TransitionStateOptions {
+    fn default() -> Self {
+        TransitionStateOptions { hessian: HessianMode::First, final_hessian: false, reset: None }
+    }
+}
+
+impl TransitionStateOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Source of the initial Hessian.
+    pub fn hessian(mut self, hessian: HessianMode) -> Self {
+        self.hessian = hessian;
+        self
+    }
+
+    /// Compute the Hessian at the final geometry.
+    pub fn final_hessian(mut self, final_hessian: bool) -> Self {
+        self.final_hessian = final_hessian;
+        self
+    }
+
+    /// Reset the Hessian when its eigenvalues become inconsistent.
+    pub fn reset(mut self, reset: bool) -> Self {
+        self.reset = Some(reset);
+        self
+    }
+
+    /// Check for settings contradicting a transition state search.
+    pub fn validate(&self) -> PyResult<()> {
+        match self.hessian {
+            HessianMode::Never | HessianMode::Last => {
+                return Err(PyValueError::new_err(format!(
+                    "Transition state optimization requires an initial Hessian, got `hessian = \"{}\"`",
+                    self.hessian.to_param()
+                )));
+            },
+            HessianMode::Stop => {
+                return Err(PyValueError::new_err(
+                    "`hessian = \"stop\"` stops before the transition state optimization",
+                ));
+            },
+            HessianMode::File(_) if self.final_hessian => {
+                return Err(PyValueError::new_err(
+                    "geomeTRIC cannot read the initial Hessian from a file and compute the final Hessian",
+                ));
+            },
+            _ => (),
+        }
+        if self.reset == Some(true) {
+            return Err(PyValueError::new_err(
+                "Resetting the Hessian discards the mode followed by transition state optimization",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Value of the `hessian` keyword.
+    pub fn hessian_param(&self) -> String {
+        match (&self.hessian, self.final_hessian) {
+            (HessianMode::First, true) => HessianMode::FirstLast.to_param(),
+            (hessian, _) => hessian.to_param(),
+        }
+    }
+}
+
+/// Typed geomeTRIC optimizer parameters (builder).
+///
+/// Options left as `None` are not passed to geomeTRIC, so geomeTRIC's own
+/// defaults apply.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OptimizerParams {
+    prefix: Option<String>,
+    subfrctor: Option<i64>,
+    qccnv: Option<bool>,
+    molcnv: Option<bool>,
+    convergence: Option<ConvergencePreset>,
+    maxiter: Option<i64>,
+    coordsys: Option<CoordSys>,
+    transition: Option<bool>,
+    trust: Option<f64>,
+    tmax: Option<f64>,
+    tmin: Option<f64>,
+    qdata: Option<bool>,
+    verbose: Option<i64>,
+    hessian: Option<HessianMode>,
+    transition_state: Option<TransitionStateOptions>,
+    port: Option<u16>,
+}
+
+impl OptimizerParams {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix of geomeTRIC output files (`prefix` keyword).
+    ///
+    /// geomeTRIC writes `<prefix>.log`, `<prefix>_optim.xyz` and the scratch
+    /// directory `<prefix>.tmp`. If not given, geomeTRIC derives the prefix
+    /// from the `input` path, which is a random temporary file name when
+    /// `input` of [`run_optimization`](crate::optimize::run_optimization) is
+    /// `None`.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Projection of net force and torque out of the gradient (`subfrctor`
+    /// keyword; 0, 1 or 2).
+    ///
+    /// `0` keeps the gradient as given by the driver; `1` (geomeTRIC default)
+    /// projects out the net force and torque; `2` is geomeTRIC's
+    /// automatic mode. Near convergence, small residual net forces (e.g. from
+    /// numerical noise of the driver) can otherwise keep the gradient
+    /// criteria from being met and cause extra steps.
+    pub fn subfrctor(mut self, subfrctor: i64) -> Self {
+        self.subfrctor = Some(subfrctor);
+        self
+    }
+
+    /// Q-Chem style convergence (`qccnv` keyword).
+    ///
+    /// Converged when the gradient criteria are met together with either the
+    /// energy or the displacement criteria. This avoids extra steps on
+    /// nearly-converged structures whose tiny residual forces give
+    /// displacements just above threshold.
+    pub fn qccnv(mut self, qccnv: bool) -> Self {
+        self.qccnv = Some(qccnv);
+        self
+    }
+
+    /// Molpro style convergence (`molcnv` keyword).
+    ///
+    /// Converged when the maximum gradient criterion is met together with
+    /// either the energy or the maximum displacement criteria.
+    pub fn molcnv(mut self, molcnv: bool) -> Self {
+        self.molcnv = Some(molcnv);
+        self
+    }
+
+    /// Convergence criteria, see [`ConvergencePreset`].
+    pub fn convergence(mut self, convergence: ConvergencePreset) -> Self {
+        self.convergence = Some(convergence);
+        self
+    }
+
+    /// Maximum number of optimization steps (`maxiter` keyword).
+    pub fn maxiter(mut self, maxiter: i64) -> Self {
+        self.maxiter = Some(maxiter);
+        self
+    }
+
+    /// Coordinate system (`coordsys` keyword).
+    pub fn coordsys(mut self, coordsys: CoordSys) -> Self {
+        self.coordsys = Some(coordsys);
+        self
+    }
+
+    /// Transition state optimization (`transition` keyword).
+    pub fn transition(mut self, transition: bool) -> Self {
+        self.transition = Some(transition);
+        self
+    }
+
+    /// Initial trust radius in Angstrom (`trust` keyword).
+    pub fn trust(mut self, trust: f64) -> Self {
+        self.trust = Some(trust);
+        self
+    }
+
+    /// Maximum trust radius in Angstrom (`tmax` keyword).
+    pub fn tmax(mut self, tmax: f64) -> Self {
+        self.tmax = Some(tmax);
+        self
+    }
+
+    /// Minimum trust radius in Angstrom (`tmin` keyword).
+    ///
+    /// Steps are no longer rejected once the trust radius has shrunk to this
+    /// value, which keeps optimizations on noisy energy surfaces moving.
+    pub fn tmin(mut self, tmin: f64) -> Self {
+        self.tmin = Some(tmin);
+        self
+    }
+
+    /// Write the coordinates, energy and gradient of every evaluation to
+    /// `qdata.txt` (`qdata` keyword), readable by
+    /// [`read_qdata`](crate::qdata::read_qdata).
+    pub fn qdata(mut self, qdata: bool) -> Self {
+        self.qdata = Some(qdata);
+        self
+    }
+
+    /// Detail of geomeTRIC's printout (`verbose` keyword; 0 is geomeTRIC
+    /// default).
+    ///
+    /// Whether the printout is shown at all is controlled by
+    /// [`set_log_level`](crate::logging::set_log_level).
+    pub fn verbose(mut self, verbose: i64) -> Self {
+        self.verbose = Some(verbose);
+        self
+    }
+
+    /// When the Cartesian Hessian is computed or read (`hessian` keyword), see
+    /// [`HessianMode`].
+    ///
+    /// A Hessian computed at the final geometry is returned in
+    /// [`OptResult::hessian`](crate::result::OptResult).
+    pub fn hessian(mut self, hessian: HessianMode) -> Self {
+        self.hessian = Some(hessian);
+        self
+    }
+
+    /// Transition state optimization with its options, see
+    /// [`TransitionStateOptions`].
+    ///
+    /// This sets the `transition`, `hessian` and `reset` keywords, so it can
+    /// not be combined with [`transition(false)`](Self::transition) or
+    /// [`hessian`](Self::hessian).
+    pub fn transition_state(mut self, options: TransitionStateOptions) -> Self {
+        self.transition_state = Some(options);
+        self
+    }
+
+    /// Port of the Work Queue master (`port` keyword).
+    ///
+    /// geomeTRIC then distributes independent gradient calculations
+    /// (finite-difference Hessians and NEB images) to Work Queue workers
+    /// connecting to this port. The driver must support remote jobs, see
+    /// [`GeomDriverAPI::prepare_wq_job`](crate::interface::GeomDriverAPI::prepare_wq_job);
+    /// the python package `work_queue` must be installed.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    /// Check the parameters for invalid values.
+    pub fn validate(&self) -> PyResult<()> {
+        if let Some(prefix) = &self.prefix {
+            if prefix.trim().is_empty() {
+                return Err(PyValueError::new_err("Parameter `prefix` must not be empty"));
+            }
+        }
+        if let Some(subfrctor) = self.subfrctor {
+            if !(0..=2).contains(&subfrctor) {
+                return Err(PyValueError::new_err(format!(
+                    "Parameter `subfrctor` must be 0, 1 or 2, got {}",
+                    subfrctor
+                )));
+            }
+        }
+        if self.qccnv == Some(true) && self.molcnv == Some(true) {
+            return Err(PyValueError::new_err(
+                "Parameters `qccnv` and `molcnv` cannot be enabled together",
+            ));
+        }
+        if let Some(maxiter) = self.maxiter {
+            if maxiter <= 0 {
+                return Err(PyValueError::new_err(format!(
+                    "Parameter `maxiter` must be positive, got {}",
+                    maxiter
+                )));
+            }
+        }
+        for (key, value) in [("trust", self.trust), ("tmax", self.tmax), ("tmin", self.tmin)] {
+            if let Some(value) = value {
+                if !(value.is_finite() && value > 0.0) {
+                    return Err(PyValueError::new_err(format!(
+                        "Parameter `{}` must be positive, got {}",
+                        key, value
+                    )));
+                }
+            }
+        }
+        if let Some(verbose) = self.verbose {
+            if verbose < 0 {
+                return Err(PyValueError::new_err(format!(
+                    "Parameter `verbose` must not be negative, got {}",
+                    verbose
+                )));
+            }
+        }
+        // Trust radii must be ordered as tmin <= trust <= tmax
+        let radii = [("tmin", self.tmin), ("trust", self.trust), ("tmax", self.tmax)];
+        for (i, (lower, lower_value)) in radii.iter().enumerate() {
+            for (upper, upper_value) in &radii[i + 1..] {
+                if let (Some(lower_value), Some(upper_value)) = (lower_value, upper_value) {
+                    if lower_value > upper_value {
+                        return Err(PyValueError::new_err(format!(
+                            "Parameter `{}` ({}) must not exceed `{}` ({})",
+                            lower, lower_value, upper, upper_value
+                        )));
+                    }
+                }
+            }
+        }
+        if let Some(convergence) = &self.convergence {
+            convergence.validate()?;
+        }
+        if self.port == Some(0) {
+            return Err(PyValueError::new_err("Parameter `port` must not be 0"));
+        }
+        if let Some(transition_state) = &self.transition_state {
+            if self.transition == Some(false) {
+                return Err(PyValueError::new_err(
+                    "Transition state options given with `transition = false`",
+                ));
+            }
+            if self.hessian.is_some() {
+                return Err(PyValueError::new_err(
+                    "Parameter `hessian` must be given in transition state options",
+                ));
+            }
+            transition_state.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Convert to TOML table, with geomeTRIC keywords as keys.
+    pub fn to_toml(&self) -> toml::Value {
+        let mut table = toml::Table::new();
+        insert_some(&mut table, "prefix", &self.prefix);
+        insert_some(&mut table, "subfrctor", &self.subfrctor);
+        insert_some(&mut table, "qccnv", &self.qccnv);
+        insert_some(&mut table, "molcnv", &self.molcnv);
+        if let Some(convergence) = &self.convergence {
+            table.extend(convergence.to_toml());
+        }
+        insert_some(&mut table, "maxiter", &self.maxiter);
+        insert_some(&mut table, "coordsys", &self.coordsys.map(|c| c.as_str()));
+        insert_some(&mut table, "transition", &self.transition);
+        insert_some(&mut table, "trust", &self.trust);
+        insert_some(&mut table, "tmax", &self.tmax);
+        insert_some(&mut table, "tmin", &self.tmin);
+        insert_some(&mut table, "qdata", &self.qdata);
+        insert_some(&mut table, "verbose", &self.verbose);
+        insert_some(&mut table, "hessian", &self.hessian.as_ref().map(|h| h.to_param()));
+        if let Some(transition_state) = &self.transition_state {
+            table.insert("transition".into(), true.into());
+            table.insert("hessian".into(), transition_state.hessian_param().into());
+            insert_some(&mut table, "reset", &transition_state.reset);
+        }
+        insert_some(&mut table, "port", &self.port.map(i64::from));
+        toml::Value::Table(table)
+    }
+
+    /// Validate and convert to `Py<PyDict>`, usable as `params` of
+    /// [`run_optimization`](crate::optimize::run_optimization).
+    pub fn to_py(&self) -> PyResult<Py<PyDict>> {
+        self.validate()?;
+        toml2py(&self.to_toml())
+    }
+}
+
+/// Insert `value` to `table` only if it is `Some`.
+fn insert_some<T>(table: &mut toml::Table, key: &str, value: &Option<T>)
+where
+    T: Clone + Into<toml::Value>,
+{
+    if let Some(value) = value {
+        table.insert(key.into(), value.clone().into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefix() {
+        let params = OptimizerParams::new().prefix("water");
+        assert!(params.validate().is_ok());
+        assert_eq!(params.to_toml()["prefix"].as_str(), Some("water"));
+
+        assert!(OptimizerParams::new().prefix(" ").validate().is_err());
+        assert!(OptimizerParams::new().to_toml().as_table().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_convergence_handling() {
+        let params = OptimizerParams::new().subfrctor(0).qccnv(true);
+        assert!(params.validate().is_ok());
+        assert_eq!(params.to_toml()["subfrctor"].as_integer(), Some(0));
+        assert_eq!(params.to_toml()["qccnv"].as_bool(), Some(true));
+
+        assert!(OptimizerParams::new().subfrctor(3).validate().is_err());
+        assert!(OptimizerParams::new().qccnv(true).molcnv(true).validate().is_err());
+    }
+
+    #[test]
+    fn test_coordsys() {
+        for coordsys in CoordSys::ALL {
+            assert_eq!(coordsys.to_string().parse::<CoordSys>().unwrap(), coordsys);
+        }
+        assert_eq!("TRIC-P".parse::<CoordSys>().unwrap(), CoordSys::TricP);
+        assert!("tric_p".parse::<CoordSys>().is_err());
+    }
+
+    #[test]
+    fn test_optimizer_options() {
+        let params = OptimizerParams::new()
+            .convergence(ConvergencePreset::GauTight)
+            .maxiter(100)
+            .coordsys(CoordSys::Dlc)
+            .transition(true)
+            .trust(0.05)
+            .tmax(0.2);
+        assert!(params.validate().is_ok());
+        let table = params.to_toml();
+        assert_eq!(table["convergence_set"].as_str(), Some("GAU_TIGHT"));
+        assert_eq!(table["maxiter"].as_integer(), Some(100));
+        assert_eq!(table["coordsys"].as_str(), Some("dlc"));
+        assert_eq!(table["transition"].as_bool(), Some(true));
+        assert_eq!(table["trust"].as_float(), Some(0.05));
+
+        let custom = ConvergencePreset::Custom(ConvergenceCriteria::default());
+        let params = OptimizerParams::new().convergence(custom);
+        assert_eq!(params.to_toml()["convergence_grms"].as_float(), Some(3.0e-4));
+        assert!(params.to_toml().get("convergence_set").is_none());
+        let invalid = ConvergenceCriteria { grms: 0.0, ..Default::default() };
+        assert!(OptimizerParams::new()
+            .convergence(ConvergencePreset::Custom(invalid))
+            .validate()
+            .is_err());
+
+        assert!(OptimizerParams::new().maxiter(0).validate().is_err());
+        assert_eq!(OptimizerParams::new().port(9123).to_toml()["port"].as_integer(), Some(9123));
+        assert!(OptimizerParams::new().port(0).validate().is_err());
+        assert!(OptimizerParams::new().trust(0.5).tmax(0.3).validate().is_err());
+    }
+
+    #[test]
+    fn test_trust_radius() {
+        let params = OptimizerParams::new().tmin(1.0e-3).trust(0.1).tmax(0.3);
+        assert!(params.validate().is_ok());
+        assert_eq!(params.to_toml()["tmin"].as_float(), Some(1.0e-3));
+
+        assert!(OptimizerParams::new().tmin(0.2).trust(0.1).validate().is_err());
+        assert!(OptimizerParams::new().tmin(0.5).tmax(0.3).validate().is_err());
+        assert!(OptimizerParams::new().tmin(-1.0).validate().is_err());
+    }
+
+    #[test]
+    fn test_transition_state() {
+        let ts = TransitionStateOptions::new().final_hessian(true).reset(false);
+        let params = OptimizerParams::new().transition_state(ts.clone());
+        assert!(params.validate().is_ok());
+        let table = params.to_toml();
+        assert_eq!(table["transition"].as_bool(), Some(true));
+        assert_eq!(table["hessian"].as_str(), Some("first+last"));
+        assert_eq!(table["reset"].as_bool(), Some(false));
+
+        let params = OptimizerParams::new().transition_state(ts.clone()).transition(false);
+        assert!(params.validate().is_err());
+        let params = OptimizerParams::new().transition_state(ts.clone()).hessian(HessianMode::Each);
+        assert!(params.validate().is_err());
+        assert!(ts.clone().reset(true).validate().is_err());
+        assert!(ts.clone().hessian(HessianMode::Never).validate().is_err());
+        assert!(ts.hessian(HessianMode::File("h.txt".into())).validate().is_err());
+    }
+}
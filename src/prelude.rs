@@ -1,4 +1,69 @@
-pub use crate::engine::{get_pyo3_engine_cls, init_pyo3_molecule};
-pub use crate::interface::{GeomDriverAPI, GradOutput, PyGeomDriver};
-pub use crate::optimize::run_optimization;
-pub use crate::util::{toml2py, tomlstr2py};
+#[cfg(feature = "ndarray")]
+pub use crate::array::{ArrayDriver, ArrayGeomDriverAPI};
+pub use crate::cache::CachedDriver;
+pub use crate::callback::{CallbackHandle, OptCallback, StepInfo};
+pub use crate::checkpoint::{resume_optimization, Checkpoint};
+pub use crate::constraints::{ConstraintCoord, ConstraintReport, Constraints, ScanSpec};
+pub use crate::convergence::{
+    ConvergenceCriteria, ConvergenceReport, ConvergenceStatus, Criterion,
+};
+pub use crate::engine::{
+    get_pyo3_engine_cls, init_molecule_from_path, init_pyo3_engine, init_pyo3_molecule,
+    init_pyo3_molecule_with_comments, CancelToken, CrossingDirection, EnergyTarget, Format,
+    NonFinitePolicy,
+};
+pub use crate::error::GeometricError;
+pub use crate::extension::{create_driver, driver_names, register_driver, register_module};
+pub use crate::frequency::{run_frequencies, Frequencies, WignerOptions, WignerSamples};
+pub use crate::gradcheck::{
+    check_gradient, check_gradient_at, check_gradient_coords, GradCheckReport,
+};
+pub use crate::hessian::{
+    final_hessian_path, numerical_hessian, numerical_hessian_parallel, read_hessian, write_hessian,
+    HessianMode,
+};
+pub use crate::interface::{
+    with_driver, AsyncDriver, AsyncGeomDriverAPI, AtomData, DriverError, GeomDriverAPI,
+    GeomDriverSync, GradFuture, GradOutput, HessOutput, PyGeomDriver, WorkQueueJob,
+};
+pub use crate::internal::{internal_coords, InternalCoord, InternalCoordKind};
+pub use crate::irc::{run_irc, IrcDirection, IrcOptions, IrcResult};
+pub use crate::logging::{install_log_bridge, remove_log_bridge, set_log_level, LogLevel};
+pub use crate::meci::{run_meci, MeciOptions, TwoStateDriverAPI};
+pub use crate::memory::{estimate_memory, MemoryEstimate};
+pub use crate::molecule::{
+    molecule_from_file, molecule_from_xyz_str, parse_xyz, LengthUnit, Molecule, MoleculeSpec,
+    XyzData,
+};
+pub use crate::neb::{run_neb, NebParams, NebResult};
+pub use crate::numgrad::{EnergyDriverAPI, EnergyOnlyDriver};
+pub use crate::optimize::{
+    optimize_and_characterize, run_optimization, run_optimization_builtin,
+    run_optimization_with_options, run_optimization_with_progress, BuiltinEngine,
+    LinearMoleculePolicy, NumpyErrorMode, Optimizer, OptimizerSession, RunHook, RunOptions,
+};
+pub use crate::parallel::{OptimizationJob, ParallelOptimizer};
+pub use crate::params::{ConvergencePreset, CoordSys, OptimizerParams, TransitionStateOptions};
+#[cfg(feature = "qcengine")]
+pub use crate::qcengine::QcEngineDriver;
+pub use crate::qcschema::{
+    constraints_from_json, molecule_from_qcschema, molecule_to_qcschema, run_optimization_qcschema,
+    run_optimization_schema, QcSchemaMolecule, QcSchemaOptimizationInput,
+    QcSchemaOptimizationResult,
+};
+pub use crate::qdata::{parse_qdata, read_qdata, QDataFrame};
+pub use crate::result::{
+    atom_permutation, CallStats, OptResult, OptimizationOutcome, OptimizationResult, Progress,
+};
+pub use crate::runtime::{initialize_python, register_finalizer, shutdown};
+pub use crate::scan::{run_scan, run_scan_spec, ScanPoint, ScanResult};
+pub use crate::scratch::{ScratchManager, ScratchRetention};
+pub use crate::staged::{Stage, StagedOptimization};
+pub use crate::trajectory::read_trajectory_binary;
+pub use crate::util::{diff_params, toml2py, tomlstr2py, ParamDiff};
+pub use crate::version::{geometric_version, Version};
+pub use crate::wilson::{
+    install_native_coords, primitive_values, remove_native_coords, wilson_b, Primitive,
+};
+#[cfg(feature = "xtb")]
+pub use crate::xtb::{XtbDriver, XtbMethod};
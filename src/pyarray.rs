@@ -0,0 +1,30 @@
+//! numpy array types used by this crate.
+//!
+//! Loading the `numpy` crate crashes rustdoc (a broken intra-doc link on
+//! numpy 0.24's `ToPyArray::to_pyarray_bound`, resolved for its impls on
+//! primitive slices). So when documenting, the types are replaced by
+//! placeholders of the same names; function bodies are not type-checked by
+//! rustdoc, so only the names need to resolve.
+
+#[cfg(not(doc))]
+pub use numpy::{PyArray1, PyArrayMethods, PyReadonlyArray1, PyReadonlyArrayDyn};
+
+#[cfg(doc)]
+pub use placeholder::*;
+
+#[cfg(doc)]
+mod placeholder {
+    use std::marker::PhantomData;
+
+    /// `numpy::PyArray1`.
+    pub struct PyArray1<T>(PhantomData<T>);
+
+    /// `numpy::PyReadonlyArray1`.
+    pub struct PyReadonlyArray1<'py, T>(PhantomData<&'py T>);
+
+    /// `numpy::PyReadonlyArrayDyn`.
+    pub struct PyReadonlyArrayDyn<'py, T>(PhantomData<&'py T>);
+
+    /// `numpy::PyArrayMethods`.
+    pub trait PyArrayMethods {}
+}
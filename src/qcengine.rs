@@ -0,0 +1,158 @@
+//! Driver evaluating gradients with QCEngine (feature `qcengine`).
+//!
+//! [`QcEngineDriver`] calls `qcengine.compute` for a program (e.g. `psi4`,
+//! `nwchem`, `xtb`) and model, so programs supported by QCEngine can be used
+//! without writing a driver. The python package `qcengine` and the program
+//! itself must be installed.
+
+use crate::interface::{AtomData, DriverError, GeomDriverAPI, GradOutput};
+use crate::qcschema::QcSchemaModel;
+use crate::util::json2py_val_with_bound;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde_json::{json, Map, Value};
+
+/// Driver computing gradients by `qcengine.compute`.
+///
+/// - `program`: QCEngine program name, e.g. `psi4`.
+/// - `model`: Method and basis of the calculation.
+/// - `keywords`: Program keywords of the `AtomicInput`.
+/// - `task_config`: QCEngine task configuration, e.g. `{"ncores": 4, "memory":
+///   8}`.
+///
+/// Molecules are given to QCEngine with `fix_com` and `fix_orientation`, so
+/// gradients are in geomeTRIC's frame; ghost atoms are passed as not `real`.
+#[derive(Debug, Clone)]
+pub struct QcEngineDriver {
+    pub program: String,
+    pub model: QcSchemaModel,
+    pub keywords: Map<String, Value>,
+    pub task_config: Map<String, Value>,
+    atom_data: AtomData,
+}
+
+impl QcEngineDriver {
+    /// Driver of `program` with `method`, without basis.
+    pub fn new(program: impl Into<String>, method: impl Into<String>) -> Self {
+        QcEngineDriver {
+            program: program.into(),
+            model: QcSchemaModel { method: method.into(), basis: None },
+            keywords: Map::new(),
+            task_config: Map::new(),
+            atom_data: AtomData::default(),
+        }
+    }
+
+    /// Basis set of the model.
+    pub fn basis(mut self, basis: impl Into<String>) -> Self {
+        self.model.basis = Some(basis.into());
+        self
+    }
+
+    /// Program keywords.
+    pub fn keywords(mut self, keywords: Map<String, Value>) -> Self {
+        self.keywords = keywords;
+        self
+    }
+
+    /// QCEngine task configuration.
+    pub fn task_config(mut self, task_config: Map<String, Value>) -> Self {
+        self.task_config = task_config;
+        self
+    }
+
+    /// QCSchema `AtomicInput` of a gradient at `coords` (Bohr).
+    fn atomic_input(&self, coords: &[f64]) -> Value {
+        let mut molecule = json!({
+            "symbols": self.atom_data.elem,
+            "geometry": coords,
+            "real": self.atom_data.ghost.iter().map(|ghost| !ghost).collect::<Vec<_>>(),
+            "fix_com": true,
+            "fix_orientation": true,
+        });
+        if let Some(charge) = self.atom_data.charge {
+            molecule["molecular_charge"] = json!(charge);
+        }
+        if let Some(mult) = self.atom_data.mult {
+            molecule["molecular_multiplicity"] = json!(mult);
+        }
+        json!({
+            "molecule": molecule,
+            "driver": "gradient",
+            "model": self.model,
+            "keywords": self.keywords,
+        })
+    }
+
+    /// Run `qcengine.compute`; the inner error is the message of a failed
+    /// computation.
+    fn compute(&self, py: Python<'_>, coords: &[f64]) -> PyResult<Result<GradOutput, String>> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("raise_error", false)?;
+        kwargs.set_item("return_dict", true)?;
+        kwargs.set_item("task_config", json2py_val_with_bound(py, &json!(self.task_config))?)?;
+        let input = json2py_val_with_bound(py, &self.atomic_input(coords))?;
+        let result =
+            py.import("qcengine")?.call_method("compute", (input, &self.program), Some(&kwargs))?;
+        if !result.get_item("success")?.extract::<bool>()? {
+            let error = result.get_item("error")?;
+            return Ok(Err(match error.is_none() {
+                true => "QCEngine computation failed".into(),
+                false => format!(
+                    "{}: {}",
+                    error.get_item("error_type")?,
+                    error.get_item("error_message")?
+                ),
+            }));
+        }
+        let energy = result.get_item("properties")?.get_item("return_energy")?.extract::<f64>()?;
+        let gradient = py
+            .import("numpy")?
+            .call_method1("ravel", (result.get_item("return_result")?,))?
+            .call_method0("tolist")?
+            .extract::<Vec<f64>>()?;
+        Ok(Ok(GradOutput::new(energy, gradient)))
+    }
+}
+
+impl GeomDriverAPI for QcEngineDriver {
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        if self.atom_data.state != 0 {
+            return Err(DriverError::CalculationFailed(
+                "QCEngine gradients are of the ground state only".into(),
+            ));
+        }
+        Python::with_gil(|py| match self.compute(py, coords) {
+            Ok(output) => output,
+            Err(err) => Err(err.to_string()),
+        })
+        .map_err(DriverError::CalculationFailed)
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.atom_data = atom_data.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_atomic_input() {
+        let mut driver = QcEngineDriver::new("psi4", "hf").basis("sto-3g");
+        driver.set_atom_data(&AtomData {
+            elem: vec!["H".into(), "H".into()],
+            ghost: vec![false, true],
+            charge: Some(0),
+            mult: Some(1),
+            lattice: None,
+            state: 0,
+        });
+        let input = driver.atomic_input(&[0.0, 0.0, 0.0, 0.0, 0.0, 1.4]);
+        assert_eq!(input["model"], json!({"method": "hf", "basis": "sto-3g"}));
+        assert_eq!(input["molecule"]["real"], json!([true, false]));
+        assert_eq!(input["molecule"]["molecular_multiplicity"], json!(1));
+        assert_eq!(input["driver"], "gradient");
+    }
+}
@@ -0,0 +1,511 @@
+//! Interoperability with MolSSI QCSchema (JSON).
+//!
+//! Only the fields of molecules needed by geomeTRIC are converted: symbols,
+//! geometry, charge, multiplicity and name. Other fields are ignored on input.
+//!
+//! [`run_optimization_schema`] takes a QCSchema `OptimizationInput`, as
+//! geomeTRIC's `run_json` entry point does, but evaluates gradients with a
+//! driver of this crate instead of QCEngine.
+
+use crate::constraints::{ConstraintCoord, Constraints};
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::PyGeomDriver;
+use crate::molecule::Molecule;
+use crate::optimize::{run_optimization_with_options, RunOptions};
+use crate::result::{OptResult, OptimizationOutcome, BOHR2ANG};
+use crate::runtime::initialize_python;
+use crate::util::json2py_val_with_bound;
+use crate::version::geometric_version;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// QCSchema molecule (`qcschema_molecule`).
+///
+/// - `symbols`: Element symbols.
+/// - `geometry`: Coordinates in Bohr, flattened (natom * 3).
+/// - `molecular_charge`, `molecular_multiplicity`: Total charge and spin
+///   multiplicity.
+/// - `name`: Optional name of the molecule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaMolecule {
+    #[serde(default = "schema_name")]
+    pub schema_name: String,
+    #[serde(default = "schema_version")]
+    pub schema_version: u32,
+    pub symbols: Vec<String>,
+    pub geometry: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub molecular_charge: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub molecular_multiplicity: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+fn schema_name() -> String {
+    "qcschema_molecule".into()
+}
+
+fn schema_version() -> u32 {
+    2
+}
+
+/// QCSchema model (`method` and `basis`) of the gradient calculations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaModel {
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub basis: Option<String>,
+}
+
+/// QCSchema specification of the gradient calculations of an optimization.
+///
+/// Only the `gradient` driver is supported. `model`, `keywords` and `extras`
+/// are not interpreted; the driver is expected to match them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaInputSpecification {
+    #[serde(default = "gradient_driver")]
+    pub driver: String,
+    pub model: QcSchemaModel,
+    #[serde(default)]
+    pub keywords: Map<String, Value>,
+    #[serde(default)]
+    pub extras: Map<String, Value>,
+}
+
+fn gradient_driver() -> String {
+    "gradient".into()
+}
+
+/// Which gradient calculations are kept in
+/// [`QcSchemaOptimizationResult::trajectory`] (`protocols.trajectory`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrajectoryProtocol {
+    #[default]
+    All,
+    InitialAndFinal,
+    Final,
+    None,
+}
+
+/// QCSchema protocols of an optimization.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaProtocols {
+    #[serde(default)]
+    pub trajectory: TrajectoryProtocol,
+}
+
+/// QCSchema optimization input (`qcschema_optimization_input`).
+///
+/// - `keywords`: geomeTRIC parameters (e.g. `coordsys`, `maxiter`), passed to
+///   `run_optimizer`. As in geomeTRIC's `run_json`, `program` is ignored and
+///   `constraints` is given as geomeTRIC's JSON constraints (see
+///   [`constraints_from_json`]).
+/// - `input_specification`: Specification of gradient calculations.
+/// - `initial_molecule`: Starting geometry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaOptimizationInput {
+    #[serde(default = "optimization_input_name")]
+    pub schema_name: String,
+    #[serde(default = "optimization_schema_version")]
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub keywords: Map<String, Value>,
+    #[serde(default)]
+    pub extras: Map<String, Value>,
+    #[serde(default)]
+    pub protocols: QcSchemaProtocols,
+    pub input_specification: QcSchemaInputSpecification,
+    pub initial_molecule: QcSchemaMolecule,
+}
+
+fn optimization_input_name() -> String {
+    "qcschema_optimization_input".into()
+}
+
+fn optimization_schema_version() -> u32 {
+    1
+}
+
+/// QCSchema properties of a gradient calculation; only the energy is given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaProperties {
+    pub return_energy: f64,
+}
+
+/// QCSchema result of one gradient calculation (`qcschema_output`) in the
+/// trajectory; `return_result` is the gradient in Hartree/Bohr, flattened
+/// (natom * 3).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaAtomicResult {
+    pub schema_name: String,
+    pub schema_version: u32,
+    pub driver: String,
+    pub model: QcSchemaModel,
+    pub molecule: QcSchemaMolecule,
+    pub return_result: Vec<f64>,
+    pub properties: QcSchemaProperties,
+    pub success: bool,
+    pub provenance: QcSchemaProvenance,
+}
+
+/// QCSchema provenance of a result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaProvenance {
+    pub creator: String,
+    pub version: String,
+    pub routine: String,
+}
+
+/// QCSchema error of a failed optimization, e.g. `convergence_error`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaComputeError {
+    pub error_type: String,
+    pub error_message: String,
+}
+
+/// QCSchema optimization result (`qcschema_optimization_output`).
+///
+/// Fields of the input are repeated. `trajectory` and `energies` cover all
+/// gradient calculations (`trajectory` is reduced by `protocols`);
+/// `final_molecule` is the last geometry, also if the optimization failed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QcSchemaOptimizationResult {
+    pub schema_name: String,
+    pub schema_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub keywords: Map<String, Value>,
+    pub extras: Map<String, Value>,
+    pub protocols: QcSchemaProtocols,
+    pub input_specification: QcSchemaInputSpecification,
+    pub initial_molecule: QcSchemaMolecule,
+    pub final_molecule: Option<QcSchemaMolecule>,
+    pub trajectory: Vec<QcSchemaAtomicResult>,
+    pub energies: Vec<f64>,
+    pub success: bool,
+    pub provenance: QcSchemaProvenance,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<QcSchemaComputeError>,
+}
+
+/// Build a geomeTRIC molecule from QCSchema JSON.
+///
+/// Charge must be an integer, as geomeTRIC molecules carry integer charges.
+pub fn molecule_from_qcschema(json: &str) -> PyResult<PyObject> {
+    let schema: QcSchemaMolecule = serde_json::from_str(json)
+        .map_err(|e| PyValueError::new_err(format!("Failed to parse QCSchema molecule: {}", e)))?;
+    if schema.geometry.len() != schema.symbols.len() * 3 {
+        return Err(PyValueError::new_err(format!(
+            "Length of QCSchema geometry ({}) does not match number of symbols ({}) * 3",
+            schema.geometry.len(),
+            schema.symbols.len()
+        )));
+    }
+    let elem = schema.symbols.iter().map(String::as_str).collect::<Vec<_>>();
+    let xyz = schema.geometry.iter().map(|x| x * BOHR2ANG).collect::<Vec<_>>();
+    let molecule = Molecule::new(&elem, &[xyz])?;
+    if let Some(charge) = schema.molecular_charge {
+        if charge.fract() != 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "Fractional molecular charge {} is not supported",
+                charge
+            )));
+        }
+        molecule.set_charge(charge as i64)?;
+    }
+    if let Some(mult) = schema.molecular_multiplicity {
+        molecule.set_mult(mult as i64)?;
+    }
+    Ok(molecule.pyobj)
+}
+
+/// Convert (the first frame of) a geomeTRIC molecule to QCSchema JSON.
+pub fn molecule_to_qcschema(molecule: &PyObject) -> PyResult<String> {
+    let molecule = Python::with_gil(|py| Molecule::from(molecule.clone_ref(py)));
+    let xyz = molecule
+        .xyzs()?
+        .into_iter()
+        .next()
+        .ok_or_else(|| PyValueError::new_err("Molecule has no frames"))?;
+    let schema = schema_molecule(molecule.elem()?, &xyz, molecule.charge()?, molecule.mult()?)?;
+    serde_json::to_string(&schema)
+        .map_err(|e| PyValueError::new_err(format!("Failed to serialize QCSchema molecule: {}", e)))
+}
+
+/// QCSchema molecule of coordinates `xyz` in Angstrom.
+fn schema_molecule(
+    symbols: Vec<String>,
+    xyz: &[f64],
+    charge: Option<i64>,
+    mult: Option<i64>,
+) -> PyResult<QcSchemaMolecule> {
+    let mult = mult
+        .map(u32::try_from)
+        .transpose()
+        .map_err(|_| PyValueError::new_err("Spin multiplicity must be positive"))?;
+    Ok(QcSchemaMolecule {
+        schema_name: schema_name(),
+        schema_version: schema_version(),
+        symbols,
+        geometry: xyz.iter().map(|x| x / BOHR2ANG).collect(),
+        molecular_charge: charge.map(|c| c as f64),
+        molecular_multiplicity: mult,
+        name: None,
+    })
+}
+
+/// Convert geomeTRIC's JSON constraints (`constraints` keyword of `run_json`)
+/// to [`Constraints`].
+///
+/// The JSON maps `freeze` and `set` to lists of
+/// `{"type": ..., "indices": [...], "value": ...}` with 0-based indices, e.g.
+/// `{"set": [{"type": "distance", "indices": [0, 1], "value": 1.5}]}`. Types
+/// `distance`, `angle` and `dihedral` are supported, and `xyz` for `freeze`.
+pub fn constraints_from_json(json: &Value) -> PyResult<Constraints> {
+    let invalid = |msg: String| PyValueError::new_err(format!("Invalid constraints: {}", msg));
+    let sections = json.as_object().ok_or_else(|| invalid("expected an object".into()))?;
+    let mut constraints = Constraints::new();
+    for (section, specs) in sections {
+        let specs =
+            specs.as_array().ok_or_else(|| invalid(format!("`{}` is not a list", section)))?;
+        for spec in specs {
+            let kind =
+                spec["type"].as_str().ok_or_else(|| invalid(format!("no type in {}", spec)))?;
+            let indices: Vec<usize> = spec["indices"]
+                .as_array()
+                .and_then(|indices| {
+                    indices.iter().map(|i| i.as_u64().map(|i| i as usize)).collect()
+                })
+                .ok_or_else(|| invalid(format!("no indices in {}", spec)))?;
+            if section == "freeze" && kind == "xyz" {
+                constraints = constraints.freeze_cartesian(&indices);
+                continue;
+            }
+            let coord = match (kind, indices.as_slice()) {
+                ("distance", &[a, b]) => ConstraintCoord::Distance(a, b),
+                ("angle", &[a, b, c]) => ConstraintCoord::Angle(a, b, c),
+                ("dihedral", &[a, b, c, d]) => ConstraintCoord::Dihedral(a, b, c, d),
+                _ => return Err(invalid(format!("unsupported constraint {}", spec))),
+            };
+            constraints = match section.as_str() {
+                "freeze" => constraints.freeze(coord),
+                "set" => {
+                    let value = spec["value"]
+                        .as_f64()
+                        .ok_or_else(|| invalid(format!("no value in {}", spec)))?;
+                    constraints.set(coord, value)
+                },
+                _ => return Err(invalid(format!("unsupported section `{}`", section))),
+            };
+        }
+    }
+    Ok(constraints)
+}
+
+/// Run a QCSchema optimization, with gradients evaluated by `driver`.
+///
+/// Invalid input is returned as error. Failures of the optimization itself
+/// (including non-convergence) give a result with `success = false` and
+/// `error` set, keeping the trajectory evaluated so far when available.
+pub fn run_optimization_qcschema(
+    input: &QcSchemaOptimizationInput,
+    driver: &PyGeomDriver,
+) -> Result<QcSchemaOptimizationResult, GeometricError> {
+    if input.input_specification.driver != "gradient" {
+        return Err(GeometricError::InvalidParameters(PyValueError::new_err(format!(
+            "Unsupported QCSchema driver `{}`, expected `gradient`",
+            input.input_specification.driver
+        ))));
+    }
+    initialize_python();
+    let molecule_json = serde_json::to_string(&input.initial_molecule).map_err(|e| {
+        PyValueError::new_err(format!("Failed to serialize QCSchema molecule: {}", e))
+    })?;
+    let molecule = molecule_from_qcschema(&molecule_json)?;
+    let mut options = RunOptions::default();
+    let params = Python::with_gil(|py| -> PyResult<_> {
+        let params = PyDict::new(py);
+        for (key, value) in &input.keywords {
+            match key.as_str() {
+                "program" => (),
+                "constraints" => options.constraints = Some(constraints_from_json(value)?),
+                _ => params.set_item(key, json2py_val_with_bound(py, value)?)?,
+            }
+        }
+        Ok(params.unbind())
+    })?;
+
+    let custom_engine = init_pyo3_engine(&molecule, driver)?;
+    let (result, error) =
+        match run_optimization_with_options(custom_engine, &params, None, &options) {
+            Ok(OptimizationOutcome::Converged(result)) => (Some(result), None),
+            Ok(OptimizationOutcome::NotConverged { result, reason }) => {
+                (Some(result), Some(("convergence_error", reason)))
+            },
+            Ok(outcome) => (
+                Some(outcome.into_result()),
+                Some(("unknown_error", "Optimization stopped".into())),
+            ),
+            Err(err) => {
+                let error_type = match err {
+                    GeometricError::NotConverged(_) => "convergence_error",
+                    GeometricError::InvalidParameters(_) => "input_error",
+                    GeometricError::NotInstalled(_) | GeometricError::Unsupported(_) => {
+                        "resource_error"
+                    },
+                    GeometricError::Engine(_) | GeometricError::Python(_) => "unknown_error",
+                };
+                (None, Some((error_type, err.to_string())))
+            },
+        };
+    let version = geometric_version().map(|v| v.to_string()).unwrap_or_default();
+    Ok(optimization_result(input, result.as_ref(), error, version)?)
+}
+
+/// Run a QCSchema optimization given as JSON, see
+/// [`run_optimization_qcschema`]; the result is returned as JSON.
+pub fn run_optimization_schema(
+    input_json: &str,
+    driver: &PyGeomDriver,
+) -> Result<String, GeometricError> {
+    let input: QcSchemaOptimizationInput = serde_json::from_str(input_json).map_err(|e| {
+        PyValueError::new_err(format!("Failed to parse QCSchema optimization input: {}", e))
+    })?;
+    let result = run_optimization_qcschema(&input, driver)?;
+    Ok(serde_json::to_string(&result).map_err(|e| {
+        PyValueError::new_err(format!("Failed to serialize QCSchema optimization result: {}", e))
+    })?)
+}
+
+/// Assemble the QCSchema result from the trajectory of the optimization.
+fn optimization_result(
+    input: &QcSchemaOptimizationInput,
+    result: Option<&OptResult>,
+    error: Option<(&str, String)>,
+    version: String,
+) -> PyResult<QcSchemaOptimizationResult> {
+    let provenance = QcSchemaProvenance {
+        creator: "geomeTRIC".into(),
+        version,
+        routine: "geometric_pyo3::qcschema::run_optimization_qcschema".into(),
+    };
+    let initial = &input.initial_molecule;
+    let molecule = |xyz: &[f64]| -> PyResult<QcSchemaMolecule> {
+        Ok(QcSchemaMolecule {
+            name: initial.name.clone(),
+            molecular_charge: initial.molecular_charge,
+            molecular_multiplicity: initial.molecular_multiplicity,
+            ..schema_molecule(initial.symbols.clone(), xyz, None, None)?
+        })
+    };
+    let mut trajectory = vec![];
+    let mut energies = vec![];
+    let mut final_molecule = None;
+    if let Some(result) = result {
+        let nframe = result.xyzs.len();
+        let keep = |index: usize| match input.protocols.trajectory {
+            TrajectoryProtocol::All => true,
+            TrajectoryProtocol::InitialAndFinal => index == 0 || index + 1 == nframe,
+            TrajectoryProtocol::Final => index + 1 == nframe,
+            TrajectoryProtocol::None => false,
+        };
+        for (index, (xyz, &energy)) in result.xyzs.iter().zip(&result.energies).enumerate() {
+            energies.push(energy);
+            if !keep(index) {
+                continue;
+            }
+            trajectory.push(QcSchemaAtomicResult {
+                schema_name: "qcschema_output".into(),
+                schema_version: 1,
+                driver: "gradient".into(),
+                model: input.input_specification.model.clone(),
+                molecule: molecule(xyz)?,
+                return_result: result.gradients.get(index).cloned().unwrap_or_default(),
+                properties: QcSchemaProperties { return_energy: energy },
+                success: true,
+                provenance: provenance.clone(),
+            });
+        }
+        final_molecule = result.final_coords().map(molecule).transpose()?;
+    }
+    Ok(QcSchemaOptimizationResult {
+        schema_name: "qcschema_optimization_output".into(),
+        schema_version: input.schema_version,
+        id: input.id.clone(),
+        keywords: input.keywords.clone(),
+        extras: input.extras.clone(),
+        protocols: input.protocols.clone(),
+        input_specification: input.input_specification.clone(),
+        initial_molecule: input.initial_molecule.clone(),
+        final_molecule,
+        trajectory,
+        energies,
+        success: error.is_none(),
+        provenance,
+        error: error.map(|(error_type, error_message)| QcSchemaComputeError {
+            error_type: error_type.into(),
+            error_message,
+        }),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_qcschema_serde() {
+        let json = r#"{"symbols": ["O", "H", "H"], "geometry": [0, 0, 0, 0, 1.43, 1.11, 0, -1.43, 1.11],
+                       "molecular_charge": 0.0, "molecular_multiplicity": 1, "extras": {}}"#;
+        let schema: QcSchemaMolecule = serde_json::from_str(json).unwrap();
+        assert_eq!(schema.schema_name, "qcschema_molecule");
+        assert_eq!(schema.molecular_multiplicity, Some(1));
+        let roundtrip = serde_json::to_string(&schema).unwrap();
+        assert_eq!(serde_json::from_str::<QcSchemaMolecule>(&roundtrip).unwrap(), schema);
+        assert!(!roundtrip.contains("\"name\""));
+    }
+
+    #[test]
+    fn test_optimization_input() {
+        let json = r#"{
+            "keywords": {"coordsys": "tric", "program": "psi4",
+                         "constraints": {"freeze": [{"type": "xyz", "indices": [2]}],
+                                         "set": [{"type": "distance", "indices": [0, 1], "value": 0.97}]}},
+            "protocols": {"trajectory": "final"},
+            "input_specification": {"model": {"method": "hf", "basis": "sto-3g"}},
+            "initial_molecule": {"symbols": ["O", "H", "H"],
+                                 "geometry": [0, 0, 0, 0, 1.43, 1.11, 0, -1.43, 1.11]}
+        }"#;
+        let input: QcSchemaOptimizationInput = serde_json::from_str(json).unwrap();
+        assert_eq!(input.schema_name, "qcschema_optimization_input");
+        assert_eq!(input.input_specification.driver, "gradient");
+        assert_eq!(input.protocols.trajectory, TrajectoryProtocol::Final);
+
+        let constraints = constraints_from_json(&input.keywords["constraints"]).unwrap();
+        let expected =
+            Constraints::new().freeze_cartesian(&[2]).set(ConstraintCoord::Distance(0, 1), 0.97);
+        assert_eq!(constraints, expected);
+
+        let result =
+            optimization_result(&input, None, Some(("input_error", "bad".into())), "1.1.0".into())
+                .unwrap();
+        assert!(!result.success);
+        assert_eq!(result.schema_name, "qcschema_optimization_output");
+        let result_json = serde_json::to_string(&result).unwrap();
+        assert!(
+            result_json.contains(r#""error":{"error_type":"input_error","error_message":"bad"}"#)
+        );
+
+        pyo3::prepare_freethreaded_python();
+        let scan = serde_json::json!({"scan": [{"type": "distance", "indices": [0, 1]}]});
+        assert!(constraints_from_json(&scan).is_err());
+    }
+}
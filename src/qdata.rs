@@ -0,0 +1,119 @@
+//! Parser of `qdata.txt`, the per-step QM data written by geomeTRIC with
+//! `qdata = true` (see [`OptimizerParams::qdata`]).
+//!
+//! The file uses ForceBalance's format, one block per evaluation:
+//!
+//! ```text
+//! JOB 0
+//! COORDS x1 y1 z1 x2 y2 z2 ...
+//! ENERGY -76.0267
+//! FORCES g1x g1y g1z ...
+//! ```
+//!
+//! This is handy to collect training data of machine-learning potentials from
+//! optimizations.
+//!
+//! [`OptimizerParams::qdata`]: crate::params::OptimizerParams::qdata
+
+use std::fs;
+use std::path::Path;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+/// Data of one evaluation in `qdata.txt`.
+///
+/// - `job`: Index of the evaluation (`JOB` line).
+/// - `coords`: Coordinates in Angstrom, flattened (natom * 3).
+/// - `energy`: Energy in Hartree, if given.
+/// - `gradient`: Gradient in Hartree/Bohr, flattened (natom * 3), if given.
+///   Note that ForceBalance labels it `FORCES`, but stores the gradient.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QDataFrame {
+    pub job: usize,
+    pub coords: Vec<f64>,
+    pub energy: Option<f64>,
+    pub gradient: Option<Vec<f64>>,
+}
+
+/// Parse the content of `qdata.txt`.
+///
+/// Keys other than `JOB`, `COORDS`, `ENERGY` and `FORCES` (e.g. ForceBalance's
+/// `ESPXYZ`) are ignored.
+pub fn parse_qdata(text: &str) -> PyResult<Vec<QDataFrame>> {
+    let mut frames: Vec<QDataFrame> = vec![];
+    for (lineno, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        let Some(key) = tokens.next() else {
+            continue;
+        };
+        let values = || {
+            tokens.clone().map(|v| v.parse::<f64>()).collect::<Result<Vec<_>, _>>().map_err(|e| {
+                PyValueError::new_err(format!("Invalid {} at line {}: {}", key, lineno + 1, e))
+            })
+        };
+        if key == "JOB" {
+            let job = tokens.next().and_then(|j| j.parse::<usize>().ok()).ok_or_else(|| {
+                PyValueError::new_err(format!("Invalid JOB at line {}", lineno + 1))
+            })?;
+            frames.push(QDataFrame { job, ..Default::default() });
+            continue;
+        }
+        let frame = match frames.last_mut() {
+            Some(frame) => frame,
+            None if matches!(key, "COORDS" | "ENERGY" | "FORCES") => {
+                return Err(PyValueError::new_err(format!(
+                    "{} before the first JOB at line {}",
+                    key,
+                    lineno + 1
+                )));
+            },
+            None => continue,
+        };
+        match key {
+            "COORDS" => frame.coords = values()?,
+            "ENERGY" => match values()?.as_slice() {
+                [energy] => frame.energy = Some(*energy),
+                _ => {
+                    return Err(PyValueError::new_err(format!(
+                        "ENERGY must have one value at line {}",
+                        lineno + 1
+                    )));
+                },
+            },
+            "FORCES" => frame.gradient = Some(values()?),
+            _ => (),
+        }
+    }
+    Ok(frames)
+}
+
+/// Read and parse a `qdata.txt` file, see [`parse_qdata`].
+pub fn read_qdata(path: impl AsRef<Path>) -> PyResult<Vec<QDataFrame>> {
+    parse_qdata(&fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_qdata() {
+        let text = "JOB 0\nCOORDS 0.0 0.0 0.0 0.0 0.0 0.74\nENERGY -1.17\n\
+                    FORCES 0.0 0.0 0.01 0.0 0.0 -0.01\n\nJOB 1\nCOORDS 0.0 0.0 0.0 0.0 0.0 0.73\n";
+        let frames = parse_qdata(text).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].energy, Some(-1.17));
+        assert_eq!(frames[0].gradient.as_ref().unwrap()[2], 0.01);
+        assert_eq!(frames[1], QDataFrame {
+            job: 1,
+            coords: vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.73],
+            energy: None,
+            gradient: None,
+        });
+
+        pyo3::prepare_freethreaded_python();
+        assert!(parse_qdata("ENERGY -1.0\n").is_err());
+        assert!(parse_qdata("JOB 0\nENERGY -1.0 2.0\n").is_err());
+    }
+}
@@ -0,0 +1,489 @@
+//! Rust-side representation of geomeTRIC optimization results.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::constraints::ConstraintReport;
+use crate::molecule::{LengthUnit, Molecule};
+use crate::params::CoordSys;
+use pyo3::exceptions::PyIndexError;
+use pyo3::prelude::*;
+
+/// Conversion factor from Bohr to Angstrom (same value as `geometric.nifty`).
+pub(crate) const BOHR2ANG: f64 = 0.52917721067;
+
+/// Optimization trajectory extracted from geomeTRIC.
+///
+/// - `elem`: Element symbols of the molecule.
+/// - `xyzs`: Coordinates of each frame in Angstrom, flattened (natom * 3), with
+///   dimension of coordinate (3) to be contiguous.
+/// - `energies`: Energy of each frame in Hartree.
+/// - `gradients`: Gradient of each frame in Hartree/Bohr, flattened (natom *
+///   3); empty for frames whose gradient is not available.
+/// - `charge`, `mult`: Total charge and spin multiplicity, if they have been
+///   set on the input molecule.
+/// - `energy_components`: Labeled energy components of each frame, as given by
+///   [`GradOutput::energy_components`](crate::interface::GradOutput). Maps are
+///   empty if the driver does not supply them.
+/// - `extras`: Extra properties of each frame, as given by
+///   [`GradOutput::extras`](crate::interface::GradOutput). Maps are empty if
+///   the driver does not supply them.
+/// - `run_id`: Label of the run given by
+///   [`RunOptions::run_id`](crate::optimize::RunOptions), for correlating
+///   results with external tracing.
+/// - `atom_permutation`: `None` if atoms are in the same order as the input
+///   molecule (the normal case). If geomeTRIC reordered atoms,
+///   `atom_permutation[i]` is the input index of atom `i` of this result, to
+///   map results back to the original indexing (see [`atom_permutation`]).
+/// - `coordsys`: Coordinate system used, if it was chosen by
+///   [`RunOptions::coordsys_fallback`](crate::optimize::RunOptions).
+/// - `converged`: Whether geomeTRIC reported convergence (same to
+///   [`OptimizationOutcome::is_converged`]).
+/// - `log`: Text of geomeTRIC's log file, if captured by
+///   [`RunOptions::in_memory`](crate::optimize::RunOptions).
+/// - `hessian`: Cartesian Hessian at the final geometry (Hartree/Bohr²,
+///   flattened natom * 3 by natom * 3), if geomeTRIC computed it (see
+///   [`OptimizerParams::hessian`](crate::params::OptimizerParams::hessian)).
+/// - `constraint_report`: Target and achieved values of `$set` constraints at
+///   the final geometry (see
+///   [`Constraints::report`](crate::constraints::Constraints::report)), if
+///   constraints were given by
+///   [`RunOptions::constraints`](crate::optimize::RunOptions).
+/// - `stats`: Driver call statistics of the run (see [`CallStats`]).
+///
+/// All data is copied out of python objects, so the result is `Send + 'static`
+/// and can be moved across threads and outlive any GIL scope.
+///
+/// Fields may be added in future versions; results are built by
+/// [`OptResult::new`] outside of this crate.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct OptResult {
+    pub elem: Vec<String>,
+    pub xyzs: Vec<Vec<f64>>,
+    pub energies: Vec<f64>,
+    pub gradients: Vec<Vec<f64>>,
+    pub charge: Option<i64>,
+    pub mult: Option<i64>,
+    pub energy_components: Vec<HashMap<String, f64>>,
+    pub extras: Vec<HashMap<String, serde_json::Value>>,
+    pub run_id: Option<String>,
+    pub atom_permutation: Option<Vec<usize>>,
+    pub coordsys: Option<CoordSys>,
+    pub converged: bool,
+    pub log: Option<String>,
+    pub hessian: Option<Vec<f64>>,
+    pub constraint_report: Vec<ConstraintReport>,
+    pub stats: CallStats,
+}
+
+/// Driver call statistics of an optimization, recorded by the engine.
+///
+/// - `n_gradient`: Number of gradient evaluations requested by geomeTRIC.
+/// - `n_hessian`: Number of analytic Hessians provided by the driver.
+/// - `driver_time`: Cumulative wall-clock time spent in the driver.
+/// - `python_time`: Cumulative wall-clock time outside the driver between
+///   evaluations, from the start of the run to the last evaluation (geomeTRIC's
+///   steps, coordinate transformations, etc.).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CallStats {
+    pub n_gradient: usize,
+    pub n_hessian: usize,
+    pub driver_time: Duration,
+    pub python_time: Duration,
+}
+
+/// Alias of [`OptResult`].
+pub type OptimizationResult = OptResult;
+
+/// Rust handle of geomeTRIC's `Progress` object (a
+/// `geometric.molecule.Molecule` holding the optimization trajectory, e.g.
+/// returned by `run_optimizer`).
+///
+/// Trajectory data is extracted from python on first access and cached, so
+/// repeated access is free.
+pub struct Progress {
+    pub pyobj: PyObject,
+    geometries: OnceLock<Vec<Vec<f64>>>,
+    energies: OnceLock<Vec<f64>>,
+    gradients: OnceLock<Vec<Vec<f64>>>,
+}
+
+impl From<PyObject> for Progress {
+    fn from(pyobj: PyObject) -> Self {
+        Progress {
+            pyobj,
+            geometries: OnceLock::new(),
+            energies: OnceLock::new(),
+            gradients: OnceLock::new(),
+        }
+    }
+}
+
+impl Progress {
+    /// Coordinates of each frame (Angstrom, flattened natom * 3), from `xyzs`.
+    pub fn geometries(&self) -> PyResult<&[Vec<f64>]> {
+        cached(&self.geometries, || {
+            Python::with_gil(|py| flatten_frames(&self.pyobj.bind(py).getattr("xyzs")?))
+        })
+        .map(Vec::as_slice)
+    }
+
+    /// Energy of each frame (Hartree), from `qm_energies`.
+    pub fn energies(&self) -> PyResult<&[f64]> {
+        cached(&self.energies, || {
+            Python::with_gil(|py| self.pyobj.bind(py).getattr("qm_energies")?.extract())
+        })
+        .map(Vec::as_slice)
+    }
+
+    /// Gradient of each frame (Hartree/Bohr, flattened natom * 3), from
+    /// `qm_grads`; empty for all frames if not available.
+    pub fn gradients(&self) -> PyResult<&[Vec<f64>]> {
+        cached(&self.gradients, || {
+            Python::with_gil(|py| {
+                let progress = self.pyobj.bind(py);
+                match progress.hasattr("qm_grads")? {
+                    true => flatten_frames(&progress.getattr("qm_grads")?),
+                    false => Ok(vec![vec![]; self.geometries()?.len()]),
+                }
+            })
+        })
+        .map(Vec::as_slice)
+    }
+
+    /// Convert to [`OptResult`], with elements, charge and multiplicity read
+    /// from the progress object.
+    pub fn into_result(self) -> PyResult<OptResult> {
+        self.geometries()?;
+        self.energies()?;
+        self.gradients()?;
+        Python::with_gil(|py| {
+            let mut result = OptResult::with_molecule_info(
+                self.pyobj.bind(py),
+                self.geometries.into_inner().unwrap(),
+                self.energies.into_inner().unwrap(),
+            )?;
+            result.gradients = self.gradients.into_inner().unwrap();
+            Ok(result)
+        })
+    }
+}
+
+/// Get the cached value, or initialize it by `init`.
+fn cached<T>(cell: &OnceLock<T>, init: impl FnOnce() -> PyResult<T>) -> PyResult<&T> {
+    if let Some(value) = cell.get() {
+        return Ok(value);
+    }
+    let value = init()?;
+    Ok(cell.get_or_init(|| value))
+}
+
+/// Extract a list of numpy arrays as flattened vectors.
+fn flatten_frames(frames: &Bound<'_, PyAny>) -> PyResult<Vec<Vec<f64>>> {
+    frames
+        .try_iter()?
+        .map(|frame| frame?.call_method0("flatten")?.call_method0("tolist")?.extract())
+        .collect()
+}
+
+impl OptResult {
+    /// Result of a trajectory of `elem`, with coordinates `xyzs` (Angstrom) and
+    /// `energies` of each frame.
+    ///
+    /// Per-frame data other than coordinates and energies is left empty (one
+    /// empty entry per frame); other fields are unset.
+    pub fn new(elem: Vec<String>, xyzs: Vec<Vec<f64>>, energies: Vec<f64>) -> Self {
+        let nframe = xyzs.len();
+        OptResult {
+            elem,
+            xyzs,
+            energies,
+            gradients: vec![vec![]; nframe],
+            charge: None,
+            mult: None,
+            energy_components: vec![HashMap::new(); nframe],
+            extras: vec![HashMap::new(); nframe],
+            run_id: None,
+            atom_permutation: None,
+            coordsys: None,
+            converged: false,
+            log: None,
+            hessian: None,
+            constraint_report: vec![],
+            stats: Default::default(),
+        }
+    }
+
+    /// Extract the trajectory from geomeTRIC's `Progress` object (the
+    /// `geometric.molecule.Molecule` returned by `run_optimizer`).
+    pub fn from_progress(progress: &Bound<'_, PyAny>) -> PyResult<Self> {
+        Progress::from(progress.clone().unbind()).into_result()
+    }
+
+    /// Build result from trajectory, with elements, charge and multiplicity
+    /// read from a geomeTRIC molecule.
+    pub(crate) fn with_molecule_info(
+        molecule: &Bound<'_, PyAny>,
+        xyzs: Vec<Vec<f64>>,
+        energies: Vec<f64>,
+    ) -> PyResult<Self> {
+        let molecule = Molecule::from(molecule.clone().unbind());
+        let elem = molecule.elem()?;
+        let charge = molecule.charge()?;
+        let mult = molecule.mult()?;
+        Ok(OptResult { charge, mult, ..OptResult::new(elem, xyzs, energies) })
+    }
+
+    /// Coordinates of the last frame (Angstrom).
+    pub fn final_coords(&self) -> Option<&[f64]> {
+        self.xyzs.last().map(|xyz| xyz.as_slice())
+    }
+
+    /// Coordinates of all frames in the given unit.
+    pub fn xyzs_in(&self, unit: LengthUnit) -> Vec<Vec<f64>> {
+        self.xyzs.iter().map(|xyz| unit.from_angstrom(xyz)).collect()
+    }
+
+    /// Coordinates of the last frame in the given unit.
+    pub fn final_coords_in(&self, unit: LengthUnit) -> Option<Vec<f64>> {
+        self.final_coords().map(|xyz| unit.from_angstrom(xyz))
+    }
+
+    /// Number of optimization steps (frames after the initial geometry).
+    pub fn n_steps(&self) -> usize {
+        self.xyzs.len().saturating_sub(1)
+    }
+
+    /// Energy of the last frame (Hartree).
+    pub fn final_energy(&self) -> Option<f64> {
+        self.energies.last().copied()
+    }
+
+    /// Largest distance (Angstrom) any atom moved between the first and the
+    /// last frame.
+    ///
+    /// A large value often indicates a bad starting geometry or wrong
+    /// connectivity. Returns zero for trajectories of less than two frames.
+    pub fn max_atom_displacement(&self) -> f64 {
+        let (Some(first), Some(last)) = (self.xyzs.first(), self.xyzs.last()) else {
+            return 0.0;
+        };
+        first
+            .chunks(3)
+            .zip(last.chunks(3))
+            .map(|(a, b)| a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt())
+            .fold(0.0, f64::max)
+    }
+
+    /// Copy of the result with only the given frames, in the given order.
+    ///
+    /// Per-frame data (coordinates, energies, gradients, energy components,
+    /// extras) is selected; other fields are copied.
+    pub fn select_frames(&self, indices: &[usize]) -> OptResult {
+        fn select<T: Clone>(values: &[T], indices: &[usize]) -> Vec<T> {
+            indices.iter().filter_map(|&i| values.get(i).cloned()).collect()
+        }
+        OptResult {
+            xyzs: select(&self.xyzs, indices),
+            energies: select(&self.energies, indices),
+            gradients: select(&self.gradients, indices),
+            energy_components: select(&self.energy_components, indices),
+            extras: select(&self.extras, indices),
+            ..self.clone()
+        }
+    }
+
+    /// Build a geomeTRIC molecule of one trajectory frame.
+    ///
+    /// Elements, charge and multiplicity are copied from this result.
+    pub fn frame_molecule(&self, index: usize) -> PyResult<Molecule> {
+        let xyz = self.xyzs.get(index).ok_or_else(|| {
+            PyIndexError::new_err(format!(
+                "Frame index {} out of range for trajectory of {} frames",
+                index,
+                self.xyzs.len()
+            ))
+        })?;
+        let elem = self.elem.iter().map(String::as_str).collect::<Vec<_>>();
+        let molecule = Molecule::new(&elem, std::slice::from_ref(xyz))?;
+        if let Some(charge) = self.charge {
+            molecule.set_charge(charge)?;
+        }
+        if let Some(mult) = self.mult {
+            molecule.set_mult(mult)?;
+        }
+        Ok(molecule)
+    }
+
+    /// Build geomeTRIC molecules of all trajectory frames.
+    ///
+    /// Each frame creates a new python object, so this can be expensive for
+    /// long trajectories; use [`OptResult::frame_molecule`] if only a few
+    /// frames are needed.
+    pub fn frame_molecules(&self) -> PyResult<Vec<Molecule>> {
+        (0..self.xyzs.len()).map(|index| self.frame_molecule(index)).collect()
+    }
+}
+
+/// Detect reordering of atoms between the input and output of geomeTRIC.
+///
+/// Atoms of the output (`elem`, with first-frame coordinates `xyz` in Angstrom)
+/// are matched to the nearest input atom of the same element
+/// (`ref_elem`/`ref_xyz`, the input geometry). Returns `None` if the ordering
+/// is unchanged, otherwise the input index of each output atom.
+pub fn atom_permutation(
+    ref_elem: &[String],
+    ref_xyz: &[f64],
+    elem: &[String],
+    xyz: &[f64],
+) -> Option<Vec<usize>> {
+    if ref_elem == elem && ref_xyz.len() == xyz.len() {
+        let max_diff = ref_xyz.iter().zip(xyz).map(|(x, y)| (x - y).abs()).fold(0.0, f64::max);
+        if max_diff < 1.0e-6 {
+            return None;
+        }
+    }
+    let dist2 = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f64>();
+    let mut used = vec![false; ref_elem.len()];
+    let permutation = elem
+        .iter()
+        .zip(xyz.chunks(3))
+        .map(|(e, coord)| {
+            let idx = (0..ref_elem.len()).filter(|&j| !used[j] && &ref_elem[j] == e).min_by(
+                |&j, &k| {
+                    let dj = dist2(coord, &ref_xyz[3 * j..3 * j + 3]);
+                    let dk = dist2(coord, &ref_xyz[3 * k..3 * k + 3]);
+                    dj.total_cmp(&dk)
+                },
+            )?;
+            used[idx] = true;
+            Some(idx)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    match permutation.iter().enumerate().all(|(i, &j)| i == j) {
+        true => None,
+        false => Some(permutation),
+    }
+}
+
+/// Outcome of [`run_optimization`](crate::optimize::run_optimization).
+///
+/// - `Converged`: geomeTRIC finished normally.
+/// - `MaxTime`: The driver time budget (see
+///   [`EngineMixin::set_time_budget`](crate::engine::EngineMixin::set_time_budget))
+///   or the wall-clock timeout (see
+///   [`RunOptions::timeout`](crate::optimize::RunOptions)) has been exhausted.
+///   The result is not converged; its trajectory ends at the lowest-energy
+///   geometry evaluated so far.
+/// - `EnergyTarget`: The energy crossed the target given by
+///   [`RunOptions::stop_at_energy`](crate::optimize::RunOptions). The
+///   trajectory ends at the geometry where the crossing happened, which is
+///   generally not a stationary point; this is distinct from convergence.
+/// - `Cancelled`: The optimization was cancelled by
+///   [`RunOptions::cancel_token`](crate::optimize::RunOptions). The trajectory
+///   contains all evaluated geometries, ending at the last one.
+/// - `NotConverged`: geomeTRIC gave up without convergence (e.g. `maxiter`
+///   reached). The trajectory contains all evaluated geometries, ending at the
+///   last one, so the optimization can be inspected or restarted from it;
+///   `reason` is geomeTRIC's error message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptimizationOutcome {
+    Converged(OptResult),
+    MaxTime(OptResult),
+    EnergyTarget(OptResult),
+    Cancelled(OptResult),
+    NotConverged { result: OptResult, reason: String },
+}
+
+impl OptimizationOutcome {
+    /// Whether geomeTRIC reported convergence.
+    pub fn is_converged(&self) -> bool {
+        matches!(self, OptimizationOutcome::Converged(_))
+    }
+
+    /// Reference to the inner result, regardless of convergence.
+    pub fn result(&self) -> &OptResult {
+        match self {
+            OptimizationOutcome::Converged(result)
+            | OptimizationOutcome::MaxTime(result)
+            | OptimizationOutcome::EnergyTarget(result)
+            | OptimizationOutcome::Cancelled(result)
+            | OptimizationOutcome::NotConverged { result, .. } => result,
+        }
+    }
+
+    /// Mutable reference to the inner result, regardless of convergence.
+    pub fn result_mut(&mut self) -> &mut OptResult {
+        match self {
+            OptimizationOutcome::Converged(result)
+            | OptimizationOutcome::MaxTime(result)
+            | OptimizationOutcome::EnergyTarget(result)
+            | OptimizationOutcome::Cancelled(result)
+            | OptimizationOutcome::NotConverged { result, .. } => result,
+        }
+    }
+
+    /// Take the inner result, regardless of convergence.
+    pub fn into_result(self) -> OptResult {
+        match self {
+            OptimizationOutcome::Converged(result)
+            | OptimizationOutcome::MaxTime(result)
+            | OptimizationOutcome::EnergyTarget(result)
+            | OptimizationOutcome::Cancelled(result)
+            | OptimizationOutcome::NotConverged { result, .. } => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::IntoPyDict;
+
+    #[test]
+    fn test_max_atom_displacement() {
+        let mut result = OptResult::new(
+            vec!["H".into(), "H".into()],
+            vec![vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.74]],
+            vec![-1.0],
+        );
+        assert_eq!(result.max_atom_displacement(), 0.0);
+
+        result.xyzs.push(vec![0.0, 0.1, 0.0, 0.3, 0.0, 1.14]);
+        assert!((result.max_atom_displacement() - 0.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_atom_permutation() {
+        let elem = ["O", "H", "H"].map(String::from);
+        let xyz = [0.0, 0.0, 0.0, 0.0, 0.76, 0.59, 0.0, -0.76, 0.59];
+        // ordering is stable for a normal optimization (output first frame is input)
+        assert_eq!(atom_permutation(&elem, &xyz, &elem, &xyz), None);
+
+        let reordered_elem = ["H", "O", "H"].map(String::from);
+        let reordered_xyz = [0.0, -0.76, 0.59, 0.0, 0.0, 0.0, 0.0, 0.76, 0.59];
+        let permutation = atom_permutation(&elem, &xyz, &reordered_elem, &reordered_xyz);
+        assert_eq!(permutation, Some(vec![2, 0, 1]));
+    }
+
+    #[test]
+    fn test_result_outlives_python_thread() {
+        pyo3::prepare_freethreaded_python();
+
+        let worker = std::thread::spawn(|| {
+            Python::with_gil(|py| {
+                let molecule = py
+                    .import("types")?
+                    .getattr("SimpleNamespace")?
+                    .call((), Some(&[("elem", vec!["H", "H"])].into_py_dict(py)?))?;
+                let xyzs = vec![vec![0.0, 0.0, 0.0, 0.0, 0.0, 0.74]];
+                OptResult::with_molecule_info(&molecule, xyzs, vec![-1.17])
+            })
+        });
+        let result = worker.join().unwrap().unwrap();
+        assert_eq!(result.elem, ["H", "H"]);
+        assert_eq!(result.final_energy(), Some(-1.17));
+    }
+}
@@ -0,0 +1,84 @@
+//! Lifecycle of the embedded python interpreter.
+//!
+//! Applications embedding python (instead of being a python extension) may
+//! initialize the interpreter by [`initialize_python`], and tear it down by
+//! [`shutdown`] at exit.
+
+use std::sync::{Mutex, OnceLock};
+
+use crate::interface::PyGeomDriver;
+use pyo3::prelude::*;
+
+/// Whether the python interpreter was initialized by this crate.
+static OWNS_INTERPRETER: OnceLock<bool> = OnceLock::new();
+
+/// Drivers whose [`finalize`](crate::interface::GeomDriverAPI::finalize) hook
+/// runs at [`shutdown`].
+static FINALIZERS: Mutex<Vec<PyGeomDriver>> = Mutex::new(Vec::new());
+
+/// Initialize the python interpreter (thread-safe, only once).
+///
+/// This is [`pyo3::prepare_freethreaded_python`], additionally recording
+/// whether the interpreter was initialized here, or already running (e.g. when
+/// this crate is used from a python extension). Only in the former case
+/// [`shutdown`] may finalize the interpreter.
+pub fn initialize_python() {
+    OWNS_INTERPRETER.get_or_init(|| {
+        // Safety: only queries the interpreter state.
+        let initialized = unsafe { pyo3::ffi::Py_IsInitialized() } != 0;
+        pyo3::prepare_freethreaded_python();
+        !initialized
+    });
+}
+
+/// Whether the python interpreter was initialized by [`initialize_python`].
+pub fn owns_interpreter() -> bool {
+    OWNS_INTERPRETER.get().copied().unwrap_or(false)
+}
+
+/// Register the driver's `finalize` hook to be run at [`shutdown`].
+pub fn register_finalizer(driver: &PyGeomDriver) {
+    FINALIZERS.lock().unwrap().push(driver.clone());
+}
+
+/// Gracefully shut down: run registered driver `finalize` hooks, flush and
+/// close python logging handlers (including geomeTRIC's log files), and
+/// optionally finalize the interpreter.
+///
+/// The interpreter is only finalized if `finalize_interpreter` is set and it
+/// was initialized by [`initialize_python`]. Python can not be used again
+/// after it is finalized.
+///
+/// # Safety
+///
+/// This must not be called while optimizations (or any other python work) are
+/// in flight, and no python objects (e.g.
+/// [`Molecule`](crate::molecule::Molecule) handles or parameter dictionaries)
+/// may be used after the interpreter is finalized.
+pub unsafe fn shutdown(finalize_interpreter: bool) -> PyResult<()> {
+    let drivers = std::mem::take(&mut *FINALIZERS.lock().unwrap());
+    for driver in drivers {
+        driver.pointer.lock().unwrap().finalize();
+    }
+
+    if unsafe { pyo3::ffi::Py_IsInitialized() } == 0 {
+        return Ok(());
+    }
+    Python::with_gil(|py| {
+        py.import("logging")?.call_method0("shutdown")?;
+        let sys = py.import("sys")?;
+        sys.getattr("stdout")?.call_method0("flush")?;
+        sys.getattr("stderr")?.call_method0("flush")?;
+        PyResult::Ok(())
+    })?;
+
+    if finalize_interpreter && owns_interpreter() {
+        // Safety: guaranteed by the caller; the GIL is acquired for
+        // finalization.
+        unsafe {
+            pyo3::ffi::PyGILState_Ensure();
+            pyo3::ffi::Py_FinalizeEx();
+        }
+    }
+    Ok(())
+}
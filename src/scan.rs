@@ -0,0 +1,149 @@
+//! Relaxed scan of an internal coordinate.
+
+use crate::constraints::{ConstraintCoord, ScanSpec};
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::PyGeomDriver;
+use crate::molecule::Molecule;
+use crate::optimize::{run_optimization_with_options, RunOptions};
+use crate::result::OptResult;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Result of a relaxed scan.
+///
+/// - `coord`: The scanned coordinate.
+/// - `values`: Scan values (Angstrom or degree), in order of scan.
+/// - `results`: Constrained optimization result of each scan point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanResult {
+    pub coord: ConstraintCoord,
+    pub values: Vec<f64>,
+    pub results: Vec<OptResult>,
+}
+
+/// Optimized result of one scan point.
+///
+/// - `value`: Value of the scanned coordinate (Angstrom or degree).
+/// - `energy`: Optimized energy in Hartree.
+/// - `geometry`: Optimized coordinates in Angstrom, flattened (natom * 3).
+/// - `converged`: Whether the constrained optimization converged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanPoint {
+    pub value: f64,
+    pub energy: f64,
+    pub geometry: Vec<f64>,
+    pub converged: bool,
+}
+
+impl ScanResult {
+    /// Optimized value, energy and geometry of each scan point, in order of
+    /// scan. Points without any frame are skipped.
+    pub fn points(&self) -> Vec<ScanPoint> {
+        self.values
+            .iter()
+            .zip(&self.results)
+            .filter_map(|(&value, result)| {
+                Some(ScanPoint {
+                    value,
+                    energy: result.final_energy()?,
+                    geometry: result.final_coords()?.to_vec(),
+                    converged: result.converged,
+                })
+            })
+            .collect()
+    }
+
+    /// Scan value and final energy (Hartree) of each scan point.
+    pub fn energy_profile(&self) -> Vec<(f64, f64)> {
+        self.values
+            .iter()
+            .zip(&self.results)
+            .filter_map(|(&value, result)| Some((value, result.final_energy()?)))
+            .collect()
+    }
+
+    /// Scan value and result of the lowest-energy scan point.
+    pub fn lowest_energy_point(&self) -> Option<(f64, &OptResult)> {
+        self.values
+            .iter()
+            .zip(&self.results)
+            .filter(|(_, result)| result.final_energy().is_some())
+            .min_by(|(_, r1), (_, r2)| {
+                r1.final_energy().unwrap().total_cmp(&r2.final_energy().unwrap())
+            })
+            .map(|(&value, result)| (value, result))
+    }
+}
+
+/// Relaxed scan: optimize the molecule with `coord` constrained to each of
+/// `values` in turn.
+///
+/// Each scan point starts from the optimized geometry of the previous point
+/// (the first from the molecule's geometry). Constraints in `options` are kept
+/// at all scan points, together with the scanned coordinate.
+pub fn run_scan(
+    molecule: &Molecule,
+    driver: &PyGeomDriver,
+    params: &Py<PyDict>,
+    coord: ConstraintCoord,
+    values: &[f64],
+    options: &RunOptions,
+) -> Result<ScanResult, GeometricError> {
+    let mut options = options.clone();
+    let constraints = options.constraints.take().unwrap_or_default();
+    let mut results: Vec<OptResult> = vec![];
+    for &value in values {
+        options.constraints = Some(constraints.clone().set(coord, value));
+        if let Some(coords) = results.last().and_then(|r| r.final_coords()) {
+            options.initial_coords = Some(coords.to_vec());
+        }
+        let custom_engine = init_pyo3_engine(&molecule.pyobj, driver)?;
+        let outcome = run_optimization_with_options(custom_engine, params, None, &options)?;
+        results.push(outcome.into_result());
+    }
+    Ok(ScanResult { coord, values: values.to_vec(), results })
+}
+
+/// Relaxed scan over the points of a `$scan` specification, see [`run_scan`].
+///
+/// Unlike passing the scan to geomeTRIC in [`Constraints`], this gives the
+/// result of every scan point.
+///
+/// [`Constraints`]: crate::constraints::Constraints
+pub fn run_scan_spec(
+    molecule: &Molecule,
+    driver: &PyGeomDriver,
+    params: &Py<PyDict>,
+    spec: &ScanSpec,
+    options: &RunOptions,
+) -> Result<Vec<ScanPoint>, GeometricError> {
+    Ok(run_scan(molecule, driver, params, spec.coord, &spec.values(), options)?.points())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_energy_profile() {
+        let point = |energy: f64| {
+            OptResult::new(vec!["H".into(), "H".into()], vec![vec![0.0; 6]], vec![energy])
+        };
+        let scan = ScanResult {
+            coord: ConstraintCoord::Distance(0, 1),
+            values: vec![0.6, 0.7, 0.8],
+            results: vec![point(-1.10), point(-1.17), point(-1.15)],
+        };
+        assert_eq!(scan.energy_profile(), vec![(0.6, -1.10), (0.7, -1.17), (0.8, -1.15)]);
+        assert_eq!(scan.lowest_energy_point().map(|(value, _)| value), Some(0.7));
+        let points = scan.points();
+        assert_eq!(points.len(), 3);
+        assert_eq!((points[1].value, points[1].energy), (0.7, -1.17));
+
+        let spec =
+            ScanSpec { coord: ConstraintCoord::Distance(0, 1), start: 0.6, end: 0.8, steps: 3 };
+        let values = spec.values();
+        assert!(values.iter().zip([0.6, 0.7, 0.8]).all(|(a, b)| (a - b).abs() < 1.0e-12));
+    }
+}
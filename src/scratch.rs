@@ -0,0 +1,126 @@
+//! Scratch directories of driver evaluations.
+//!
+//! geomeTRIC passes a `dirname` to every `calc_new`, but does not create or
+//! clean it. With a [`ScratchManager`] set in
+//! [`RunOptions::scratch`](crate::optimize::RunOptions), the engine instead
+//! creates one subdirectory per evaluation under a configurable root, passes
+//! its path to the driver as `dirname`, and removes it afterwards according to
+//! a [`ScratchRetention`] policy.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Which scratch directories are kept after evaluations.
+///
+/// Directories of failed evaluations are always kept for inspection.
+///
+/// - `KeepAll`: Keep every directory.
+/// - `KeepLast(n)`: Keep the `n` most recent directories of successful
+///   evaluations.
+/// - `KeepFailed`: Remove directories of successful evaluations right away
+///   (default).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScratchRetention {
+    KeepAll,
+    KeepLast(usize),
+    #[default]
+    KeepFailed,
+}
+
+/// Creates per-evaluation scratch directories under a root directory.
+///
+/// Directories are named by kind and evaluation index, e.g. `step_0000`,
+/// `step_0001`, `hessian_0002`, so they sort in order of evaluation. The root
+/// and existing directories are reused.
+#[derive(Debug, Clone)]
+pub struct ScratchManager {
+    root: PathBuf,
+    retention: ScratchRetention,
+    /// Number of directories created so far.
+    count: usize,
+    /// Directories of successful evaluations not yet removed, oldest first.
+    kept: VecDeque<PathBuf>,
+}
+
+impl ScratchManager {
+    /// Manager creating directories under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ScratchManager {
+            root: root.into(),
+            retention: ScratchRetention::default(),
+            count: 0,
+            kept: VecDeque::new(),
+        }
+    }
+
+    /// Retention policy of directories.
+    pub fn retention(mut self, retention: ScratchRetention) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Manager of the same policy, with root `<root>/<name>`; used to separate
+    /// runs sharing one manager configuration.
+    pub fn subdirectory(&self, name: &str) -> Self {
+        ScratchManager::new(self.root.join(name)).retention(self.retention)
+    }
+
+    /// Create the directory of the next evaluation, e.g. `<root>/step_0003`.
+    pub fn create(&mut self, kind: &str) -> io::Result<PathBuf> {
+        let dir = self.root.join(format!("{}_{:04}", kind, self.count));
+        fs::create_dir_all(&dir)?;
+        self.count += 1;
+        Ok(dir)
+    }
+
+    /// Apply the retention policy after the evaluation in `dir` finished.
+    pub fn release(&mut self, dir: PathBuf, success: bool) -> io::Result<()> {
+        if !success {
+            return Ok(());
+        }
+        self.kept.push_back(dir);
+        let keep = match self.retention {
+            ScratchRetention::KeepAll => return Ok(()),
+            ScratchRetention::KeepLast(n) => n,
+            ScratchRetention::KeepFailed => 0,
+        };
+        while self.kept.len() > keep {
+            let dir = self.kept.pop_front().unwrap();
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scratch_retention() {
+        let root = TempDir::new().unwrap();
+        let mut scratch = ScratchManager::new(root.path()).retention(ScratchRetention::KeepLast(1));
+        let step0 = scratch.create("step").unwrap();
+        scratch.release(step0.clone(), true).unwrap();
+        let step1 = scratch.create("step").unwrap();
+        scratch.release(step1.clone(), false).unwrap();
+        let step2 = scratch.create("step").unwrap();
+        scratch.release(step2.clone(), true).unwrap();
+        let hessian = scratch.create("hessian").unwrap();
+        scratch.release(hessian.clone(), true).unwrap();
+
+        assert_eq!(hessian, root.path().join("hessian_0003"));
+        assert!(!step0.exists());
+        assert!(step1.exists());
+        assert!(!step2.exists());
+        assert!(hessian.exists());
+    }
+}
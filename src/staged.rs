@@ -0,0 +1,119 @@
+//! Multi-stage optimization protocols.
+//!
+//! Common protocols optimize a system in stages with different constraints,
+//! e.g. relax hydrogens first with heavy atoms frozen, then relax everything.
+
+use crate::constraints::Constraints;
+use crate::engine::init_pyo3_engine;
+use crate::error::GeometricError;
+use crate::interface::PyGeomDriver;
+use crate::molecule::Molecule;
+use crate::optimize::{run_optimization_with_options, RunOptions};
+use crate::result::OptimizationOutcome;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// One stage of [`StagedOptimization`].
+///
+/// - `params`: Parameters of geomeTRIC for this stage.
+/// - `options`: Options of this stage (e.g. `constraints`). `initial_coords` is
+///   overridden by the previous stage's final geometry, except for the first
+///   stage.
+pub struct Stage {
+    pub params: Py<PyDict>,
+    pub options: RunOptions,
+}
+
+/// Builder of multi-stage optimizations.
+///
+/// All stages use the same driver and molecule (so the same metadata); each
+/// stage starts from the final geometry of the previous one, and only if the
+/// previous one converged.
+pub struct StagedOptimization {
+    molecule: Molecule,
+    driver: PyGeomDriver,
+    stages: Vec<Stage>,
+}
+
+impl StagedOptimization {
+    pub fn new(molecule: Molecule, driver: PyGeomDriver) -> Self {
+        StagedOptimization { molecule, driver, stages: vec![] }
+    }
+
+    /// Add a stage with parameters and options.
+    pub fn stage(mut self, params: Py<PyDict>, options: RunOptions) -> Self {
+        self.stages.push(Stage { params, options });
+        self
+    }
+
+    /// Add a stage with parameters and constraints, other options being
+    /// default.
+    pub fn constrained_stage(self, params: Py<PyDict>, constraints: Constraints) -> Self {
+        let options = RunOptions { constraints: Some(constraints), ..Default::default() };
+        self.stage(params, options)
+    }
+
+    /// Run the stages in order, stopping after a stage that does not converge;
+    /// returns the outcome of each stage run. All stages converged if as many
+    /// outcomes as stages are returned, the last one being converged.
+    pub fn run(&self) -> Result<Vec<OptimizationOutcome>, GeometricError> {
+        run_stages(&self.stages, |stage, options| {
+            let custom_engine = init_pyo3_engine(&self.molecule.pyobj, &self.driver)?;
+            run_optimization_with_options(custom_engine, &stage.params, None, &options)
+        })
+    }
+}
+
+/// Run `stages` by `run_stage`, each with its options starting from the final
+/// geometry of the previous stage, until a stage does not converge.
+fn run_stages(
+    stages: &[Stage],
+    mut run_stage: impl FnMut(&Stage, RunOptions) -> Result<OptimizationOutcome, GeometricError>,
+) -> Result<Vec<OptimizationOutcome>, GeometricError> {
+    let mut outcomes: Vec<OptimizationOutcome> = vec![];
+    for stage in stages {
+        let mut options = stage.options.clone();
+        if outcomes.last().is_some_and(|outcome| !outcome.is_converged()) {
+            break;
+        }
+        if let Some(coords) = outcomes.last().and_then(|r| r.result().final_coords()) {
+            options.initial_coords = Some(coords.to_vec());
+        }
+        outcomes.push(run_stage(stage, options)?);
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::result::OptResult;
+
+    #[test]
+    fn test_stages_start_from_previous() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let types = py.import("types").unwrap();
+            let molecule = types.getattr("SimpleNamespace").unwrap().call0().unwrap();
+            molecule.setattr("elem", vec!["H"]).unwrap();
+            let stages = (0..3)
+                .map(|_| Stage { params: PyDict::new(py).unbind(), options: RunOptions::default() })
+                .collect::<Vec<_>>();
+            let mut starts = vec![];
+            let outcomes = run_stages(&stages, |_, options| {
+                starts.push(options.initial_coords);
+                let x = starts.len() as f64;
+                let result =
+                    OptResult::with_molecule_info(&molecule, vec![vec![x, 0.0, 0.0]], vec![-x])?;
+                // The second stage does not converge, so the third is not run
+                Ok(match starts.len() {
+                    2 => OptimizationOutcome::MaxTime(result),
+                    _ => OptimizationOutcome::Converged(result),
+                })
+            })
+            .unwrap();
+            assert_eq!(outcomes.len(), 2);
+            assert_eq!(starts, vec![None, Some(vec![1.0, 0.0, 0.0])]);
+        });
+    }
+}
@@ -0,0 +1,263 @@
+//! Analytic model potentials for tests of downstream crates.
+//!
+//! All potentials implement [`GeomDriverAPI`] in atomic units (coordinates in
+//! Bohr, energies in Hartree), regardless of elements. Known minima are given
+//! as [`Reference`] geometries, e.g.
+//!
+//! ```ignore
+//! let lj = LennardJones::new(0.01, 6.0);
+//! let reference = lj.tetrahedron();
+//! let outcome = Optimizer::new(&reference.elem(), &perturbed_xyz, lj).run()?;
+//! assert!((outcome.result().final_energy().unwrap() - reference.energy).abs() < 1e-8);
+//! ```
+
+use crate::interface::{DriverError, GeomDriverAPI, GradOutput};
+use crate::result::BOHR2ANG;
+
+/// A known stationary geometry of a model potential.
+///
+/// - `symbols`: Element symbols (only used to build molecules).
+/// - `coords`: Coordinates in Bohr, flattened (natom * 3).
+/// - `energy`: Energy in Hartree.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub symbols: Vec<String>,
+    pub coords: Vec<f64>,
+    pub energy: f64,
+}
+
+impl Reference {
+    fn new(symbol: &str, coords: Vec<f64>, energy: f64) -> Self {
+        Reference { symbols: vec![symbol.to_string(); coords.len() / 3], coords, energy }
+    }
+
+    /// Element symbols as `&str`, as taken by molecule constructors.
+    pub fn elem(&self) -> Vec<&str> {
+        self.symbols.iter().map(String::as_str).collect()
+    }
+
+    /// Coordinates in Angstrom.
+    pub fn xyz(&self) -> Vec<f64> {
+        self.coords.iter().map(|x| x * BOHR2ANG).collect()
+    }
+}
+
+/// Isotropic harmonic well `E = k |x - x0|² / 2` around `center` (Bohr,
+/// flattened natom * 3); its minimum is `center` with zero energy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Harmonic {
+    pub k: f64,
+    pub center: Vec<f64>,
+}
+
+impl GeomDriverAPI for Harmonic {
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        check_natom(coords, self.center.len() / 3)?;
+        let diff = coords.iter().zip(&self.center).map(|(x, x0)| x - x0).collect::<Vec<_>>();
+        let energy = 0.5 * self.k * diff.iter().map(|d| d * d).sum::<f64>();
+        Ok(GradOutput::new(energy, diff.iter().map(|d| self.k * d).collect()))
+    }
+}
+
+/// Pairwise harmonic springs `E = Σ_ij w_ij (r_ij - b_ij)²`, over ordered
+/// pairs as in geomeTRIC's custom engine test.
+///
+/// - `b`: Equilibrium distances (Bohr), natom by natom.
+/// - `w`: Force constants (Hartree/Bohr²), natom by natom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairHarmonic {
+    pub b: Vec<Vec<f64>>,
+    pub w: Vec<Vec<f64>>,
+}
+
+impl PairHarmonic {
+    /// Three-atom model of geomeTRIC's custom engine test (the model of
+    /// example `model_driver`); its transition state has energy 0.32.
+    pub fn three_atom() -> Self {
+        PairHarmonic {
+            b: vec![vec![0.0, 1.8, 1.8], vec![1.8, 0.0, 2.8], vec![1.8, 2.8, 0.0]],
+            w: vec![vec![0.0, 1.0, 1.0], vec![1.0, 0.0, 0.5], vec![1.0, 0.5, 0.0]],
+        }
+    }
+}
+
+impl GeomDriverAPI for PairHarmonic {
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        check_natom(coords, self.b.len())?;
+        // Both orders of each pair contribute.
+        Ok(pairwise(coords, |i, j, r| {
+            let (e_ij, e_ji) = (r - self.b[i][j], r - self.b[j][i]);
+            let energy = self.w[i][j] * e_ij * e_ij + self.w[j][i] * e_ji * e_ji;
+            (energy, 2.0 * (self.w[i][j] * e_ij + self.w[j][i] * e_ji))
+        }))
+    }
+}
+
+/// Lennard-Jones cluster `E = Σ_{i<j} 4ε [(σ/r)¹² - (σ/r)⁶]`.
+///
+/// - `epsilon`: Well depth (Hartree).
+/// - `sigma`: Distance of zero energy (Bohr).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LennardJones {
+    pub epsilon: f64,
+    pub sigma: f64,
+}
+
+impl LennardJones {
+    pub fn new(epsilon: f64, sigma: f64) -> Self {
+        LennardJones { epsilon, sigma }
+    }
+
+    /// Pair distance of the minimum, 2^(1/6) σ.
+    pub fn r_min(&self) -> f64 {
+        2.0_f64.powf(1.0 / 6.0) * self.sigma
+    }
+
+    /// Minimum of the dimer, energy -ε.
+    pub fn dimer(&self) -> Reference {
+        Reference::new("Ar", dimer(self.r_min()), -self.epsilon)
+    }
+
+    /// Minimum of the trimer (equilateral triangle), energy -3ε.
+    pub fn triangle(&self) -> Reference {
+        Reference::new("Ar", triangle(self.r_min()), -3.0 * self.epsilon)
+    }
+
+    /// Minimum of the tetramer (regular tetrahedron), energy -6ε.
+    pub fn tetrahedron(&self) -> Reference {
+        Reference::new("Ar", tetrahedron(self.r_min()), -6.0 * self.epsilon)
+    }
+}
+
+impl GeomDriverAPI for LennardJones {
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        check_natom(coords, coords.len() / 3)?;
+        Ok(pairwise(coords, |_, _, r| {
+            let s6 = (self.sigma / r).powi(6);
+            let energy = 4.0 * self.epsilon * (s6 * s6 - s6);
+            (energy, -24.0 * self.epsilon * (2.0 * s6 * s6 - s6) / r)
+        }))
+    }
+}
+
+/// Morse cluster `E = Σ_{i<j} D [(1 - exp(-a (r - r_e)))² - 1]`, with energy
+/// -D per pair at distance `r_e`.
+///
+/// - `d`: Well depth (Hartree).
+/// - `a`: Range parameter (1/Bohr).
+/// - `r_e`: Equilibrium distance (Bohr).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Morse {
+    pub d: f64,
+    pub a: f64,
+    pub r_e: f64,
+}
+
+impl Morse {
+    pub fn new(d: f64, a: f64, r_e: f64) -> Self {
+        Morse { d, a, r_e }
+    }
+
+    /// Minimum of the dimer, energy -D.
+    pub fn dimer(&self) -> Reference {
+        Reference::new("H", dimer(self.r_e), -self.d)
+    }
+
+    /// Minimum of the trimer (equilateral triangle), energy -3D.
+    pub fn triangle(&self) -> Reference {
+        Reference::new("H", triangle(self.r_e), -3.0 * self.d)
+    }
+}
+
+impl GeomDriverAPI for Morse {
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        check_natom(coords, coords.len() / 3)?;
+        Ok(pairwise(coords, |_, _, r| {
+            let x = (-self.a * (r - self.r_e)).exp();
+            (self.d * ((1.0 - x).powi(2) - 1.0), 2.0 * self.d * self.a * x * (1.0 - x))
+        }))
+    }
+}
+
+/// Check that coordinates are of `natom` atoms.
+fn check_natom(coords: &[f64], natom: usize) -> Result<(), DriverError> {
+    if coords.len() != natom * 3 || natom == 0 {
+        return Err(DriverError::CalculationFailed(format!(
+            "Length of coordinates ({}) does not match number of atoms ({}) * 3",
+            coords.len(),
+            natom
+        )));
+    }
+    Ok(())
+}
+
+/// Energy and gradient of a sum of pair potentials; `pair(i, j, r)` gives the
+/// energy and its derivative by `r` of atoms `i < j` at distance `r`.
+fn pairwise(coords: &[f64], mut pair: impl FnMut(usize, usize, f64) -> (f64, f64)) -> GradOutput {
+    let natom = coords.len() / 3;
+    let mut energy = 0.0;
+    let mut gradient = vec![0.0; coords.len()];
+    for i in 0..natom {
+        for j in i + 1..natom {
+            let dr = [0, 1, 2].map(|k| coords[3 * i + k] - coords[3 * j + k]);
+            let r = dr.iter().map(|d| d * d).sum::<f64>().sqrt();
+            let (e, de_dr) = pair(i, j, r);
+            energy += e;
+            for k in 0..3 {
+                gradient[3 * i + k] += de_dr * dr[k] / r;
+                gradient[3 * j + k] -= de_dr * dr[k] / r;
+            }
+        }
+    }
+    GradOutput::new(energy, gradient)
+}
+
+fn dimer(r: f64) -> Vec<f64> {
+    vec![0.0, 0.0, 0.0, 0.0, 0.0, r]
+}
+
+fn triangle(r: f64) -> Vec<f64> {
+    vec![0.0, 0.0, 0.0, r, 0.0, 0.0, 0.5 * r, 0.75_f64.sqrt() * r, 0.0]
+}
+
+fn tetrahedron(r: f64) -> Vec<f64> {
+    // Alternate vertices of a cube of edge r / √2
+    let h = 0.5 * r / 2.0_f64.sqrt();
+    vec![h, h, h, h, -h, -h, -h, h, -h, -h, -h, h]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradcheck::check_gradient_at;
+
+    #[test]
+    fn test_model_potentials() {
+        let lj = LennardJones::new(0.01, 6.0);
+        let morse = Morse::new(0.1, 1.0, 2.5);
+        let cases: Vec<(Box<dyn GeomDriverAPI>, Reference)> = vec![
+            (Box::new(lj), lj.dimer()),
+            (Box::new(lj), lj.triangle()),
+            (Box::new(lj), lj.tetrahedron()),
+            (Box::new(morse), morse.dimer()),
+            (Box::new(morse), morse.triangle()),
+        ];
+        for (mut driver, reference) in cases {
+            let output = driver.calc_new(&reference.coords, "").unwrap();
+            assert!((output.energy - reference.energy).abs() < 1e-12);
+            assert!(output.gradient.iter().all(|g| g.abs() < 1e-12));
+            // Away from the minimum
+            let coords = reference.coords.iter().enumerate().map(|(i, x)| x + 0.01 * i as f64);
+            let coords = coords.collect::<Vec<_>>();
+            assert!(check_gradient_at(&mut *driver, &coords, "", 1e-4, 1e-7).unwrap().passed());
+        }
+
+        let mut model = PairHarmonic::three_atom();
+        let coords = [0.0, 0.6, 0.0, 1.7, 1.5, 0.0, -1.7, 0.9, 0.0];
+        assert!(check_gradient_at(&mut model, &coords, "", 1e-4, 1e-7).unwrap().passed());
+        let mut harmonic = Harmonic { k: 0.5, center: coords.to_vec() };
+        assert_eq!(harmonic.calc_new(&coords, "").unwrap().energy, 0.0);
+        assert!(check_gradient_at(&mut harmonic, &[0.0; 9], "", 1e-4, 1e-7).unwrap().passed());
+        assert!(harmonic.calc_new(&[0.0; 6], "").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+//! Export of optimization trajectories.
+//!
+//! [`OptResult::write_xyz_trajectory`] writes a multi-frame XYZ file for
+//! visualization (e.g. VMD, Avogadro). [`OptResult::write_trajectory_binary`]
+//! writes a compact binary format, which is far more efficient than XYZ or
+//! JSON for large systems over many steps.
+//!
+//! # Binary format (version 1)
+//!
+//! All values are little-endian.
+//!
+//! | Field         | Type                           |
+//! |---------------|--------------------------------|
+//! | magic         | `b"GTRJ"`                      |
+//! | version       | `u32` (= 1)                    |
+//! | natom, nframe | `u32`, `u32`                   |
+//! | charge, mult  | `u8` flag (0/1) + `i64`, each  |
+//! | elem          | natom × (`u8` length + UTF-8)  |
+//! | energies      | nframe × `f64`                 |
+//! | frames        | nframe × natom × 3 × `f64`     |
+//!
+//! Coordinates are in Angstrom and energies in Hartree, as in [`OptResult`].
+//! Gradients, energy components, run id, atom permutation, coordinate system,
+//! convergence flag and log are not stored.
+
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::result::OptResult;
+
+/// Magic bytes of the binary trajectory format.
+const MAGIC: &[u8; 4] = b"GTRJ";
+
+/// Version of the binary trajectory format.
+pub const TRAJECTORY_FORMAT_VERSION: u32 = 1;
+
+impl OptResult {
+    /// Trajectory as multi-frame XYZ (Angstrom), with the step index and
+    /// energy (Hartree) of each frame in the comment line.
+    ///
+    /// The format can be read back by
+    /// [`parse_xyz`](crate::molecule::parse_xyz).
+    pub fn to_xyz_string(&self) -> String {
+        let mut xyz_str = String::new();
+        for (step, xyz) in self.xyzs.iter().enumerate() {
+            writeln!(xyz_str, "{}", self.elem.len()).unwrap();
+            match self.energies.get(step) {
+                Some(energy) => writeln!(xyz_str, "Step {} Energy {:.10}", step, energy).unwrap(),
+                None => writeln!(xyz_str, "Step {}", step).unwrap(),
+            }
+            for (elem, coord) in self.elem.iter().zip(xyz.chunks(3)) {
+                writeln!(
+                    xyz_str,
+                    "{:<3} {:>15.10} {:>15.10} {:>15.10}",
+                    elem, coord[0], coord[1], coord[2]
+                )
+                .unwrap();
+            }
+        }
+        xyz_str
+    }
+
+    /// Write the trajectory to `path` as multi-frame XYZ, see
+    /// [`to_xyz_string`](Self::to_xyz_string).
+    pub fn write_xyz_trajectory(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_xyz_string())
+    }
+
+    /// Write the trajectory to `path` in the compact binary format (see
+    /// [module documentation](crate::trajectory)).
+    pub fn write_trajectory_binary(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let natom = self.elem.len();
+        if self.xyzs.len() != self.energies.len() || self.xyzs.iter().any(|x| x.len() != natom * 3)
+        {
+            return Err(invalid_data("Inconsistent numbers of frames, energies or atoms"));
+        }
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&TRAJECTORY_FORMAT_VERSION.to_le_bytes())?;
+        writer.write_all(&(natom as u32).to_le_bytes())?;
+        writer.write_all(&(self.xyzs.len() as u32).to_le_bytes())?;
+        for value in [self.charge, self.mult] {
+            writer.write_all(&[value.is_some() as u8])?;
+            writer.write_all(&value.unwrap_or(0).to_le_bytes())?;
+        }
+        for elem in &self.elem {
+            let len = u8::try_from(elem.len())
+                .map_err(|_| invalid_data(format!("Element symbol too long: {}", elem)))?;
+            writer.write_all(&[len])?;
+            writer.write_all(elem.as_bytes())?;
+        }
+        for value in self.energies.iter().chain(self.xyzs.iter().flatten()) {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.flush()
+    }
+}
+
+/// Read a trajectory written by [`OptResult::write_trajectory_binary`].
+pub fn read_trajectory_binary(path: impl AsRef<Path>) -> io::Result<OptResult> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("Not a binary trajectory file"));
+    }
+    let version = read_u32(&mut reader)?;
+    if version != TRAJECTORY_FORMAT_VERSION {
+        return Err(invalid_data(format!("Unsupported trajectory format version {}", version)));
+    }
+    let natom = read_u32(&mut reader)? as usize;
+    let nframe = read_u32(&mut reader)? as usize;
+    let mut optional = || -> io::Result<Option<i64>> {
+        let mut flag = [0u8; 1];
+        reader.read_exact(&mut flag)?;
+        let mut value = [0u8; 8];
+        reader.read_exact(&mut value)?;
+        Ok((flag[0] != 0).then_some(i64::from_le_bytes(value)))
+    };
+    let charge = optional()?;
+    let mult = optional()?;
+    let elem = (0..natom)
+        .map(|_| {
+            let mut len = [0u8; 1];
+            reader.read_exact(&mut len)?;
+            let mut bytes = vec![0u8; len[0] as usize];
+            reader.read_exact(&mut bytes)?;
+            String::from_utf8(bytes).map_err(invalid_data)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    let energies = read_f64s(&mut reader, nframe)?;
+    let xyzs = (0..nframe).map(|_| read_f64s(&mut reader, natom * 3)).collect::<io::Result<_>>()?;
+    Ok(OptResult { charge, mult, ..OptResult::new(elem, xyzs, energies) })
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_f64s(reader: &mut impl Read, len: usize) -> io::Result<Vec<f64>> {
+    let mut bytes = vec![0u8; len * 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect())
+}
+
+fn invalid_data<E>(err: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trajectory_binary_roundtrip() {
+        let elem = vec!["O".into(), "H".into(), "H".into()];
+        let xyzs = vec![vec![0.0, 0.0, 0.0, 0.0, 0.76, 0.59, 0.0, -0.76, 0.59], vec![
+            0.0, 0.0, 0.01, 0.0, 0.75, 0.58, 0.0, -0.75, 0.58,
+        ]];
+        let result =
+            OptResult { charge: Some(0), ..OptResult::new(elem, xyzs, vec![-76.0, -76.01]) };
+        let file = tempfile::NamedTempFile::new().unwrap();
+        result.write_trajectory_binary(file.path()).unwrap();
+        assert_eq!(read_trajectory_binary(file.path()).unwrap(), result);
+
+        pyo3::prepare_freethreaded_python();
+        let xyz = crate::molecule::parse_xyz(&result.to_xyz_string()).unwrap();
+        assert_eq!(xyz.elem, result.elem);
+        assert_eq!(xyz.xyzs, result.xyzs);
+        assert_eq!(xyz.comments[1], "Step 1 Energy -76.0100000000");
+    }
+}
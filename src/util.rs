@@ -78,6 +78,112 @@ pub fn tomlstr2py(toml_str: &str) -> PyResult<Py<PyDict>> {
     toml2py(&value)
 }
 
+/// Convert `serde_json::Value` to `PyObject`.
+///
+/// JSON `null` becomes `None`; integers that do not fit `i64` or `u64` are
+/// converted to float.
+pub fn json2py_val_with_bound<'py>(
+    py: Python<'py>,
+    value: &serde_json::Value,
+) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        serde_json::Value::Null => Ok(py.None().into_bound(py)),
+        serde_json::Value::Bool(b) => Ok(b.into_pyobject(py)?.to_owned().into_any()),
+        serde_json::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(i.into_pyobject(py)?.into_any())
+            } else if let Some(u) = n.as_u64() {
+                Ok(u.into_pyobject(py)?.into_any())
+            } else {
+                Ok(n.as_f64().unwrap_or(f64::NAN).into_pyobject(py)?.into_any())
+            }
+        },
+        serde_json::Value::String(s) => Ok(s.into_pyobject(py)?.into_any()),
+        serde_json::Value::Array(arr) => {
+            let py_list = PyList::empty(py);
+            for item in arr {
+                py_list.append(json2py_val_with_bound(py, item)?)?;
+            }
+            Ok(py_list.into_any())
+        },
+        serde_json::Value::Object(map) => {
+            let py_dict = PyDict::new(py);
+            for (key, value) in map.iter() {
+                py_dict.set_item(key, json2py_val_with_bound(py, value)?)?;
+            }
+            Ok(py_dict.into_any())
+        },
+    }
+}
+
+/// Difference of one parameter between two parameter dictionaries.
+///
+/// `key` is the dotted path of the parameter (e.g. `"outer.inner"` for nested
+/// tables), and values are given by their python `repr`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParamDiff {
+    Added { key: String, new: String },
+    Removed { key: String, old: String },
+    Changed { key: String, old: String, new: String },
+}
+
+impl ParamDiff {
+    /// Dotted path of the parameter.
+    pub fn key(&self) -> &str {
+        match self {
+            ParamDiff::Added { key, .. } => key,
+            ParamDiff::Removed { key, .. } => key,
+            ParamDiff::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// Compare two parameter dictionaries and report differences from `a` to `b`.
+///
+/// Nested dictionaries are compared recursively. Differences are sorted by
+/// key.
+pub fn diff_params(a: &Py<PyDict>, b: &Py<PyDict>) -> PyResult<Vec<ParamDiff>> {
+    Python::with_gil(|py| {
+        let mut diffs = vec![];
+        diff_params_with_bound(a.bind(py), b.bind(py), "", &mut diffs)?;
+        diffs.sort_by(|d1, d2| d1.key().cmp(d2.key()));
+        Ok(diffs)
+    })
+}
+
+fn diff_params_with_bound(
+    a: &Bound<'_, PyDict>,
+    b: &Bound<'_, PyDict>,
+    prefix: &str,
+    diffs: &mut Vec<ParamDiff>,
+) -> PyResult<()> {
+    let repr = |value: &Bound<'_, PyAny>| -> PyResult<String> { Ok(value.repr()?.to_string()) };
+    for (key, old) in a.iter() {
+        let key_str = format!("{}{}", prefix, key.str()?);
+        match b.get_item(&key)? {
+            None => diffs.push(ParamDiff::Removed { key: key_str, old: repr(&old)? }),
+            Some(new) => match (old.downcast::<PyDict>(), new.downcast::<PyDict>()) {
+                (Ok(old), Ok(new)) => {
+                    diff_params_with_bound(old, new, &format!("{}.", key_str), diffs)?
+                },
+                _ if old.eq(&new)? => (),
+                _ => diffs.push(ParamDiff::Changed {
+                    key: key_str,
+                    old: repr(&old)?,
+                    new: repr(&new)?,
+                }),
+            },
+        }
+    }
+    for (key, new) in b.iter() {
+        if !a.contains(&key)? {
+            let key_str = format!("{}{}", prefix, key.str()?);
+            diffs.push(ParamDiff::Added { key: key_str, new: repr(&new)? });
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,4 +235,34 @@ mod tests {
             println!("Converted TOML to PyObject: {:?}", dict);
         });
     }
+
+    #[test]
+    fn test_diff_params() {
+        pyo3::prepare_freethreaded_python();
+
+        let a = tomlstr2py("maxiter = 300\ncoordsys = \"tric\"\n[nested]\nx = 1").unwrap();
+        let b = tomlstr2py("maxiter = 500\ntransition = true\n[nested]\nx = 2").unwrap();
+        let diffs = diff_params(&a, &b).unwrap();
+        assert_eq!(diffs, vec![
+            ParamDiff::Removed { key: "coordsys".into(), old: "'tric'".into() },
+            ParamDiff::Changed { key: "maxiter".into(), old: "300".into(), new: "500".into() },
+            ParamDiff::Changed { key: "nested.x".into(), old: "1".into(), new: "2".into() },
+            ParamDiff::Added { key: "transition".into(), new: "True".into() },
+        ]);
+        assert!(diff_params(&a, &a).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_json2py() {
+        pyo3::prepare_freethreaded_python();
+
+        let value = serde_json::json!({"dipole": [0.1, -0.2, 3], "s2": null, "ok": true});
+        Python::with_gil(|py| {
+            let obj = json2py_val_with_bound(py, &value).unwrap();
+            assert_eq!(
+                obj.repr().unwrap().to_string(),
+                "{'dipole': [0.1, -0.2, 3], 'ok': True, 's2': None}"
+            );
+        });
+    }
 }
@@ -0,0 +1,118 @@
+//! Detection of the installed geomeTRIC version.
+//!
+//! Features of this crate that wrap newer geomeTRIC modules check the version
+//! first, and fail with [`GeometricError::Unsupported`] instead of an obscure
+//! python error.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use crate::error::GeometricError;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+
+create_exception!(
+    geometric_pyo3,
+    UnsupportedGeometricVersion,
+    PyException,
+    "Raised when a feature is not available in the installed geomeTRIC version."
+);
+
+/// Release version of geomeTRIC (`major.minor.patch`).
+///
+/// Further components (e.g. `0.9.7.2`) and local or pre-release suffixes are
+/// ignored; missing components are zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Version { major, minor, patch }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl FromStr for Version {
+    type Err = PyErr;
+
+    fn from_str(s: &str) -> PyResult<Self> {
+        let release = s.trim().split(['+', '-']).next().unwrap_or_default();
+        let mut numbers = vec![];
+        for component in release.split('.').take(3) {
+            let digits = component.chars().take_while(|c| c.is_ascii_digit()).collect::<String>();
+            match digits.parse::<u32>() {
+                Ok(number) => numbers.push(number),
+                Err(_) => break,
+            }
+            // Pre-release suffix (e.g. `1.1rc1`) ends the release numbers
+            if digits.len() != component.len() {
+                break;
+            }
+        }
+        if numbers.is_empty() {
+            return Err(PyValueError::new_err(format!("Invalid version string: {:?}", s)));
+        }
+        numbers.resize(3, 0);
+        Ok(Version::new(numbers[0], numbers[1], numbers[2]))
+    }
+}
+
+/// geomeTRIC version providing `geometric.neb`.
+pub const NEB_MIN_VERSION: Version = Version::new(1, 1, 0);
+
+/// geomeTRIC version providing IRC (`irc` keyword).
+pub const IRC_MIN_VERSION: Version = Version::new(1, 1, 0);
+
+/// Version of the installed geomeTRIC (`geometric.__version__`), detected once
+/// per process.
+pub fn geometric_version() -> Result<Version, GeometricError> {
+    static VERSION: OnceLock<Version> = OnceLock::new();
+    if let Some(version) = VERSION.get() {
+        return Ok(*version);
+    }
+    let version = Python::with_gil(|py| -> PyResult<Version> {
+        py.import("geometric")?.getattr("__version__")?.extract::<String>()?.parse()
+    })?;
+    Ok(*VERSION.get_or_init(|| version))
+}
+
+/// Check that the installed geomeTRIC provides `feature`.
+pub(crate) fn require_geometric(feature: &str, min: Version) -> Result<(), GeometricError> {
+    let version = geometric_version()?;
+    if version < min {
+        return Err(GeometricError::Unsupported(UnsupportedGeometricVersion::new_err(format!(
+            "{} requires geomeTRIC >= {}, found {}",
+            feature, min, version
+        ))));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let parse = |s: &str| s.parse::<Version>().unwrap();
+        assert_eq!(parse("1.0"), Version::new(1, 0, 0));
+        assert_eq!(parse("0.9.7.2"), Version::new(0, 9, 7));
+        assert_eq!(parse("1.1+3.gabc1234"), Version::new(1, 1, 0));
+        assert_eq!(parse("1.1rc1"), Version::new(1, 1, 0));
+        assert!(parse("0.9.7.2") < NEB_MIN_VERSION);
+        assert!(parse("1.1.0") >= NEB_MIN_VERSION);
+        pyo3::prepare_freethreaded_python();
+        assert!("unknown".parse::<Version>().is_err());
+    }
+}
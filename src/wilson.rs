@@ -0,0 +1,349 @@
+//! Primitive internal coordinates and Wilson B-matrix evaluated in Rust.
+//!
+//! For cheap drivers (e.g. ML potentials), geomeTRIC's per-primitive python
+//! evaluation of internal coordinates and their derivatives dominates the wall
+//! time of large systems. After [`install_native_coords`], geomeTRIC's
+//! `PrimitiveInternalCoordinates.calculate` and `derivatives` (the rows of the
+//! Wilson B-matrix) evaluate distances, angles, dihedrals and out-of-plane
+//! angles in Rust, with the same conventions as geomeTRIC; other primitives
+//! (translations, rotations, Cartesians, linear angles) are still evaluated by
+//! geomeTRIC.
+
+use crate::pyarray::{PyArray1, PyArrayMethods, PyReadonlyArrayDyn};
+use pyo3::prelude::*;
+use pyo3::types::{PyList, PyTuple};
+
+/// Attribute of `PrimitiveInternalCoordinates` keeping the original methods
+/// while the native evaluation is installed.
+const ORIGINAL_ATTR: &str = "_geometric_pyo3_original";
+
+/// Attribute of a `PrimitiveInternalCoordinates` instance caching its
+/// primitives converted to [`Primitive`].
+const CACHE_ATTR: &str = "_geometric_pyo3_primitives";
+
+/// A primitive internal coordinate evaluated natively, with 0-based atoms in
+/// geomeTRIC's order.
+///
+/// Values are in Bohr or radians, as geomeTRIC's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Primitive {
+    Distance(usize, usize),
+    /// Angle `a-b-c` at atom `b`.
+    Angle(usize, usize, usize),
+    Dihedral(usize, usize, usize, usize),
+    /// Out-of-plane angle of geomeTRIC, defined like a dihedral.
+    OutOfPlane(usize, usize, usize, usize),
+}
+
+impl Primitive {
+    /// Primitive of a geomeTRIC coordinate object, if supported.
+    fn from_py(prim: &Bound<'_, PyAny>) -> PyResult<Option<Self>> {
+        let class_name = prim.get_type().getattr("__name__")?.extract::<String>()?;
+        let atom = |attr: &str| prim.getattr(attr)?.extract::<usize>();
+        Ok(match class_name.as_str() {
+            "Distance" => Some(Primitive::Distance(atom("a")?, atom("b")?)),
+            "Angle" => Some(Primitive::Angle(atom("a")?, atom("b")?, atom("c")?)),
+            "Dihedral" => Some(Primitive::Dihedral(atom("a")?, atom("b")?, atom("c")?, atom("d")?)),
+            "OutOfPlane" => {
+                Some(Primitive::OutOfPlane(atom("a")?, atom("b")?, atom("c")?, atom("d")?))
+            },
+            _ => None,
+        })
+    }
+
+    /// Value at coordinates `coords` (Bohr, flattened natom * 3).
+    pub fn value(&self, coords: &[f64]) -> f64 {
+        let x = |i: usize| [coords[3 * i], coords[3 * i + 1], coords[3 * i + 2]];
+        match *self {
+            Primitive::Distance(a, b) => norm(sub(x(a), x(b))),
+            Primitive::Angle(a, b, c) => {
+                let u = unit(sub(x(a), x(b)));
+                let v = unit(sub(x(c), x(b)));
+                dot(u, v).clamp(-1.0, 1.0).acos()
+            },
+            Primitive::Dihedral(a, b, c, d) | Primitive::OutOfPlane(a, b, c, d) => {
+                let vec1 = sub(x(b), x(a));
+                let vec2 = sub(x(c), x(b));
+                let vec3 = sub(x(d), x(c));
+                let cross1 = cross(vec2, vec3);
+                let cross2 = cross(vec1, vec2);
+                (dot(vec1, cross1) * norm(vec2)).atan2(dot(cross1, cross2))
+            },
+        }
+    }
+
+    /// Derivatives by the Cartesian coordinates, added to `row` (flattened
+    /// natom * 3).
+    fn add_derivative(&self, coords: &[f64], row: &mut [f64]) {
+        let x = |i: usize| [coords[3 * i], coords[3 * i + 1], coords[3 * i + 2]];
+        let mut add = |i: usize, d: [f64; 3]| (0..3).for_each(|k| row[3 * i + k] += d[k]);
+        match *self {
+            Primitive::Distance(a, b) => {
+                let u = unit(sub(x(a), x(b)));
+                add(a, u);
+                add(b, scale(u, -1.0));
+            },
+            Primitive::Angle(a, b, c) => {
+                let (u, v) = (sub(x(a), x(b)), sub(x(c), x(b)));
+                let (lu, lv) = (norm(u), norm(v));
+                let (u, v) = (scale(u, 1.0 / lu), scale(v, 1.0 / lv));
+                // Any normal vector for (near-)linear angles, as geomeTRIC
+                let w = match norm(add3(u, v)) < 1e-10 || norm(sub(u, v)) < 1e-10 {
+                    true => {
+                        let (vector1, vector2) = ([1.0, -1.0, 1.0], [-1.0, 1.0, 1.0]);
+                        match norm(add3(u, vector1)) < 1e-10 || norm(sub(u, vector1)) < 1e-10 {
+                            true => vector2,
+                            false => vector1,
+                        }
+                    },
+                    false => cross(u, v),
+                };
+                let w = unit(w);
+                let term1 = scale(cross(u, w), 1.0 / lu);
+                let term2 = scale(cross(w, v), 1.0 / lv);
+                add(a, term1);
+                add(c, term2);
+                add(b, scale(add3(term1, term2), -1.0));
+            },
+            Primitive::Dihedral(a, b, c, d) | Primitive::OutOfPlane(a, b, c, d) => {
+                let (u, w, v) = (sub(x(a), x(b)), sub(x(c), x(b)), sub(x(d), x(c)));
+                let (lu, lw, lv) = (norm(u), norm(w), norm(v));
+                let (u, w, v) = (scale(u, 1.0 / lu), scale(w, 1.0 / lw), scale(v, 1.0 / lv));
+                let (cos_u, cos_v) = (dot(u, w), dot(v, w));
+                // Terms of (near-)linear angles are dropped, as geomeTRIC
+                let (term1, term3) = match 1.0 - cos_u * cos_u < 1e-6 {
+                    true => ([0.0; 3], [0.0; 3]),
+                    false => {
+                        let sin2 = 1.0 - cos_u * cos_u;
+                        let uw = cross(u, w);
+                        (scale(uw, 1.0 / (lu * sin2)), scale(uw, cos_u / (lw * sin2)))
+                    },
+                };
+                let (term2, term4) = match 1.0 - cos_v * cos_v < 1e-6 {
+                    true => ([0.0; 3], [0.0; 3]),
+                    false => {
+                        let sin2 = 1.0 - cos_v * cos_v;
+                        let vw = cross(v, w);
+                        (scale(vw, 1.0 / (lv * sin2)), scale(vw, cos_v / (lw * sin2)))
+                    },
+                };
+                add(a, term1);
+                add(d, scale(term2, -1.0));
+                add(b, sub(sub(term3, term1), term4));
+                add(c, sub(add3(term2, term4), term3));
+            },
+        }
+    }
+
+    /// Derivatives by the Cartesian coordinates (flattened natom * 3), a row of
+    /// the Wilson B-matrix.
+    pub fn derivative(&self, coords: &[f64]) -> Vec<f64> {
+        let mut row = vec![0.0; coords.len()];
+        self.add_derivative(coords, &mut row);
+        row
+    }
+}
+
+/// Values of primitives at coordinates `coords` (Bohr, flattened natom * 3).
+pub fn primitive_values(prims: &[Primitive], coords: &[f64]) -> Vec<f64> {
+    prims.iter().map(|prim| prim.value(coords)).collect()
+}
+
+/// Wilson B-matrix of primitives at coordinates `coords` (Bohr, flattened
+/// natom * 3), flattened nprim by natom * 3.
+pub fn wilson_b(prims: &[Primitive], coords: &[f64]) -> Vec<f64> {
+    let mut b = vec![0.0; prims.len() * coords.len()];
+    if !coords.is_empty() {
+        for (prim, row) in prims.iter().zip(b.chunks_mut(coords.len())) {
+            prim.add_derivative(coords, row);
+        }
+    }
+    b
+}
+
+/// Primitives of a `PrimitiveInternalCoordinates`, converted once per set of
+/// coordinate objects; `None` for primitives left to geomeTRIC.
+#[pyclass]
+struct PrimitiveCache {
+    // Strong references, so that objects are not replaced by others at the
+    // same address.
+    objects: Vec<Py<PyAny>>,
+    prims: Vec<Option<Primitive>>,
+}
+
+/// Coordinate objects and their converted primitives.
+type Primitives<'py> = (Vec<Bound<'py, PyAny>>, Vec<Option<Primitive>>);
+
+/// Coordinate objects of `this` and their converted primitives.
+fn cached_primitives<'py>(this: &Bound<'py, PyAny>) -> PyResult<Primitives<'py>> {
+    let py = this.py();
+    let internals = this.getattr("Internals")?.downcast_into::<PyList>()?;
+    let objects = internals.iter().collect::<Vec<_>>();
+    if let Ok(cache) = this.getattr(CACHE_ATTR) {
+        if let Ok(cache) = cache.downcast::<PrimitiveCache>() {
+            let cache = cache.borrow();
+            let valid = cache.objects.len() == objects.len()
+                && cache.objects.iter().zip(&objects).all(|(a, b)| a.bind(py).is(b));
+            if valid {
+                return Ok((objects, cache.prims.clone()));
+            }
+        }
+    }
+    let prims = objects.iter().map(Primitive::from_py).collect::<PyResult<Vec<_>>>()?;
+    let objects_ref = objects.iter().map(|obj| obj.clone().unbind()).collect();
+    this.setattr(CACHE_ATTR, PrimitiveCache { objects: objects_ref, prims: prims.clone() })?;
+    Ok((objects, prims))
+}
+
+/// Flattened coordinates of a numpy array given by geomeTRIC.
+fn flat_coords(xyz: &Bound<'_, PyAny>) -> PyResult<Vec<f64>> {
+    let xyz = xyz.extract::<PyReadonlyArrayDyn<f64>>()?;
+    Ok(xyz.as_array().iter().copied().collect())
+}
+
+/// Replacement of `PrimitiveInternalCoordinates.calculate`.
+#[pyfunction]
+fn native_calculate<'py>(
+    this: &Bound<'py, PyAny>,
+    xyz: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyArray1<f64>>> {
+    let coords = flat_coords(xyz)?;
+    let (objects, prims) = cached_primitives(this)?;
+    let values = objects
+        .iter()
+        .zip(&prims)
+        .map(|(obj, prim)| match prim {
+            Some(prim) => Ok(prim.value(&coords)),
+            None => obj.call_method1("value", (xyz,))?.extract::<f64>(),
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+    Ok(PyArray1::from_vec(this.py(), values))
+}
+
+/// Replacement of `PrimitiveInternalCoordinates.derivatives`, of shape (nprim,
+/// natom, 3).
+#[pyfunction]
+fn native_derivatives<'py>(
+    this: &Bound<'py, PyAny>,
+    xyz: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let coords = flat_coords(xyz)?;
+    let (objects, prims) = cached_primitives(this)?;
+    let ncoord = coords.len();
+    let mut b = vec![0.0; objects.len() * ncoord];
+    if ncoord > 0 {
+        for ((obj, prim), row) in objects.iter().zip(&prims).zip(b.chunks_mut(ncoord)) {
+            match prim {
+                Some(prim) => prim.add_derivative(&coords, row),
+                None => {
+                    row.copy_from_slice(&flat_coords(&obj.call_method1("derivative", (xyz,))?)?)
+                },
+            }
+        }
+    }
+    let shape = [objects.len(), ncoord / 3, 3];
+    Ok(PyArray1::from_vec(this.py(), b).reshape(shape)?.into_any())
+}
+
+/// Evaluate geomeTRIC's primitive internal coordinates and Wilson B-matrix in
+/// Rust (see the [module documentation](self)), process-wide.
+///
+/// Results are the same as geomeTRIC's up to rounding. Calling this again has
+/// no effect.
+pub fn install_native_coords() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let cls = py.import("geometric.internal")?.getattr("PrimitiveInternalCoordinates")?;
+        if cls.hasattr(ORIGINAL_ATTR)? {
+            return Ok(());
+        }
+        let original = PyTuple::new(py, [cls.getattr("calculate")?, cls.getattr("derivatives")?])?;
+        // `partialmethod` binds the instance as the first argument
+        let partialmethod = py.import("functools")?.getattr("partialmethod")?;
+        cls.setattr(ORIGINAL_ATTR, original)?;
+        cls.setattr("calculate", partialmethod.call1((wrap_pyfunction!(native_calculate, py)?,))?)?;
+        cls.setattr(
+            "derivatives",
+            partialmethod.call1((wrap_pyfunction!(native_derivatives, py)?,))?,
+        )?;
+        Ok(())
+    })
+}
+
+/// Restore geomeTRIC's own evaluation replaced by [`install_native_coords`].
+pub fn remove_native_coords() -> PyResult<()> {
+    Python::with_gil(|py| {
+        let cls = py.import("geometric.internal")?.getattr("PrimitiveInternalCoordinates")?;
+        if !cls.hasattr(ORIGINAL_ATTR)? {
+            return Ok(());
+        }
+        let (calculate, derivatives) =
+            cls.getattr(ORIGINAL_ATTR)?.extract::<(PyObject, PyObject)>()?;
+        cls.setattr("calculate", calculate)?;
+        cls.setattr("derivatives", derivatives)?;
+        cls.delattr(ORIGINAL_ATTR)?;
+        Ok(())
+    })
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn add3(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn scale(a: [f64; 3], s: f64) -> [f64; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn unit(a: [f64; 3]) -> [f64; 3] {
+    scale(a, 1.0 / norm(a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wilson_b() {
+        // Hydrogen peroxide (Bohr)
+        let coords = [0.0, 1.37, -0.12, 0.0, -1.37, -0.12, 1.58, 1.75, 0.83, -1.58, -1.75, 0.83];
+        let prims = [
+            Primitive::Distance(0, 1),
+            Primitive::Angle(2, 0, 1),
+            Primitive::Dihedral(2, 0, 1, 3),
+            Primitive::OutOfPlane(0, 1, 2, 3),
+        ];
+        let values = primitive_values(&prims, &coords);
+        assert!((values[0] - 2.74).abs() < 1e-12);
+        assert!(values[2].abs() > 1.0 && values[2].abs() < std::f64::consts::PI);
+
+        // Finite differences of values
+        let b = wilson_b(&prims, &coords);
+        let h = 1e-5;
+        for (i, prim) in prims.iter().enumerate() {
+            for k in 0..coords.len() {
+                let mut displaced = coords;
+                displaced[k] += h;
+                let plus = prim.value(&displaced);
+                displaced[k] -= 2.0 * h;
+                let minus = prim.value(&displaced);
+                let numerical = (plus - minus) / (2.0 * h);
+                assert!((b[i * coords.len() + k] - numerical).abs() < 1e-7, "{:?} {}", prim, k);
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Native GFN-xTB driver (feature `xtb`).
+//!
+//! [`XtbDriver`] computes energies and gradients through the C API of the
+//! [xtb](https://github.com/grimme-lab/xtb) library, without python, which is
+//! handy for pre-optimization and testing. The library `libxtb` must be
+//! installed; it is located by pkg-config (`xtb.pc`) if available, or else
+//! searched by the linker in its default paths.
+
+use std::ffi::{c_char, c_double, c_int, CStr};
+use std::ptr;
+
+use crate::interface::{AtomData, DriverError, GeomDriverAPI, GradOutput};
+
+/// Bindings of the xtb C API (`xtb.h`).
+mod ffi {
+    use std::ffi::{c_char, c_double, c_int, c_void};
+
+    pub type Environment = *mut c_void;
+    pub type Molecule = *mut c_void;
+    pub type Calculator = *mut c_void;
+    pub type Results = *mut c_void;
+
+    /// `XTB_VERBOSITY_MUTED`.
+    pub const VERBOSITY_MUTED: c_int = 0;
+
+    // Linked by the build script.
+    extern "C" {
+        pub fn xtb_newEnvironment() -> Environment;
+        pub fn xtb_delEnvironment(env: *mut Environment);
+        pub fn xtb_checkEnvironment(env: Environment) -> c_int;
+        pub fn xtb_getError(env: Environment, buffer: *mut c_char, buffersize: *const c_int);
+        pub fn xtb_setVerbosity(env: Environment, verbosity: c_int);
+
+        pub fn xtb_newMolecule(
+            env: Environment,
+            natoms: *const c_int,
+            numbers: *const c_int,
+            positions: *const c_double,
+            charge: *const c_double,
+            uhf: *const c_int,
+            lattice: *const c_double,
+            periodic: *const bool,
+        ) -> Molecule;
+        pub fn xtb_delMolecule(mol: *mut Molecule);
+        pub fn xtb_updateMolecule(
+            env: Environment,
+            mol: Molecule,
+            positions: *const c_double,
+            lattice: *const c_double,
+        );
+
+        pub fn xtb_newCalculator() -> Calculator;
+        pub fn xtb_delCalculator(calc: *mut Calculator);
+        pub fn xtb_loadGFN0xTB(
+            env: Environment,
+            mol: Molecule,
+            calc: Calculator,
+            file: *mut c_char,
+        );
+        pub fn xtb_loadGFN1xTB(
+            env: Environment,
+            mol: Molecule,
+            calc: Calculator,
+            file: *mut c_char,
+        );
+        pub fn xtb_loadGFN2xTB(
+            env: Environment,
+            mol: Molecule,
+            calc: Calculator,
+            file: *mut c_char,
+        );
+        pub fn xtb_loadGFNFF(env: Environment, mol: Molecule, calc: Calculator, file: *mut c_char);
+        pub fn xtb_setAccuracy(env: Environment, calc: Calculator, accuracy: c_double);
+        pub fn xtb_setMaxIter(env: Environment, calc: Calculator, maxiter: c_int);
+        pub fn xtb_setElectronicTemp(env: Environment, calc: Calculator, temperature: c_double);
+        pub fn xtb_singlepoint(env: Environment, mol: Molecule, calc: Calculator, res: Results);
+
+        pub fn xtb_newResults() -> Results;
+        pub fn xtb_delResults(res: *mut Results);
+        pub fn xtb_getEnergy(env: Environment, res: Results, energy: *mut c_double);
+        pub fn xtb_getGradient(env: Environment, res: Results, gradient: *mut c_double);
+    }
+}
+
+/// Element symbols by atomic number (1 to 86), the elements parametrized in
+/// GFN-xTB.
+const ELEMENTS: [&str; 86] = [
+    "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
+    "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
+    "Se", "Br", "Kr", "Rb", "Sr", "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd", "In",
+    "Sn", "Sb", "Te", "I", "Xe", "Cs", "Ba", "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb",
+    "Dy", "Ho", "Er", "Tm", "Yb", "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg", "Tl",
+    "Pb", "Bi", "Po", "At", "Rn",
+];
+
+/// Atomic number of an element symbol (case-insensitive).
+fn atomic_number(symbol: &str) -> Option<c_int> {
+    ELEMENTS.iter().position(|e| e.eq_ignore_ascii_case(symbol)).map(|idx| idx as c_int + 1)
+}
+
+/// Hamiltonian of xtb.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum XtbMethod {
+    Gfn0,
+    Gfn1,
+    #[default]
+    Gfn2,
+    GfnFF,
+}
+
+/// Handles of the xtb library, created at the first evaluation.
+struct XtbState {
+    env: ffi::Environment,
+    mol: ffi::Molecule,
+    calc: ffi::Calculator,
+    res: ffi::Results,
+}
+
+impl Drop for XtbState {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::xtb_delResults(&mut self.res);
+            ffi::xtb_delCalculator(&mut self.calc);
+            ffi::xtb_delMolecule(&mut self.mol);
+            ffi::xtb_delEnvironment(&mut self.env);
+        }
+    }
+}
+
+/// Driver computing GFN-xTB energies and gradients with the xtb library.
+///
+/// - `method`: Hamiltonian (default GFN2-xTB).
+/// - `accuracy`: Numerical accuracy of xtb (default 1.0; smaller is tighter).
+/// - `electronic_temperature`: Electronic temperature (K) of Fermi smearing,
+///   xtb default if `None`.
+/// - `max_iterations`: Maximum number of SCC iterations, xtb default if `None`.
+///
+/// Charge and multiplicity are taken from the molecule (see
+/// [`AtomData`]); ghost atoms, periodic systems and excited states are not
+/// supported.
+pub struct XtbDriver {
+    pub method: XtbMethod,
+    pub accuracy: f64,
+    pub electronic_temperature: Option<f64>,
+    pub max_iterations: Option<usize>,
+    atom_data: AtomData,
+    state: Option<XtbState>,
+}
+
+// The xtb handles are owned by the driver and only used through `&mut self`.
+unsafe impl Send for XtbDriver {}
+
+impl Default for XtbDriver {
+    fn default() -> Self {
+        XtbDriver::new(XtbMethod::default())
+    }
+}
+
+impl XtbDriver {
+    pub fn new(method: XtbMethod) -> Self {
+        XtbDriver {
+            method,
+            accuracy: 1.0,
+            electronic_temperature: None,
+            max_iterations: None,
+            atom_data: AtomData::default(),
+            state: None,
+        }
+    }
+
+    /// Numerical accuracy of xtb.
+    pub fn accuracy(mut self, accuracy: f64) -> Self {
+        self.accuracy = accuracy;
+        self
+    }
+
+    /// Electronic temperature (K).
+    pub fn electronic_temperature(mut self, temperature: f64) -> Self {
+        self.electronic_temperature = Some(temperature);
+        self
+    }
+
+    /// Maximum number of SCC iterations.
+    pub fn max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    /// Atomic numbers, charge and number of unpaired electrons of the molecule.
+    fn system(&self) -> Result<(Vec<c_int>, f64, c_int), DriverError> {
+        let atom_data = &self.atom_data;
+        if atom_data.ghost.iter().any(|&ghost| ghost) {
+            return Err(DriverError::CalculationFailed("xtb does not support ghost atoms".into()));
+        }
+        if atom_data.state != 0 {
+            return Err(DriverError::CalculationFailed(
+                "xtb only supports the ground state".into(),
+            ));
+        }
+        if atom_data.lattice.is_some() {
+            return Err(DriverError::CalculationFailed(
+                "Periodic systems are not supported by the xtb driver".into(),
+            ));
+        }
+        let numbers = atom_data
+            .elem
+            .iter()
+            .map(|symbol| {
+                atomic_number(symbol).ok_or_else(|| {
+                    DriverError::CalculationFailed(format!(
+                        "Element {} not supported by xtb",
+                        symbol
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let uhf = match atom_data.mult {
+            Some(mult) if mult < 1 => {
+                return Err(DriverError::CalculationFailed(format!(
+                    "Multiplicity must be at least 1, got {}",
+                    mult
+                )));
+            },
+            Some(mult) => mult - 1,
+            None => 0,
+        };
+        Ok((numbers, atom_data.charge.unwrap_or(0) as f64, uhf as c_int))
+    }
+
+    /// Create the xtb handles for coordinates `coords` (Bohr).
+    fn init_state(&self, coords: &[f64]) -> Result<XtbState, DriverError> {
+        let (numbers, charge, uhf) = self.system()?;
+        let natom = numbers.len() as c_int;
+        unsafe {
+            let env = ffi::xtb_newEnvironment();
+            ffi::xtb_setVerbosity(env, ffi::VERBOSITY_MUTED);
+            let mol = ffi::xtb_newMolecule(
+                env,
+                &natom,
+                numbers.as_ptr(),
+                coords.as_ptr(),
+                &charge,
+                &uhf,
+                ptr::null(),
+                ptr::null(),
+            );
+            let state =
+                XtbState { env, mol, calc: ffi::xtb_newCalculator(), res: ffi::xtb_newResults() };
+            check_error(env)?;
+            let load = match self.method {
+                XtbMethod::Gfn0 => ffi::xtb_loadGFN0xTB,
+                XtbMethod::Gfn1 => ffi::xtb_loadGFN1xTB,
+                XtbMethod::Gfn2 => ffi::xtb_loadGFN2xTB,
+                XtbMethod::GfnFF => ffi::xtb_loadGFNFF,
+            };
+            load(env, state.mol, state.calc, ptr::null_mut());
+            ffi::xtb_setAccuracy(env, state.calc, self.accuracy);
+            if let Some(temperature) = self.electronic_temperature {
+                ffi::xtb_setElectronicTemp(env, state.calc, temperature);
+            }
+            if let Some(max_iterations) = self.max_iterations {
+                ffi::xtb_setMaxIter(env, state.calc, max_iterations as c_int);
+            }
+            check_error(env)?;
+            Ok(state)
+        }
+    }
+}
+
+/// Error message of the xtb environment, if an error occurred.
+fn check_error(env: ffi::Environment) -> Result<(), DriverError> {
+    unsafe {
+        if ffi::xtb_checkEnvironment(env) == 0 {
+            return Ok(());
+        }
+        let mut buffer = vec![0 as c_char; 512];
+        let size = buffer.len() as c_int;
+        ffi::xtb_getError(env, buffer.as_mut_ptr(), &size);
+        let message = CStr::from_ptr(buffer.as_ptr()).to_string_lossy().into_owned();
+        Err(DriverError::CalculationFailed(format!("xtb: {}", message.trim())))
+    }
+}
+
+impl GeomDriverAPI for XtbDriver {
+    fn calc_new(&mut self, coords: &[f64], _dirname: &str) -> Result<GradOutput, DriverError> {
+        let natom = self.atom_data.elem.len();
+        if coords.len() != natom * 3 {
+            return Err(DriverError::CalculationFailed(format!(
+                "Length of coordinates ({}) does not match number of atoms ({}) * 3",
+                coords.len(),
+                natom
+            )));
+        }
+        let state = match self.state.take() {
+            Some(state) => {
+                unsafe {
+                    ffi::xtb_updateMolecule(state.env, state.mol, coords.as_ptr(), ptr::null())
+                };
+                state
+            },
+            None => self.init_state(coords)?,
+        };
+        let mut energy: c_double = 0.0;
+        let mut gradient = vec![0.0; coords.len()];
+        unsafe {
+            ffi::xtb_singlepoint(state.env, state.mol, state.calc, state.res);
+            check_error(state.env)?;
+            ffi::xtb_getEnergy(state.env, state.res, &mut energy);
+            ffi::xtb_getGradient(state.env, state.res, gradient.as_mut_ptr());
+            check_error(state.env)?;
+        }
+        self.state = Some(state);
+        Ok(GradOutput::new(energy, gradient))
+    }
+
+    fn set_atom_data(&mut self, atom_data: &AtomData) {
+        self.atom_data = atom_data.clone();
+        self.state = None;
+    }
+
+    fn finalize(&mut self) {
+        self.state = None;
+    }
+}